@@ -0,0 +1,171 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Credentials for fetching remote modules, from the `DENO_AUTH_TOKENS`
+//! environment variable.
+//!
+//! The variable holds a `;` separated list of `<credentials>@<host>` entries,
+//! e.g. `a1b2c3@deno.land;user:pass@localhost:8080`. Credentials of the form
+//! `<user>:<password>` are sent as basic auth, anything else as a bearer
+//! token. A token is only sent to the exact host (and port) it is given for,
+//! and never over plain `http:` once a download was redirected from `https:`.
+
+use deno_core::url::Url;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthTokenData {
+  Bearer(String),
+  Basic { username: String, password: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthToken {
+  host: String,
+  /// The port of the entry, if it has one. Entries without a port are only
+  /// sent to the default port of the scheme.
+  port: Option<u16>,
+  token: AuthTokenData,
+}
+
+/// Formats the token as the value of an `Authorization` header.
+impl fmt::Display for AuthToken {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.token {
+      AuthTokenData::Bearer(token) => write!(f, "Bearer {}", token),
+      AuthTokenData::Basic { username, password } => {
+        let credentials = format!("{}:{}", username, password);
+        write!(f, "Basic {}", base64::encode(credentials))
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuthTokens(Vec<AuthToken>);
+
+impl AuthTokens {
+  /// Parses the value of `DENO_AUTH_TOKENS`. Badly formed entries are
+  /// discarded with a warning.
+  pub fn new(maybe_tokens_str: Option<String>) -> Self {
+    let mut tokens = Vec::new();
+    if let Some(tokens_str) = maybe_tokens_str {
+      for token_str in tokens_str.split(';').filter(|s| !s.trim().is_empty()) {
+        match parse_token(token_str.trim()) {
+          Some(token) => tokens.push(token),
+          None => eprintln!(
+            "{} Badly formed entry in DENO_AUTH_TOKENS discarded",
+            crate::colors::yellow("Warning")
+          ),
+        }
+      }
+    }
+    Self(tokens)
+  }
+
+  /// Returns the token for the host of `url`, if there is one. When the
+  /// download of `url` was redirected from an `https:` URL, a token is only
+  /// returned for `https:`, as it would otherwise be sent unencrypted.
+  pub fn get(
+    &self,
+    url: &Url,
+    redirected_from_https: bool,
+  ) -> Option<AuthToken> {
+    if redirected_from_https && url.scheme() != "https" {
+      return None;
+    }
+    let host = url.host_str()?;
+    self
+      .0
+      .iter()
+      .find(|token| {
+        token.host == host
+          && match token.port {
+            Some(port) => url.port_or_known_default() == Some(port),
+            None => url.port().is_none(),
+          }
+      })
+      .cloned()
+  }
+}
+
+fn parse_token(token_str: &str) -> Option<AuthToken> {
+  let at = token_str.rfind('@')?;
+  let (credentials, host) = (&token_str[..at], &token_str[at + 1..]);
+  if credentials.is_empty() || host.is_empty() {
+    return None;
+  }
+  let token = match credentials.find(':') {
+    Some(colon) => AuthTokenData::Basic {
+      username: credentials[..colon].to_string(),
+      password: credentials[colon + 1..].to_string(),
+    },
+    None => AuthTokenData::Bearer(credentials.to_string()),
+  };
+  // The colons of an IPv6 address are within brackets.
+  let (host, port) = match host.rfind(':') {
+    Some(colon) if !host.ends_with(']') => {
+      (&host[..colon], Some(host[colon + 1..].parse().ok()?))
+    }
+    _ => (host, None),
+  };
+  Some(AuthToken {
+    host: host.to_lowercase(),
+    port,
+    token,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_auth_tokens() {
+    let auth_tokens = AuthTokens::new(Some(
+      "abc123@deno.land;user:pa:ss@localhost:4545;bad;@host;x@host:port"
+        .to_string(),
+    ));
+    assert_eq!(auth_tokens.0.len(), 2);
+
+    let url = Url::parse("https://deno.land/x/mod.ts").unwrap();
+    assert_eq!(
+      auth_tokens.get(&url, false).unwrap().to_string(),
+      "Bearer abc123"
+    );
+    let url = Url::parse("http://localhost:4545/mod.ts").unwrap();
+    assert_eq!(
+      auth_tokens.get(&url, false).unwrap().to_string(),
+      format!("Basic {}", base64::encode("user:pa:ss"))
+    );
+
+    // Neither other hosts, subdomains nor other ports get the tokens.
+    let url = Url::parse("https://example.com/deno.land/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, false).is_none());
+    let url = Url::parse("https://x.deno.land/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, false).is_none());
+    let url = Url::parse("https://deno.land:8443/x/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, false).is_none());
+    let url = Url::parse("http://localhost:4546/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, false).is_none());
+  }
+
+  #[test]
+  fn test_auth_tokens_default_port() {
+    let auth_tokens = AuthTokens::new(Some("abc123@deno.land:443".to_string()));
+    let url = Url::parse("https://deno.land/x/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, false).is_some());
+    let url = Url::parse("http://deno.land/x/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, false).is_none());
+  }
+
+  #[test]
+  fn test_auth_tokens_https_downgrade() {
+    let auth_tokens = AuthTokens::new(Some("abc123@deno.land".to_string()));
+    let url = Url::parse("https://deno.land/x/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, true).is_some());
+    // The token would be sent unencrypted after a redirect from `https:`.
+    let url = Url::parse("http://deno.land/x/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url, true).is_none());
+    assert!(auth_tokens.get(&url, false).is_some());
+  }
+}
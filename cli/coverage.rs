@@ -1,20 +1,47 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::checksum;
 use crate::colors;
 use crate::inspector::InspectorSession;
+use crate::permissions::Permissions;
+use crate::program_state::ProgramState;
+use crate::worker::MainWorker;
+use deno_core::error::generic_error;
 use deno_core::error::AnyError;
 use deno_core::serde_json;
 use deno_core::serde_json::json;
 use deno_core::url::Url;
+use deno_core::ModuleSpecifier;
+use regex::Regex;
 use serde::Deserialize;
+use serde::Serialize;
+use sourcemap::SourceMap;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
 
+/// Collects the precise coverage of an isolate through an inspector session
+/// and writes it as a profile into a directory, from which it can be reported
+/// on by `deno coverage`.
 pub struct CoverageCollector {
+  dir: PathBuf,
   session: Box<InspectorSession>,
+  program_state: Arc<ProgramState>,
 }
 
 impl CoverageCollector {
-  pub fn new(session: Box<InspectorSession>) -> Self {
-    Self { session }
+  pub fn new(
+    dir: PathBuf,
+    session: Box<InspectorSession>,
+    program_state: Arc<ProgramState>,
+  ) -> Self {
+    Self {
+      dir,
+      session,
+      program_state,
+    }
   }
 
   pub async fn start_collecting(&mut self) -> Result<(), AnyError> {
@@ -33,7 +60,7 @@ impl CoverageCollector {
     Ok(())
   }
 
-  pub async fn collect(&mut self) -> Result<Vec<Coverage>, AnyError> {
+  async fn collect(&mut self) -> Result<Vec<Coverage>, AnyError> {
     let result = self
       .session
       .post_message("Profiler.takePreciseCoverage", None)
@@ -57,16 +84,27 @@ impl CoverageCollector {
       let get_script_source_result: GetScriptSourceResult =
         serde_json::from_value(result)?;
 
+      let source_hash = source_hash(&self.program_state, &script_coverage.url);
       coverages.push(Coverage {
         script_coverage,
         script_source: get_script_source_result.script_source,
+        source_hash,
       })
     }
 
     Ok(coverages)
   }
 
+  /// Take the coverage of the isolate and write it into a new profile in
+  /// the coverage directory.
   pub async fn stop_collecting(&mut self) -> Result<(), AnyError> {
+    let coverages = self.collect().await?;
+    fs::create_dir_all(&self.dir)?;
+    // Every isolate writes its own profile, so that the profiles of several
+    // runs and workers can be collected into the same directory.
+    let profile_path = self.dir.join(format!("{}.json", Uuid::new_v4()));
+    fs::write(profile_path, serde_json::to_string(&coverages)?)?;
+
     self
       .session
       .post_message("Profiler.stopPreciseCoverage", None)
@@ -78,7 +116,24 @@ impl CoverageCollector {
   }
 }
 
-#[derive(Debug, Deserialize)]
+/// Starts collecting the coverage of the worker if a coverage directory was
+/// given with `--coverage`.
+pub async fn start_coverage_collector(
+  program_state: &Arc<ProgramState>,
+  worker: &mut MainWorker,
+) -> Result<Option<CoverageCollector>, AnyError> {
+  let coverage_dir = match &program_state.flags.coverage_dir {
+    Some(coverage_dir) => PathBuf::from(coverage_dir),
+    None => return Ok(None),
+  };
+  let session = worker.create_inspector_session();
+  let mut coverage_collector =
+    CoverageCollector::new(coverage_dir, session, program_state.clone());
+  coverage_collector.start_collecting().await?;
+  Ok(Some(coverage_collector))
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CoverageRange {
   pub start_offset: usize,
@@ -86,7 +141,7 @@ pub struct CoverageRange {
   pub count: usize,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FunctionCoverage {
   pub function_name: String,
@@ -94,7 +149,7 @@ pub struct FunctionCoverage {
   pub is_block_coverage: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScriptCoverage {
   pub script_id: String,
@@ -102,11 +157,25 @@ pub struct ScriptCoverage {
   pub functions: Vec<FunctionCoverage>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Coverage {
   pub script_coverage: ScriptCoverage,
   pub script_source: String,
+  /// Hash of the source of the module when the coverage was collected, which
+  /// differs from the script source when the module was emitted.
+  #[serde(default)]
+  pub source_hash: Option<String>,
+}
+
+/// Returns the hash of the source of a module as loaded by `program_state`,
+/// or `None` for scripts that weren't loaded as a module.
+pub fn source_hash(program_state: &ProgramState, url: &str) -> Option<String> {
+  let specifier = ModuleSpecifier::resolve_url(url).ok()?;
+  let source_file = program_state
+    .file_fetcher
+    .fetch_cached_source_file(&specifier, Permissions::allow_all())?;
+  Some(checksum::gen(&[source_file.source_code.as_bytes()]))
 }
 
 #[derive(Debug, Deserialize)]
@@ -122,117 +191,1021 @@ pub struct GetScriptSourceResult {
   pub bytecode: Option<String>,
 }
 
-pub struct PrettyCoverageReporter {
-  quiet: bool,
+/// Number of calls of a function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCount {
+  pub name: String,
+  /// Zero based index of the line on which the function starts.
+  pub line_index: usize,
+  pub count: usize,
 }
 
-// TODO(caspervonb) add support for lcov output (see geninfo(1) for format spec).
-impl PrettyCoverageReporter {
-  pub fn new(quiet: bool) -> PrettyCoverageReporter {
-    PrettyCoverageReporter { quiet }
-  }
-
-  pub fn visit_coverage(&mut self, coverage: &Coverage) {
-    let lines = coverage.script_source.lines().collect::<Vec<_>>();
+/// Execution count of a branch.  V8 reports a block range for the code of
+/// every branch of a conditional, loop or other control flow which was taken
+/// a different number of times than its surrounding code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchCount {
+  /// Zero based index of the line on which the branch starts.
+  pub line_index: usize,
+  /// Index of the function which contains the branch.
+  pub block_number: usize,
+  /// Index of the branch within its function.
+  pub branch_number: usize,
+  pub count: usize,
+}
 
-    let mut covered_lines: Vec<usize> = Vec::new();
-    let mut uncovered_lines: Vec<usize> = Vec::new();
+/// Coverage of a single module, with the execution counts of the V8 script
+/// mapped back to the original source.
+#[derive(Debug)]
+pub struct FileCoverage {
+  pub url: String,
+  /// The lines of the original source.
+  pub lines: Vec<String>,
+  /// Execution counts of the lines which contain code, keyed by their zero
+  /// based index.
+  pub line_counts: BTreeMap<usize, usize>,
+  pub functions: Vec<FunctionCount>,
+  pub branches: Vec<BranchCount>,
+}
 
-    let mut line_start_offset = 0;
-    for (index, line) in lines.iter().enumerate() {
-      let line_end_offset = line_start_offset + line.len();
+impl FileCoverage {
+  /// Compute the coverage of a module from one or more profiles of the same
+  /// script, whose execution counts are summed up.  When the executed script
+  /// was emitted from another source, like TypeScript, the source map of the
+  /// emit and the original source are used to report on the original source.
+  pub fn new(
+    coverages: &[Coverage],
+    maybe_source_map: Option<Vec<u8>>,
+    maybe_original_source: Option<String>,
+  ) -> Self {
+    let script_source = &coverages[0].script_source;
+    let mut script_line_counts: Vec<Option<usize>> = Vec::new();
+    // Functions keyed by their start offset and name, and branches keyed by
+    // the start offset of their function and their own range.
+    let mut function_counts: BTreeMap<(usize, String), usize> = BTreeMap::new();
+    let mut branch_counts: BTreeMap<(usize, usize, usize), usize> =
+      BTreeMap::new();
+    for coverage in coverages {
+      let line_counts =
+        script_line_counts(&coverage.script_coverage, script_source);
+      if script_line_counts.is_empty() {
+        script_line_counts = line_counts;
+      } else {
+        for (merged, count) in script_line_counts.iter_mut().zip(line_counts) {
+          *merged = match (*merged, count) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+          };
+        }
+      }
 
-      let mut count = 0;
       for function in &coverage.script_coverage.functions {
-        for range in &function.ranges {
-          if range.start_offset <= line_start_offset
-            && range.end_offset >= line_end_offset
-          {
-            if range.count == 0 {
-              count = 0;
-              break;
-            }
-
-            count += range.count;
+        let function_range = match function.ranges.first() {
+          Some(range) => range,
+          None => continue,
+        };
+        // The top level code of a script is reported as an anonymous
+        // function spanning the whole script.
+        let is_top_level =
+          function_range.start_offset == 0 && function.function_name.is_empty();
+        if !is_top_level {
+          *function_counts
+            .entry((
+              function_range.start_offset,
+              function.function_name.clone(),
+            ))
+            .or_default() += function_range.count;
+        }
+        for range in function.ranges.iter().skip(1) {
+          *branch_counts
+            .entry((
+              function_range.start_offset,
+              range.start_offset,
+              range.end_offset,
+            ))
+            .or_default() += range.count;
+        }
+      }
+    }
+
+    let maybe_source_map = match maybe_original_source {
+      Some(_) => inline_source_map(script_source)
+        .or(maybe_source_map)
+        .and_then(|source_map| SourceMap::from_slice(&source_map).ok()),
+      None => None,
+    };
+    let source = match (&maybe_source_map, maybe_original_source) {
+      (Some(_), Some(original_source)) => original_source,
+      _ => script_source.clone(),
+    };
+
+    let line_counts = match &maybe_source_map {
+      Some(source_map) => {
+        let mut line_counts = BTreeMap::new();
+        for token in source_map.tokens() {
+          let dst_line = token.get_dst_line() as usize;
+          if let Some(Some(count)) = script_line_counts.get(dst_line) {
+            // A line of the original source is only covered when all of the
+            // code emitted for it was executed.
+            line_counts
+              .entry(token.get_src_line() as usize)
+              .and_modify(|c: &mut usize| *c = (*c).min(*count))
+              .or_insert(*count);
           }
         }
+        line_counts
+      }
+      None => script_line_counts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, count)| count.map(|count| (index, count)))
+        .collect(),
+    };
 
-        line_start_offset = line_end_offset;
+    // Map an offset of the script to the index of a line in the source.
+    let line_start_offsets = line_start_offsets(script_source);
+    let to_line_index = |offset: usize| -> Option<usize> {
+      let line = match line_start_offsets.binary_search(&offset) {
+        Ok(line) => line,
+        Err(line) => line - 1,
+      };
+      let column = offset - line_start_offsets[line];
+      match &maybe_source_map {
+        Some(source_map) => source_map
+          .lookup_token(line as u32, column as u32)
+          .map(|token| token.get_src_line() as usize),
+        None => Some(line),
       }
-      if count > 0 {
-        covered_lines.push(index);
+    };
+
+    let mut functions = Vec::new();
+    let mut anonymous_functions = 0;
+    for ((start_offset, name), count) in function_counts {
+      let name = if name.is_empty() {
+        anonymous_functions += 1;
+        format!("(anonymous_{})", anonymous_functions)
       } else {
-        uncovered_lines.push(index);
+        name
+      };
+      if let Some(line_index) = to_line_index(start_offset) {
+        functions.push(FunctionCount {
+          name,
+          line_index,
+          count,
+        });
+      }
+    }
+
+    let mut branches = Vec::new();
+    let mut block_number = 0;
+    let mut branch_number = 0;
+    let mut maybe_function_offset = None;
+    for ((function_offset, start_offset, _), count) in branch_counts {
+      if maybe_function_offset != Some(function_offset) {
+        if maybe_function_offset.is_some() {
+          block_number += 1;
+        }
+        branch_number = 0;
+        maybe_function_offset = Some(function_offset);
       }
+      if let Some(line_index) = to_line_index(start_offset) {
+        branches.push(BranchCount {
+          line_index,
+          block_number,
+          branch_number,
+          count,
+        });
+      }
+      branch_number += 1;
     }
 
-    if !self.quiet {
-      print!("cover {} ... ", coverage.script_coverage.url);
+    FileCoverage {
+      url: coverages[0].script_coverage.url.clone(),
+      lines: source.split('\n').map(String::from).collect(),
+      line_counts,
+      functions,
+      branches,
+    }
+  }
 
-      let line_coverage_ratio = covered_lines.len() as f32 / lines.len() as f32;
-      let line_coverage = format!(
-        "{:.3}% ({}/{})",
-        line_coverage_ratio * 100.0,
-        covered_lines.len(),
-        lines.len()
-      );
+  pub fn summary(&self) -> CoverageSummary {
+    CoverageSummary {
+      lines_found: self.line_counts.len(),
+      lines_hit: self.line_counts.values().filter(|c| **c > 0).count(),
+      branches_found: self.branches.len(),
+      branches_hit: self.branches.iter().filter(|b| b.count > 0).count(),
+      functions_found: self.functions.len(),
+      functions_hit: self.functions.iter().filter(|f| f.count > 0).count(),
+    }
+  }
+}
 
-      if line_coverage_ratio >= 0.9 {
-        println!("{}", colors::green(&line_coverage));
-      } else if line_coverage_ratio >= 0.75 {
-        println!("{}", colors::yellow(&line_coverage));
-      } else {
-        println!("{}", colors::red(&line_coverage));
-      }
+/// The number of lines, branches and functions found and hit in one or more
+/// modules.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CoverageSummary {
+  pub lines_found: usize,
+  pub lines_hit: usize,
+  pub branches_found: usize,
+  pub branches_hit: usize,
+  pub functions_found: usize,
+  pub functions_hit: usize,
+}
+
+impl CoverageSummary {
+  pub fn add(&mut self, other: &CoverageSummary) {
+    self.lines_found += other.lines_found;
+    self.lines_hit += other.lines_hit;
+    self.branches_found += other.branches_found;
+    self.branches_hit += other.branches_hit;
+    self.functions_found += other.functions_found;
+    self.functions_hit += other.functions_hit;
+  }
 
-      for line_index in uncovered_lines {
+  /// The ratios of lines, branches and functions that were hit, with their
+  /// names.
+  fn ratios(&self) -> [(&'static str, f32); 3] {
+    [
+      ("lines", coverage_ratio(self.lines_hit, self.lines_found)),
+      (
+        "branches",
+        coverage_ratio(self.branches_hit, self.branches_found),
+      ),
+      (
+        "functions",
+        coverage_ratio(self.functions_hit, self.functions_found),
+      ),
+    ]
+  }
+
+  /// Fails if the coverage of lines, branches or functions is below the
+  /// supplied percentage, naming every metric which is.
+  pub fn check_threshold(&self, threshold: f32) -> Result<(), AnyError> {
+    let failures: Vec<String> = self
+      .ratios()
+      .iter()
+      .map(|(name, ratio)| (name, ratio * 100.0))
+      .filter(|(_, percentage)| *percentage < threshold)
+      .map(|(name, percentage)| format!("{} ({:.3}%)", name, percentage))
+      .collect();
+    if failures.is_empty() {
+      return Ok(());
+    }
+    Err(generic_error(format!(
+      "Coverage is below the threshold of {}%: {}",
+      threshold,
+      failures.join(", ")
+    )))
+  }
+}
+
+/// Returns the UTF-16 offsets at which the lines of a script start.
+fn line_start_offsets(script_source: &str) -> Vec<usize> {
+  let mut offsets = vec![0];
+  let mut offset = 0;
+  for c in script_source.chars() {
+    offset += c.len_utf16();
+    if c == '\n' {
+      offsets.push(offset);
+    }
+  }
+  offsets
+}
+
+/// Returns the execution count of every line of a script, or `None` for
+/// lines without code.  The count of a line is the count of the innermost
+/// range which contains it, unless part of the line falls into a range that
+/// was never executed.  Offsets of V8 are in UTF-16 code units.
+fn script_line_counts(
+  script_coverage: &ScriptCoverage,
+  script_source: &str,
+) -> Vec<Option<usize>> {
+  let ranges: Vec<&CoverageRange> = script_coverage
+    .functions
+    .iter()
+    .flat_map(|function| function.ranges.iter())
+    .collect();
+
+  let mut line_counts = Vec::new();
+  let mut line_start_offset = 0;
+  for line in script_source.split('\n') {
+    let line_end_offset = line_start_offset + line.encode_utf16().count();
+    let leading = line.len() - line.trim_start().len();
+    let trailing = line.len() - line.trim_end().len();
+    if leading == line.len() {
+      line_counts.push(None);
+    } else {
+      let code_start_offset =
+        line_start_offset + line[..leading].encode_utf16().count();
+      let code_end_offset =
+        line_end_offset - line[line.len() - trailing..].encode_utf16().count();
+
+      let innermost = ranges
+        .iter()
+        .filter(|range| {
+          range.start_offset <= code_start_offset
+            && range.end_offset >= code_end_offset
+        })
+        .min_by_key(|range| range.end_offset - range.start_offset);
+      let uncovered = ranges.iter().any(|range| {
+        range.count == 0
+          && range.start_offset < code_end_offset
+          && range.end_offset > code_start_offset
+      });
+      line_counts.push(match innermost {
+        Some(_) if uncovered => Some(0),
+        Some(range) => Some(range.count),
+        None => None,
+      });
+    }
+    // Account for the line feed.
+    line_start_offset = line_end_offset + 1;
+  }
+
+  line_counts
+}
+
+fn coverage_ratio(hit: usize, found: usize) -> f32 {
+  if found == 0 {
+    1.0
+  } else {
+    hit as f32 / found as f32
+  }
+}
+
+pub trait CoverageReporter {
+  fn visit_coverage(
+    &mut self,
+    file_coverage: &FileCoverage,
+  ) -> Result<(), AnyError>;
+
+  /// Called after all modules were visited, to write out the report.
+  fn done(&mut self) -> Result<(), AnyError>;
+}
+
+/// Create the reporter for the value of `--reporter`.  Reporters which write
+/// files use `maybe_output` if given, or a default location in the
+/// `coverage` directory.
+pub fn create_reporter(
+  kind: &str,
+  quiet: bool,
+  maybe_output: Option<PathBuf>,
+) -> Box<dyn CoverageReporter> {
+  match kind {
+    "lcov" => Box::new(LcovCoverageReporter::new(
+      maybe_output.unwrap_or_else(|| PathBuf::from("coverage/lcov.info")),
+    )),
+    "html" => Box::new(HtmlCoverageReporter::new(
+      maybe_output.unwrap_or_else(|| PathBuf::from("coverage/html")),
+    )),
+    _ => Box::new(PrettyCoverageReporter::new(quiet)),
+  }
+}
+
+fn format_coverage(hit: usize, found: usize) -> String {
+  let ratio = coverage_ratio(hit, found);
+  let coverage = format!("{:.3}% ({}/{})", ratio * 100.0, hit, found);
+  if ratio >= 0.9 {
+    colors::green(&coverage).to_string()
+  } else if ratio >= 0.75 {
+    colors::yellow(&coverage).to_string()
+  } else {
+    colors::red(&coverage).to_string()
+  }
+}
+
+pub struct PrettyCoverageReporter {
+  quiet: bool,
+  summary: CoverageSummary,
+}
+
+impl PrettyCoverageReporter {
+  pub fn new(quiet: bool) -> PrettyCoverageReporter {
+    PrettyCoverageReporter {
+      quiet,
+      summary: CoverageSummary::default(),
+    }
+  }
+}
+
+impl CoverageReporter for PrettyCoverageReporter {
+  fn visit_coverage(
+    &mut self,
+    file_coverage: &FileCoverage,
+  ) -> Result<(), AnyError> {
+    let summary = file_coverage.summary();
+    self.summary.add(&summary);
+    if self.quiet {
+      return Ok(());
+    }
+
+    println!(
+      "cover {} ... {}",
+      file_coverage.url,
+      format_coverage(summary.lines_hit, summary.lines_found)
+    );
+
+    for (line_index, count) in &file_coverage.line_counts {
+      if *count == 0 {
+        let line = file_coverage
+          .lines
+          .get(*line_index)
+          .map_or("", String::as_str);
         println!(
           "{:width$}{} {}",
           line_index + 1,
           colors::gray(" |"),
-          colors::red(&lines[line_index]),
+          colors::red(line),
           width = 4
         );
       }
     }
+    Ok(())
+  }
+
+  fn done(&mut self) -> Result<(), AnyError> {
+    if self.quiet {
+      return Ok(());
+    }
+
+    let summary = &self.summary;
+    println!();
+    println!(
+      "lines     {}",
+      format_coverage(summary.lines_hit, summary.lines_found)
+    );
+    println!(
+      "branches  {}",
+      format_coverage(summary.branches_hit, summary.branches_found)
+    );
+    println!(
+      "functions {}",
+      format_coverage(summary.functions_hit, summary.functions_found)
+    );
+    Ok(())
+  }
+}
+
+/// Writes the coverage in the tracefile format of lcov, see geninfo(1).
+pub struct LcovCoverageReporter {
+  output: PathBuf,
+  records: String,
+}
+
+impl LcovCoverageReporter {
+  pub fn new(output: PathBuf) -> LcovCoverageReporter {
+    LcovCoverageReporter {
+      output,
+      records: String::new(),
+    }
   }
 }
 
-pub fn filter_script_coverages(
-  coverages: Vec<Coverage>,
-  test_file_url: Url,
-  test_modules: Vec<Url>,
-) -> Vec<Coverage> {
-  coverages
-    .into_iter()
-    .filter(|e| {
-      if let Ok(url) = Url::parse(&e.script_coverage.url) {
-        if url.path().ends_with("__anonymous__") {
-          return false;
-        }
+impl CoverageReporter for LcovCoverageReporter {
+  fn visit_coverage(
+    &mut self,
+    file_coverage: &FileCoverage,
+  ) -> Result<(), AnyError> {
+    // lcov expects paths for local files.
+    let source_file = match Url::parse(&file_coverage.url)
+      .ok()
+      .and_then(|url| url.to_file_path().ok())
+    {
+      Some(path) => path.to_string_lossy().to_string(),
+      None => file_coverage.url.clone(),
+    };
+    let summary = file_coverage.summary();
+    let records = &mut self.records;
 
-        if url == test_file_url {
-          return false;
-        }
+    records.push_str("TN:\n");
+    records.push_str(&format!("SF:{}\n", source_file));
 
-        for test_module_url in &test_modules {
-          if &url == test_module_url {
-            return false;
-          }
+    for function in &file_coverage.functions {
+      records.push_str(&format!(
+        "FN:{},{}\n",
+        function.line_index + 1,
+        function.name
+      ));
+    }
+    for function in &file_coverage.functions {
+      records.push_str(&format!("FNDA:{},{}\n", function.count, function.name));
+    }
+    records.push_str(&format!("FNF:{}\n", summary.functions_found));
+    records.push_str(&format!("FNH:{}\n", summary.functions_hit));
+
+    for branch in &file_coverage.branches {
+      records.push_str(&format!(
+        "BRDA:{},{},{},{}\n",
+        branch.line_index + 1,
+        branch.block_number,
+        branch.branch_number,
+        branch.count
+      ));
+    }
+    records.push_str(&format!("BRF:{}\n", summary.branches_found));
+    records.push_str(&format!("BRH:{}\n", summary.branches_hit));
+
+    for (line_index, count) in &file_coverage.line_counts {
+      records.push_str(&format!("DA:{},{}\n", line_index + 1, count));
+    }
+    records.push_str(&format!("LF:{}\n", summary.lines_found));
+    records.push_str(&format!("LH:{}\n", summary.lines_hit));
+    records.push_str("end_of_record\n");
+    Ok(())
+  }
+
+  fn done(&mut self) -> Result<(), AnyError> {
+    if let Some(parent) = self.output.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    fs::write(&self.output, &self.records)?;
+    Ok(())
+  }
+}
+
+/// Writes a static HTML report into a directory, with an index of all
+/// modules and a page per module on which the lines are highlighted by
+/// whether they were executed.
+pub struct HtmlCoverageReporter {
+  output: PathBuf,
+  /// File name of the page, URL and summary of every module.
+  summaries: Vec<(String, String, CoverageSummary)>,
+}
+
+impl HtmlCoverageReporter {
+  pub fn new(output: PathBuf) -> HtmlCoverageReporter {
+    HtmlCoverageReporter {
+      output,
+      summaries: Vec::new(),
+    }
+  }
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+td, th { padding: 0 8px; text-align: left; }
+pre { margin: 0; }
+.line-number { color: #888; text-align: right; user-select: none; }
+.count { color: #888; text-align: right; }
+.covered { background-color: #dfd; }
+.partial { background-color: #ffd; }
+.uncovered { background-color: #fdd; }";
+
+fn html_page(title: &str, body: &str) -> String {
+  format!(
+    "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+{style}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{body}
+</body>
+</html>
+",
+    title = escape_html(title),
+    style = HTML_STYLE,
+    body = body
+  )
+}
+
+fn escape_html(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// Table cells with the coverage of lines, branches and functions.
+fn html_summary_cells(summary: &CoverageSummary) -> String {
+  let mut cells = String::new();
+  for (hit, found) in &[
+    (summary.lines_hit, summary.lines_found),
+    (summary.branches_hit, summary.branches_found),
+    (summary.functions_hit, summary.functions_found),
+  ] {
+    let ratio = coverage_ratio(*hit, *found);
+    cells.push_str(&format!(
+      "<td class=\"{}\">{:.3}% ({}/{})</td>",
+      if ratio >= 0.9 { "covered" } else { "uncovered" },
+      ratio * 100.0,
+      hit,
+      found
+    ));
+  }
+  cells
+}
+
+impl CoverageReporter for HtmlCoverageReporter {
+  fn visit_coverage(
+    &mut self,
+    file_coverage: &FileCoverage,
+  ) -> Result<(), AnyError> {
+    let file_name = format!(
+      "{}.html",
+      file_coverage
+        .url
+        .replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "_")
+    );
+    let summary = file_coverage.summary();
+
+    let mut functions = String::new();
+    for function in &file_coverage.functions {
+      functions.push_str(&format!(
+        "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+        if function.count > 0 {
+          "covered"
+        } else {
+          "uncovered"
+        },
+        escape_html(&function.name),
+        function.line_index + 1,
+        function.count
+      ));
+    }
+
+    let mut rows = String::new();
+    for (index, line) in file_coverage.lines.iter().enumerate() {
+      let branches: Vec<&BranchCount> = file_coverage
+        .branches
+        .iter()
+        .filter(|branch| branch.line_index == index)
+        .collect();
+      let branches_hit = branches.iter().filter(|b| b.count > 0).count();
+      let branches_cell = if branches.is_empty() {
+        String::new()
+      } else {
+        format!("{}/{}", branches_hit, branches.len())
+      };
+      let (class, count) = match file_coverage.line_counts.get(&index) {
+        Some(0) => ("uncovered", "0".to_string()),
+        Some(count) if branches_hit < branches.len() => {
+          ("partial", format!("{}x", count))
         }
+        Some(count) => ("covered", format!("{}x", count)),
+        None => ("", String::new()),
+      };
+      rows.push_str(&format!(
+        "<tr class=\"{}\"><td class=\"line-number\">{}</td><td class=\"count\">{}</td><td class=\"count\">{}</td><td><pre>{}</pre></td></tr>\n",
+        class,
+        index + 1,
+        count,
+        branches_cell,
+        escape_html(line)
+      ));
+    }
+    let body = format!(
+      "<p><a href=\"index.html\">All files</a></p>
+<table>
+<tr><th>Lines</th><th>Branches</th><th>Functions</th></tr>
+<tr>{}</tr>
+</table>
+<h2>Functions</h2>
+<table>
+<tr><th>Function</th><th>Line</th><th>Calls</th></tr>
+{}</table>
+<h2>Source</h2>
+<table>
+<tr><th></th><th>Hits</th><th>Branches</th><th></th></tr>
+{}</table>",
+      html_summary_cells(&summary),
+      functions,
+      rows
+    );
+    let page = html_page(&file_coverage.url, &body);
 
-        if let Ok(path) = url.to_file_path() {
-          for test_module_url in &test_modules {
-            if let Ok(test_module_path) = test_module_url.to_file_path() {
-              if path.starts_with(test_module_path.parent().unwrap()) {
-                return true;
-              }
-            }
-          }
+    // Pages are written right away, so only the summary is kept in memory.
+    fs::create_dir_all(&self.output)?;
+    fs::write(self.output.join(&file_name), page)?;
+
+    self
+      .summaries
+      .push((file_name, file_coverage.url.clone(), summary));
+    Ok(())
+  }
+
+  fn done(&mut self) -> Result<(), AnyError> {
+    let mut rows = String::new();
+    let mut total = CoverageSummary::default();
+    for (file_name, url, summary) in &self.summaries {
+      total.add(summary);
+      rows.push_str(&format!(
+        "<tr><td><a href=\"{}\">{}</a></td>{}</tr>\n",
+        escape_html(file_name),
+        escape_html(url),
+        html_summary_cells(summary)
+      ));
+    }
+    let body = format!(
+      "<table>
+<tr><th>File</th><th>Lines</th><th>Branches</th><th>Functions</th></tr>
+<tr><td>All files</td>{}</tr>
+{}</table>",
+      html_summary_cells(&total),
+      rows
+    );
+
+    fs::create_dir_all(&self.output)?;
+    fs::write(
+      self.output.join("index.html"),
+      html_page("Coverage report", &body),
+    )?;
+    Ok(())
+  }
+}
+
+const SOURCE_MAP_PREFIX: &str =
+  "//# sourceMappingURL=data:application/json;base64,";
+
+/// Returns the inline source map of an emitted script, which makes profiles
+/// independent from the emit cache of the run which collected them.
+fn inline_source_map(script_source: &str) -> Option<Vec<u8>> {
+  let last_line = script_source.trim_end().rsplit('\n').next()?;
+  if last_line.starts_with(SOURCE_MAP_PREFIX) {
+    base64::decode(last_line.trim_start_matches(SOURCE_MAP_PREFIX)).ok()
+  } else {
+    None
+  }
+}
+
+/// Read the profiles written by `CoverageCollector`.  `files` are either
+/// coverage directories or individual profiles.
+pub fn read_profiles(files: &[PathBuf]) -> Result<Vec<Coverage>, AnyError> {
+  let mut profile_paths = Vec::new();
+  for file in files {
+    if file.is_dir() {
+      for entry in fs::read_dir(file)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |ext| ext == "json") {
+          profile_paths.push(path);
         }
       }
+    } else {
+      profile_paths.push(file.clone());
+    }
+  }
+  // Read the profiles in a stable order, regardless of the file system.
+  profile_paths.sort();
+
+  let mut coverages = Vec::new();
+  for profile_path in profile_paths {
+    let profile = fs::read_to_string(&profile_path)?;
+    let profile: Vec<Coverage> =
+      serde_json::from_str(&profile).map_err(|err| {
+        generic_error(format!(
+          "Invalid coverage profile {}: {}",
+          profile_path.display(),
+          err
+        ))
+      })?;
+    coverages.extend(profile);
+  }
+  Ok(coverages)
+}
+
+/// Keep the coverages of scripts whose URL matches any of the `include`
+/// regexes and none of the `exclude` regexes, and group them by URL.
+/// Coverages of a script whose source changed between runs can't be merged,
+/// only those matching the first source that was read are kept.
+pub fn filter_coverages(
+  coverages: Vec<Coverage>,
+  include: Vec<String>,
+  exclude: Vec<String>,
+) -> Result<Vec<Vec<Coverage>>, AnyError> {
+  let include = include
+    .iter()
+    .map(|e| Regex::new(e))
+    .collect::<Result<Vec<_>, _>>()?;
+  let exclude = exclude
+    .iter()
+    .map(|e| Regex::new(e))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let mut grouped: BTreeMap<String, Vec<Coverage>> = BTreeMap::new();
+  for coverage in coverages {
+    let url = &coverage.script_coverage.url;
+    // Scripts which were evaluated or generated by the test runner.
+    if url.is_empty()
+      || url.ends_with("__anonymous__")
+      || url.rsplit('/').next().unwrap().starts_with("$deno$test")
+    {
+      continue;
+    }
+    let is_included = include.iter().any(|e| e.is_match(url));
+    let is_excluded = exclude.iter().any(|e| e.is_match(url));
+    if !is_included || is_excluded {
+      continue;
+    }
 
-      false
-    })
-    .collect::<Vec<Coverage>>()
+    let group = grouped.entry(url.clone()).or_default();
+    let source_changed = group
+      .first()
+      .map_or(false, |first| first.script_source != coverage.script_source);
+    if source_changed {
+      eprintln!(
+        "Ignoring a coverage profile of {} as its source changed.",
+        url
+      );
+      continue;
+    }
+    group.push(coverage);
+  }
+
+  Ok(grouped.into_iter().map(|(_, group)| group).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Creates the coverage of a script from its functions, given as their
+  /// names and ranges of start offset, end offset and count.
+  fn coverage(
+    script_source: &str,
+    functions: Vec<(&str, Vec<(usize, usize, usize)>)>,
+  ) -> Coverage {
+    let functions = functions
+      .into_iter()
+      .map(|(name, ranges)| FunctionCoverage {
+        function_name: name.to_string(),
+        ranges: ranges
+          .into_iter()
+          .map(|(start_offset, end_offset, count)| CoverageRange {
+            start_offset,
+            end_offset,
+            count,
+          })
+          .collect(),
+        is_block_coverage: true,
+      })
+      .collect();
+    Coverage {
+      script_coverage: ScriptCoverage {
+        script_id: "1".to_string(),
+        url: "file:///a.js".to_string(),
+        functions,
+      },
+      script_source: script_source.to_string(),
+      source_hash: None,
+    }
+  }
+
+  #[test]
+  fn test_script_line_counts() {
+    let source = "function f() {\n  return 1;\n}\nconst x = 1;\n";
+    let coverage = coverage(
+      source,
+      vec![("", vec![(0, 42, 1)]), ("f", vec![(0, 28, 0)])],
+    );
+    assert_eq!(
+      script_line_counts(&coverage.script_coverage, source),
+      vec![Some(0), Some(0), Some(0), Some(1), None]
+    );
+  }
+
+  #[test]
+  fn test_script_line_counts_utf16_offsets() {
+    // The emoji takes two UTF-16 code units, but four bytes.
+    let source = "const s = \"\u{1F600}\";\nf();\n";
+    let coverage = coverage(source, vec![("", vec![(0, 21, 1), (16, 20, 0)])]);
+    assert_eq!(
+      script_line_counts(&coverage.script_coverage, source),
+      vec![Some(1), Some(0), None]
+    );
+  }
+
+  #[test]
+  fn test_file_coverage_merges_profiles() {
+    let source = "function f(a) {\n  if (a) {\n    return 1;\n  }\n  return 2;\n}\nf(true);\n";
+    // `f(true)` in the first run and `f(false)` in the second.
+    let coverages = vec![
+      coverage(
+        source,
+        vec![
+          ("", vec![(0, 68, 1)]),
+          ("f", vec![(0, 58, 1), (25, 44, 1), (44, 57, 0)]),
+        ],
+      ),
+      coverage(
+        source,
+        vec![("", vec![(0, 68, 1)]), ("f", vec![(0, 58, 1), (25, 44, 0)])],
+      ),
+    ];
+    let file_coverage = FileCoverage::new(&coverages, None, None);
+
+    assert_eq!(file_coverage.url, "file:///a.js");
+    assert_eq!(
+      file_coverage.line_counts.into_iter().collect::<Vec<_>>(),
+      vec![(0, 2), (1, 1), (2, 1), (3, 1), (4, 1), (5, 2), (6, 2)]
+    );
+    assert_eq!(
+      file_coverage.functions,
+      vec![FunctionCount {
+        name: "f".to_string(),
+        line_index: 0,
+        count: 2,
+      }]
+    );
+    assert_eq!(
+      file_coverage.branches,
+      vec![
+        BranchCount {
+          line_index: 1,
+          block_number: 0,
+          branch_number: 0,
+          count: 1,
+        },
+        BranchCount {
+          line_index: 3,
+          block_number: 0,
+          branch_number: 1,
+          count: 0,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_file_coverage_maps_lines_to_original_source() {
+    let script_source = "function f() {}\nf();\n";
+    let original_source = "// comment\nfunction f(): void {}\nf();\n";
+    // Both lines of the script map to the next line of the original source.
+    let source_map = br#"{"version":3,"sources":["file:///a.ts"],"names":[],"mappings":"AACA;AACA"}"#;
+    let coverages = vec![coverage(
+      script_source,
+      vec![("", vec![(0, 21, 1)]), ("f", vec![(0, 15, 1)])],
+    )];
+    let file_coverage = FileCoverage::new(
+      &coverages,
+      Some(source_map.to_vec()),
+      Some(original_source.to_string()),
+    );
+
+    assert_eq!(
+      file_coverage.lines,
+      vec!["// comment", "function f(): void {}", "f();", ""]
+    );
+    assert_eq!(
+      file_coverage.line_counts.into_iter().collect::<Vec<_>>(),
+      vec![(1, 1), (2, 1)]
+    );
+    assert_eq!(
+      file_coverage.functions,
+      vec![FunctionCount {
+        name: "f".to_string(),
+        line_index: 1,
+        count: 1,
+      }]
+    );
+  }
+
+  #[test]
+  fn test_file_coverage_without_source_map() {
+    let script_source = "function f() {}\nf();\n";
+    let coverages = vec![coverage(script_source, vec![("", vec![(0, 21, 1)])])];
+    // Without a source map the lines of the original source can't be
+    // mapped, so the script is reported on.
+    let file_coverage = FileCoverage::new(
+      &coverages,
+      None,
+      Some("// comment\nfunction f(): void {}\nf();\n".to_string()),
+    );
+    assert_eq!(file_coverage.lines, vec!["function f() {}", "f();", ""]);
+    assert_eq!(
+      file_coverage.line_counts.into_iter().collect::<Vec<_>>(),
+      vec![(0, 1), (1, 1)]
+    );
+  }
+
+  #[test]
+  fn test_check_threshold_reports_every_metric() {
+    let summary = CoverageSummary {
+      lines_found: 4,
+      lines_hit: 2,
+      branches_found: 2,
+      branches_hit: 2,
+      functions_found: 4,
+      functions_hit: 1,
+    };
+    assert!(summary.check_threshold(50.0).is_err());
+    assert!(summary.check_threshold(25.0).is_ok());
+    let err = summary.check_threshold(90.0).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Coverage is below the threshold of 90%: lines (50.000%), functions (25.000%)"
+    );
+  }
 }
@@ -372,6 +372,10 @@ impl Diagnostics {
   pub fn is_empty(&self) -> bool {
     self.0.is_empty()
   }
+
+  pub fn iter(&self) -> std::slice::Iter<Diagnostic> {
+    self.0.iter()
+  }
 }
 
 impl<'de> Deserialize<'de> for Diagnostics {
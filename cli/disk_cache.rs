@@ -105,6 +105,11 @@ impl DiskCache {
 
         out = out.join(remaining_components);
       }
+      "data" | "blob" => {
+        // These URLs have no meaningful path, so they are named after a hash
+        // of the whole URL.
+        out.push(crate::checksum::gen(&[url.as_str().as_bytes()]));
+      }
       scheme => {
         unimplemented!(
           "Don't know how to create cache name for scheme: {}\n  Url: {}",
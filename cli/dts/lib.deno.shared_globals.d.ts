@@ -667,16 +667,32 @@ declare class Worker extends EventTarget {
     options?: {
       type?: "classic" | "module";
       name?: string;
-      /** UNSTABLE: New API. Expect many changes; most likely this
-       * field will be made into an object for more granular
-       * configuration of worker thread (permissions, import map, etc.).
+      /** UNSTABLE: New API. Expect many changes.
        *
        * Set to `true` to make `Deno` namespace and all of its methods
        * available to worker thread.
        *
-       * Currently worker inherits permissions from main thread (permissions
-       * given using `--allow-*` flags).
-       * Configurable permissions are on the roadmap to be implemented.
+       * Pass an object to configure the worker further: `namespace` makes
+       * the `Deno` namespace available, and `permissions` sets the
+       * permissions of the worker. By default the worker inherits the
+       * permissions of its parent. Each permission can instead be set to
+       * `false` to deny it, `true` to grant it fully, or a list to grant it
+       * only for the listed paths, hosts, variables or commands. Creating
+       * the worker fails with `Deno.errors.PermissionDenied` if it would be
+       * granted anything its parent isn't.
+       *
+       * ```ts
+       * const worker = new Worker(
+       *   new URL("plugin_worker.ts", import.meta.url).href,
+       *   {
+       *     type: "module",
+       *     deno: {
+       *       namespace: true,
+       *       permissions: { read: ["./plugins"], net: false, write: false },
+       *     },
+       *   },
+       * );
+       * ```
        *
        * Example:
        *
@@ -712,7 +728,18 @@ declare class Worker extends EventTarget {
        * hello world2
        *
        */
-      deno?: boolean;
+      deno?: boolean | {
+        namespace?: boolean;
+        permissions?: "inherit" | {
+          env?: "inherit" | boolean | string[];
+          hrtime?: "inherit" | boolean;
+          net?: "inherit" | boolean | string[];
+          plugin?: "inherit" | boolean;
+          read?: "inherit" | boolean | Array<string | URL>;
+          run?: "inherit" | boolean | string[];
+          write?: "inherit" | boolean | Array<string | URL>;
+        };
+      };
     },
   );
   postMessage(message: any, transfer: ArrayBuffer[]): void;
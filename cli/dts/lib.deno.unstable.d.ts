@@ -976,14 +976,198 @@ declare namespace Deno {
     options: ConnectOptions | UnixConnectOptions,
   ): Promise<Conn>;
 
+  export type RecordType =
+    | "A"
+    | "AAAA"
+    | "CNAME"
+    | "MX"
+    | "NS"
+    | "SRV"
+    | "TXT";
+
+  export interface ResolveDnsOptions {
+    /** The name server to use for lookups. If not specified, the name servers
+     * of the system configuration (e.g. `/etc/resolv.conf`) are used. */
+    nameServer?: {
+      /** The IP address of the name server. */
+      ipAddr: string;
+      /** The port number the name server listens on. Defaults to `53`. */
+      port?: number;
+    };
+  }
+
+  export interface MXRecord {
+    preference: number;
+    exchange: string;
+  }
+
+  export interface SRVRecord {
+    priority: number;
+    weight: number;
+    port: number;
+    target: string;
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Resolves the DNS records of the given type for `query`. A, AAAA, CNAME and
+   * NS records resolve to strings, TXT records to arrays of strings.
+   *
+   * ```ts
+   * const a = await Deno.resolveDns("example.com", "A");
+   * const srv = await Deno.resolveDns("_http._tcp.example.com", "SRV", {
+   *   nameServer: { ipAddr: "127.0.0.1", port: 5353 },
+   * });
+   * ```
+   *
+   * Requires `allow-net` permission for the name servers. */
+  export function resolveDns(
+    query: string,
+    recordType: "A" | "AAAA" | "CNAME" | "NS",
+    options?: ResolveDnsOptions,
+  ): Promise<string[]>;
+
+  export function resolveDns(
+    query: string,
+    recordType: "MX",
+    options?: ResolveDnsOptions,
+  ): Promise<MXRecord[]>;
+
+  export function resolveDns(
+    query: string,
+    recordType: "SRV",
+    options?: ResolveDnsOptions,
+  ): Promise<SRVRecord[]>;
+
+  export function resolveDns(
+    query: string,
+    recordType: "TXT",
+    options?: ResolveDnsOptions,
+  ): Promise<string[][]>;
+
+  export function resolveDns(
+    query: string,
+    recordType: RecordType,
+    options?: ResolveDnsOptions,
+  ): Promise<string[] | MXRecord[] | SRVRecord[] | string[][]>;
+
   export interface StartTlsOptions {
     /** A literal IP address or host name that can be resolved to an IP address.
      * If not specified, defaults to `127.0.0.1`. */
     hostname?: string;
     /** Server certificate file. */
     certFile?: string;
+    /** PEM encoded CA certificates used to verify the server, in addition to
+     * `certFile`. */
+    caCerts?: string[];
+    /** PEM encoded client certificate chain, presented if the server asks
+     * for one. Requires `privateKey` or `privateKeyFile`. */
+    certChain?: string;
+    /** Client certificate chain file, instead of `certChain`. */
+    certChainFile?: string;
+    /** PEM encoded private key of the client certificate. */
+    privateKey?: string;
+    /** Private key file of the client certificate, instead of `privateKey`. */
+    privateKeyFile?: string;
+    /** ALPN protocols offered to the server, in order of preference. */
+    alpnProtocols?: string[];
+  }
+
+  /** **UNSTABLE**: new options, yet to be vetted.
+   *
+   * Mutual TLS and in-memory certificates for `Deno.connectTls()`. */
+  export interface ConnectTlsOptions {
+    /** PEM encoded CA certificates used to verify the server, in addition to
+     * `certFile`. */
+    caCerts?: string[];
+    /** PEM encoded client certificate chain, presented if the server asks
+     * for one. Requires `privateKey` or `privateKeyFile`. */
+    certChain?: string;
+    /** Client certificate chain file, instead of `certChain`. */
+    certChainFile?: string;
+    /** PEM encoded private key of the client certificate. */
+    privateKey?: string;
+    /** Private key file of the client certificate, instead of `privateKey`. */
+    privateKeyFile?: string;
+    /** ALPN protocols offered to the server, in order of preference. */
+    alpnProtocols?: string[];
+  }
+
+  /** **UNSTABLE**: new options, yet to be vetted.
+   *
+   * Client certificate verification for `Deno.listenTls()`. */
+  export interface ListenTlsOptions {
+    /** CA certificates file. If given, clients must present a certificate
+     * signed by one of these CAs. */
+    clientCaFile?: string;
+    /** PEM encoded CA certificates, like `clientCaFile`. */
+    clientCaCerts?: string[];
+    /** Certificates selected by the hostname the client sends through SNI,
+     * instead of the default certificate. Clients which don't send any of
+     * these hostnames get the default certificate, if one is given. */
+    sniCerts?: Record<string, TlsCertOptions>;
+    /** ALPN protocols advertised to clients, in order of preference, e.g.
+     * `["h2", "http/1.1"]`. The negotiated protocol is reported by
+     * `TlsConn.alpnProtocol`. */
+    alpnProtocols?: string[];
+  }
+
+  export interface TlsCertOptions {
+    /** Certificate chain file. */
+    certFile?: string;
+    /** Private key file. */
+    keyFile?: string;
+    /** PEM encoded certificate chain, instead of `certFile`. */
+    certChain?: string;
+    /** PEM encoded private key, instead of `keyFile`. */
+    privateKey?: string;
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * A connection accepted by a TLS listener.
+   *
+   * ```ts
+   * const listener = Deno.listenTls({
+   *   port: 443,
+   *   certFile: "./server.crt",
+   *   keyFile: "./server.key",
+   *   alpnProtocols: ["h2", "http/1.1"],
+   * });
+   * const conn = await listener.accept() as Deno.TlsConn;
+   * console.log(conn.alpnProtocol); // e.g. "h2"
+   * ```
+   */
+  export interface TlsConn extends Conn {
+    /** The ALPN protocol negotiated with the client, or `null` if none was
+     * negotiated. */
+    readonly alpnProtocol: string | null;
   }
 
+  export interface ListenTlsPemOptions
+    extends Omit<ListenTlsOptions, "certFile" | "keyFile"> {
+    /** PEM encoded server certificate chain. */
+    certChain: string;
+    /** PEM encoded server private key. */
+    privateKey: string;
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Listen announces on the local transport address over TLS, using a
+   * certificate and key passed as PEM data instead of files.
+   *
+   * ```ts
+   * const listener = Deno.listenTls({
+   *   port: 443,
+   *   certChain: Deno.readTextFileSync("./server.crt"),
+   *   privateKey: Deno.readTextFileSync("./server.key"),
+   * });
+   * ```
+   *
+   * Requires `allow-net` permission. */
+  export function listenTls(options: ListenTlsPemOptions): Listener;
+
   /** **UNSTABLE**: new API, yet to be vetted.
    *
    * Start TLS handshake from an existing connection using
@@ -1005,6 +1189,72 @@ declare namespace Deno {
     options?: StartTlsOptions,
   ): Promise<Conn>;
 
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * A request received on an `HttpConn`, together with the function used to
+   * send its response. */
+  export interface RequestEvent {
+    readonly request: Request;
+    respondWith(r: Response | Promise<Response>): Promise<void>;
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * An HTTP/1.1 connection served natively. Iterating over it yields the
+   * requests received on the connection, one at a time. */
+  export interface HttpConn extends AsyncIterable<RequestEvent> {
+    readonly rid: number;
+
+    /** Resolves to the next request, or to `null` once the connection was
+     * closed. */
+    nextRequest(): Promise<RequestEvent | null>;
+    close(): void;
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Serves HTTP on an accepted TCP or TLS connection. The connection is
+   * taken over by the returned `HttpConn`, so it must not be used directly
+   * anymore. Request and response bodies are streamed.
+   *
+   * ```ts
+   * const listener = Deno.listen({ port: 4500 });
+   * for await (const conn of listener) {
+   *   (async () => {
+   *     for await (const { request, respondWith } of Deno.serveHttp(conn)) {
+   *       respondWith(new Response(`Hello from ${request.url}`));
+   *     }
+   *   })();
+   * }
+   * ```
+   */
+  export function serveHttp(conn: Conn): HttpConn;
+
+  export interface AcceptWebSocketOptions {
+    /** The subprotocols supported by the server. The first protocol offered
+     * by the client which is in this list is selected. */
+    protocols?: string[];
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Performs the server side of the WebSocket handshake on an accepted TCP or
+   * TLS connection, and resolves to the open `WebSocket`. The connection is
+   * taken over by the WebSocket, so it must not be used directly anymore.
+   *
+   * ```ts
+   * const listener = Deno.listen({ port: 8080 });
+   * for await (const conn of listener) {
+   *   const socket = await Deno.acceptWebSocket(conn);
+   *   socket.onmessage = (e) => socket.send(e.data);
+   * }
+   * ```
+   */
+  export function acceptWebSocket(
+    conn: Conn,
+    options?: AcceptWebSocketOptions,
+  ): Promise<WebSocket>;
+
   /** **UNSTABLE**: The `signo` argument may change to require the Deno.Signal
    * enum.
    *
@@ -1045,6 +1295,9 @@ declare namespace Deno {
 
   export interface RunPermissionDescriptor {
     name: "run";
+    /** Optional command associated with this descriptor, either the name of
+     * a binary in `PATH` or a path to it. */
+    command?: string;
   }
 
   export interface ReadPermissionDescriptor {
@@ -1064,12 +1317,17 @@ declare namespace Deno {
      * If specified: must be a valid url. Expected format: <scheme>://<host_or_ip>[:port][/path]
      * If the scheme is unknown, callers should specify some scheme, such as x:// na:// unknown://
      *
+     * An entry as accepted by `--allow-net` is also valid, e.g. `deno.land`,
+     * `10.0.0.0/8`, `*.deno.land:443` or `[::1]:8000-8100`.
+     *
      * See: https://www.iana.org/assignments/uri-schemes/uri-schemes.xhtml */
     url?: string;
   }
 
   export interface EnvPermissionDescriptor {
     name: "env";
+    /** Optional environment variable associated with this descriptor. */
+    variable?: string;
   }
 
   export interface PluginPermissionDescriptor {
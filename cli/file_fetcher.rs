@@ -1,6 +1,8 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::auth_tokens::AuthTokens;
 use crate::colors;
+use crate::http_cache::CachePolicy;
 use crate::http_cache::HttpCache;
 use crate::http_util;
 use crate::http_util::create_http_client;
@@ -18,8 +20,10 @@ use deno_core::url;
 use deno_core::url::Url;
 use deno_core::ModuleSpecifier;
 use deno_fetch::reqwest;
+use deno_fetch::BlobUrlStore;
 use log::info;
 use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::future::Future;
 use std::io::Read;
@@ -59,9 +63,22 @@ impl SourceFileCache {
       None => None,
     }
   }
+
+  /// Removes the entries of the local files at the given paths.
+  pub fn invalidate(&self, paths: &[PathBuf]) {
+    let mut c = self.0.lock().unwrap();
+    c.retain(|_, source_file| {
+      source_file.url.scheme() != "file"
+        || !source_file
+          .url
+          .to_file_path()
+          .map_or(false, |path| paths.contains(&path))
+    });
+  }
 }
 
-const SUPPORTED_URL_SCHEMES: [&str; 3] = ["http", "https", "file"];
+const SUPPORTED_URL_SCHEMES: [&str; 5] =
+  ["http", "https", "file", "data", "blob"];
 
 #[derive(Clone)]
 pub struct SourceFileFetcher {
@@ -71,6 +88,8 @@ pub struct SourceFileFetcher {
   no_remote: bool,
   cached_only: bool,
   http_client: reqwest::Client,
+  blob_url_store: BlobUrlStore,
+  auth_tokens: AuthTokens,
   // This field is public only to expose it's location
   pub http_cache: HttpCache,
 }
@@ -83,6 +102,7 @@ impl SourceFileFetcher {
     no_remote: bool,
     cached_only: bool,
     ca_file: Option<&str>,
+    blob_url_store: BlobUrlStore,
   ) -> Result<Self, AnyError> {
     let file_fetcher = Self {
       http_cache,
@@ -92,6 +112,8 @@ impl SourceFileFetcher {
       no_remote,
       cached_only,
       http_client: create_http_client(ca_file)?,
+      blob_url_store,
+      auth_tokens: AuthTokens::new(env::var("DENO_AUTH_TOKENS").ok()),
     };
 
     Ok(file_fetcher)
@@ -150,6 +172,12 @@ impl SourceFileFetcher {
     self.source_file_cache.set(specifier.to_string(), file);
   }
 
+  /// Drops the in-process cached sources of the local files at the given
+  /// paths, so that they are read again on their next fetch.
+  pub fn invalidate_local_files(&self, paths: &[PathBuf]) {
+    self.source_file_cache.invalidate(paths);
+  }
+
   pub async fn fetch_source_file(
     &self,
     specifier: &ModuleSpecifier,
@@ -245,6 +273,13 @@ impl SourceFileFetcher {
       return self.fetch_local_file(&module_url, permissions).map(Some);
     }
 
+    // Data and blob URLs carry their source, there is nothing to cache.
+    match url_scheme {
+      "data" => return fetch_data_url(&module_url).map(Some),
+      "blob" => return self.fetch_blob_url(&module_url).map(Some),
+      _ => {}
+    }
+
     self.fetch_cached_remote_source(&module_url, 10)
   }
 
@@ -276,6 +311,14 @@ impl SourceFileFetcher {
       return self.fetch_local_file(&module_url, permissions);
     }
 
+    // Data and blob URLs carry their source, so they need neither network
+    // access nor permissions.
+    match url_scheme {
+      "data" => return fetch_data_url(&module_url),
+      "blob" => return self.fetch_blob_url(&module_url),
+      _ => {}
+    }
+
     // The file is remote, fail if `no_remote` is true.
     if no_remote {
       let e = std::io::Error::new(
@@ -327,6 +370,22 @@ impl SourceFileFetcher {
     })
   }
 
+  /// Fetch the source of a blob URL created by `URL.createObjectURL()`.
+  fn fetch_blob_url(&self, module_url: &Url) -> Result<SourceFile, AnyError> {
+    let blob = self.blob_url_store.get(module_url).ok_or_else(|| {
+      std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("Blob URL not found: \"{}\"", module_url),
+      )
+    })?;
+    let content_type = if blob.media_type.is_empty() {
+      None
+    } else {
+      Some(blob.media_type.as_str())
+    };
+    source_file_from_bytes(module_url, blob.data, content_type)
+  }
+
   /// Fetch cached remote file.
   ///
   /// This is a recursive operation if source file has redirections.
@@ -403,16 +462,38 @@ impl SourceFileFetcher {
   }
 
   /// Asynchronously fetch remote source file specified by the URL following redirects.
+  fn fetch_remote_source(
+    &self,
+    module_url: &Url,
+    use_disk_cache: bool,
+    cached_only: bool,
+    redirect_limit: i64,
+    permissions: &Permissions,
+  ) -> Pin<Box<dyn Future<Output = Result<SourceFile, AnyError>>>> {
+    self.fetch_redirected_remote_source(
+      module_url,
+      use_disk_cache,
+      cached_only,
+      redirect_limit,
+      permissions,
+      false,
+    )
+  }
+
+  /// Same as `fetch_remote_source()`, where `redirected_from_https` tells
+  /// whether the download went through an `https:` URL before being
+  /// redirected to `module_url`.
   ///
   /// Note that this is a recursive method so it can't be "async", but rather return
   /// Pin<Box<..>>.
-  fn fetch_remote_source(
+  fn fetch_redirected_remote_source(
     &self,
     module_url: &Url,
     use_disk_cache: bool,
     cached_only: bool,
     redirect_limit: i64,
     permissions: &Permissions,
+    redirected_from_https: bool,
   ) -> Pin<Box<dyn Future<Output = Result<SourceFile, AnyError>>>> {
     if redirect_limit < 0 {
       let e = custom_error("Http", "too many redirects");
@@ -425,8 +506,11 @@ impl SourceFileFetcher {
 
     let is_blocked =
       check_cache_blocklist(module_url, self.cache_blocklist.as_ref());
-    // First try local cache
-    if use_disk_cache && !is_blocked {
+    // First try local cache.
+    if use_disk_cache
+      && !is_blocked
+      && (cached_only || self.http_cache.is_fresh(&module_url))
+    {
       match self.fetch_cached_remote_source(&module_url, redirect_limit) {
         Ok(Some(source_file)) => {
           return futures::future::ok(source_file).boxed_local();
@@ -455,17 +539,57 @@ impl SourceFileFetcher {
 
     let dir = self.clone();
     let module_url = module_url.clone();
-    let module_etag = match self.http_cache.get(&module_url) {
-      Ok((_, headers)) => headers.get("etag").map(String::from),
-      Err(_) => None,
-    };
+    let (module_etag, module_last_modified) =
+      match self.http_cache.get(&module_url) {
+        Ok((_, headers)) => (
+          headers.get("etag").map(String::from),
+          headers.get("last-modified").map(String::from),
+        ),
+        Err(_) => (None, None),
+      };
     let permissions = permissions.clone();
     let http_client = self.http_client.clone();
+    // The token is looked up for every URL, so that a redirect to another host
+    // never gets the token of the original one.
+    let maybe_auth_token =
+      self.auth_tokens.get(&module_url, redirected_from_https);
+    let redirected_from_https =
+      redirected_from_https || module_url.scheme() == "https";
     // Single pass fetch, either yields code or yields redirect.
     let f = async move {
-      match http_util::fetch_once(http_client, &module_url, module_etag).await?
-      {
-        FetchOnceResult::NotModified => {
+      let result = http_util::fetch_once(
+        http_client,
+        &module_url,
+        module_etag,
+        module_last_modified,
+        maybe_auth_token,
+      )
+      .await;
+      let fetch_once_result = match result {
+        Ok(fetch_once_result) => fetch_once_result,
+        // A stale module is still used if its server can't be reached.
+        Err(err) if use_disk_cache && !is_blocked && is_unreachable(&err) => {
+          return match dir.fetch_cached_remote_source(&module_url, 10) {
+            Ok(Some(source_file)) => {
+              eprintln!(
+                "{} Using the stale cached {}: {}",
+                colors::yellow("Warning"),
+                module_url,
+                err
+              );
+              Ok(source_file)
+            }
+            _ => Err(err),
+          };
+        }
+        Err(err) => return Err(err),
+      };
+      match fetch_once_result {
+        FetchOnceResult::NotModified(headers) => {
+          // Rewriting the metadata restarts the freshness lifetime.
+          if dir.http_cache.cache_policy == CachePolicy::Http {
+            dir.http_cache.update_headers(&module_url, headers)?;
+          }
           let source_file =
             dir.fetch_cached_remote_source(&module_url, 10)?.unwrap();
 
@@ -477,12 +601,13 @@ impl SourceFileFetcher {
 
           // Recurse
           dir
-            .fetch_remote_source(
+            .fetch_redirected_remote_source(
               &new_module_url,
               use_disk_cache,
               cached_only,
               redirect_limit - 1,
               &permissions,
+              redirected_from_https,
             )
             .await
         }
@@ -523,6 +648,61 @@ impl SourceFileFetcher {
   }
 }
 
+/// Decode the source of a `data:` URL, which is either base64 or percent
+/// encoded. The media type of the URL is mapped like a `Content-Type` header.
+fn fetch_data_url(module_url: &Url) -> Result<SourceFile, AnyError> {
+  let mut url = module_url.clone();
+  url.set_fragment(None);
+  let body = &url.as_str()["data:".len()..];
+  let comma = body
+    .find(',')
+    .ok_or_else(|| uri_error(format!("Invalid data URL: \"{}\"", url)))?;
+  let (header, payload) = (body[..comma].trim(), &body[comma + 1..]);
+  let (mime_type, is_base64) = match header.rfind(';') {
+    Some(i) if header[i + 1..].trim().eq_ignore_ascii_case("base64") => {
+      (header[..i].trim(), true)
+    }
+    _ => (header, false),
+  };
+  // As in browsers, a missing media type means plain text.
+  let mime_type = if mime_type.is_empty() {
+    "text/plain;charset=US-ASCII".to_string()
+  } else if mime_type.starts_with(';') {
+    format!("text/plain{}", mime_type)
+  } else {
+    mime_type.to_string()
+  };
+  let mut data = http_util::percent_decode(payload);
+  if is_base64 {
+    data.retain(|b| !b.is_ascii_whitespace());
+    data = base64::decode(&data).map_err(|err| {
+      uri_error(format!("Invalid base64 in data URL \"{}\": {}", url, err))
+    })?;
+  }
+  source_file_from_bytes(module_url, data, Some(&mime_type))
+}
+
+/// Build the source file of a data or blob URL, which is kept in memory only.
+fn source_file_from_bytes(
+  module_url: &Url,
+  bytes: Vec<u8>,
+  content_type: Option<&str>,
+) -> Result<SourceFile, AnyError> {
+  let (media_type, charset) = map_content_type(Path::new(""), content_type);
+  let source_code = if let Some(charset) = charset {
+    text_encoding::convert_to_utf8(&bytes, &charset)?.to_string()
+  } else {
+    String::from_utf8(bytes)?
+  };
+  Ok(SourceFile {
+    url: module_url.clone(),
+    filename: PathBuf::new(),
+    media_type,
+    source_code,
+    types_header: None,
+  })
+}
+
 // convert a ContentType string into a enumerated MediaType + optional charset
 fn map_content_type(
   path: &Path,
@@ -636,6 +816,15 @@ fn check_cache_blocklist(url: &Url, black_list: &[String]) -> bool {
   false
 }
 
+/// Whether a fetch failed because the server couldn't be reached, as opposed to
+/// the server answering with an error status.
+fn is_unreachable(err: &AnyError) -> bool {
+  match err.downcast_ref::<reqwest::Error>() {
+    Some(err) => err.is_connect() || err.is_timeout(),
+    None => false,
+  }
+}
+
 #[derive(Debug, Default)]
 /// Header metadata associated with a particular "symbolic" source code file.
 /// (the associated source code file might not be cached, while remaining
@@ -665,6 +854,7 @@ mod tests {
       false,
       false,
       None,
+      BlobUrlStore::default(),
     )
     .expect("setup fail")
   }
@@ -1468,6 +1658,32 @@ mod tests {
     assert!(r.is_ok());
   }
 
+  #[tokio::test]
+  async fn test_invalidate_local_files() {
+    let (temp_dir, fetcher) = test_setup();
+    let path = temp_dir.path().join("mod.ts");
+    std::fs::write(&path, "export const a = 1;").unwrap();
+    let specifier =
+      ModuleSpecifier::resolve_url_or_path(path.to_str().unwrap()).unwrap();
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::allow_all())
+      .await;
+    assert_eq!(r.unwrap().source_code, "export const a = 1;");
+
+    // The in-process cache is used until the file is invalidated.
+    std::fs::write(&path, "export const a = 2;").unwrap();
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::allow_all())
+      .await;
+    assert_eq!(r.unwrap().source_code, "export const a = 1;");
+
+    fetcher.invalidate_local_files(&[path.clone()]);
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::allow_all())
+      .await;
+    assert_eq!(r.unwrap().source_code, "export const a = 2;");
+  }
+
   #[tokio::test]
   async fn test_fetch_source_file_1() {
     /*recompile ts file*/
@@ -1511,7 +1727,7 @@ mod tests {
     // unsupported schemes
     let test_cases = [
       "ftp://localhost:4545/testdata/subdir/print_hello.ts",
-      "blob:https://whatwg.org/d0360e2f-caee-469f-9a2f-87d5b0456f6f",
+      "chrome://settings/",
     ];
 
     for &test in test_cases.iter() {
@@ -1520,6 +1736,85 @@ mod tests {
     }
   }
 
+  #[tokio::test]
+  async fn test_fetch_data_url() {
+    let (_temp_dir, fetcher) = test_setup();
+
+    let specifier = ModuleSpecifier::resolve_url(
+      "data:application/typescript;base64,ZXhwb3J0IGNvbnN0IGEgPSAiYSI7",
+    )
+    .unwrap();
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::default())
+      .await
+      .unwrap();
+    assert_eq!(r.source_code, "export const a = \"a\";");
+    assert_eq!(r.media_type, MediaType::TypeScript);
+
+    let specifier = ModuleSpecifier::resolve_url(
+      "data:text/javascript;charset=utf-8,export%20const%20b%20=%20%22%C3%A9%22;",
+    )
+    .unwrap();
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::default())
+      .await
+      .unwrap();
+    assert_eq!(r.source_code, "export const b = \"\u{e9}\";");
+    assert_eq!(r.media_type, MediaType::JavaScript);
+
+    let specifier = ModuleSpecifier::resolve_url("data:,hello").unwrap();
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::default())
+      .await
+      .unwrap();
+    assert_eq!(r.source_code, "hello");
+    assert_eq!(r.media_type, MediaType::Unknown);
+
+    let specifier =
+      ModuleSpecifier::resolve_url("data:text/javascript;base64,!!").unwrap();
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::default())
+      .await;
+    assert!(r.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_fetch_blob_url() {
+    let temp_dir = TempDir::new().expect("tempdir fail");
+    let blob_url_store = BlobUrlStore::default();
+    let fetcher = SourceFileFetcher::new(
+      HttpCache::new(&temp_dir.path().join("deps")),
+      true,
+      vec![],
+      false,
+      false,
+      None,
+      blob_url_store.clone(),
+    )
+    .unwrap();
+    let url = blob_url_store.insert(deno_fetch::Blob {
+      data: b"export const a = 1;".to_vec(),
+      media_type: "application/javascript".to_string(),
+    });
+    let specifier = ModuleSpecifier::from(url.clone());
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::default())
+      .await
+      .unwrap();
+    assert_eq!(r.source_code, "export const a = 1;");
+    assert_eq!(r.media_type, MediaType::JavaScript);
+
+    blob_url_store.remove(&url);
+    let specifier = ModuleSpecifier::resolve_url(
+      "blob:null/d0360e2f-caee-469f-9a2f-87d5b0456f6f",
+    )
+    .unwrap();
+    let r = fetcher
+      .fetch_source_file(&specifier, None, Permissions::default())
+      .await;
+    assert!(r.is_err());
+  }
+
   async fn test_fetch_source_file_from_disk_nonstandard_encoding(
     charset: &str,
     expected_content: String,
@@ -1847,6 +2142,107 @@ mod tests {
     assert_eq!(modified1, modified2);
   }
 
+  #[tokio::test]
+  async fn test_fetch_with_cache_policy_http() {
+    let _http_server_guard = test_util::http_server();
+    let (_temp_dir, mut fetcher) = test_setup();
+    fetcher.http_cache.cache_policy = CachePolicy::Http;
+    let module_url =
+      Url::parse("http://127.0.0.1:4545/last_modified_script.ts").unwrap();
+    let fetch = |fetcher: &SourceFileFetcher| {
+      fetcher.fetch_remote_source(
+        &module_url,
+        true,
+        false,
+        1,
+        &Permissions::allow_all(),
+      )
+    };
+
+    let source = fetch(&fetcher).await.unwrap();
+    assert_eq!(source.source_code, "console.log('last-modified')");
+
+    // The response has `Cache-Control: no-cache`, so it is revalidated with
+    // `If-Modified-Since` and the cached content is used on a 304.
+    let file_name = fetcher.http_cache.get_cache_filename(&module_url);
+    fs::write(&file_name, "changed content").unwrap();
+    let source = fetch(&fetcher).await.unwrap();
+    assert_eq!(source.source_code, "changed content");
+
+    // The 304 response has `Cache-Control: max-age=3600`, which is now fresh.
+    let (_, headers) = fetcher.http_cache.get(&module_url).unwrap();
+    assert_eq!(headers.get("cache-control").unwrap(), "max-age=3600");
+    assert!(fetcher.http_cache.is_fresh(&module_url));
+    let metadata_path = crate::http_cache::Metadata::filename(&file_name);
+    let modified1 = metadata_path.metadata().unwrap().modified().unwrap();
+    let source = fetch(&fetcher).await.unwrap();
+    assert_eq!(source.source_code, "changed content");
+    let modified2 = metadata_path.metadata().unwrap().modified().unwrap();
+    assert_eq!(modified1, modified2);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_stale_with_cache_policy_http() {
+    let (_temp_dir, mut fetcher) = test_setup();
+    fetcher.http_cache.cache_policy = CachePolicy::Http;
+    // Nothing listens on this port, so the module can't be revalidated.
+    let module_url = Url::parse("http://127.0.0.1:4599/stale.ts").unwrap();
+    fetcher
+      .http_cache
+      .set(&module_url, HashMap::new(), b"export const a = 1;")
+      .unwrap();
+    assert!(!fetcher.http_cache.is_fresh(&module_url));
+
+    let source = fetcher
+      .fetch_remote_source(
+        &module_url,
+        true,
+        false,
+        1,
+        &Permissions::allow_all(),
+      )
+      .await
+      .unwrap();
+    assert_eq!(source.source_code, "export const a = 1;");
+
+    // Without the disk cache the error of the fetch is returned.
+    let result = fetcher
+      .fetch_remote_source(
+        &module_url,
+        false,
+        false,
+        1,
+        &Permissions::allow_all(),
+      )
+      .await;
+    assert!(result.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_fetch_stale_not_found() {
+    let _http_server_guard = test_util::http_server();
+    let (_temp_dir, mut fetcher) = test_setup();
+    fetcher.http_cache.cache_policy = CachePolicy::Http;
+    let module_url =
+      Url::parse("http://127.0.0.1:4545/cli/tests/not_found.ts").unwrap();
+    fetcher
+      .http_cache
+      .set(&module_url, HashMap::new(), b"export const a = 1;")
+      .unwrap();
+
+    // The server answered, so its error isn't hidden by the stale module.
+    let result = fetcher
+      .fetch_remote_source(
+        &module_url,
+        true,
+        false,
+        1,
+        &Permissions::allow_all(),
+      )
+      .await;
+    assert!(result.is_err());
+  }
+
   #[tokio::test]
   async fn test_fetch_with_types_header() {
     let _http_server_guard = test_util::http_server();
@@ -5,6 +5,7 @@ use core::task::{Context, Poll};
 use deno_core::error::AnyError;
 use deno_core::futures::stream::{Stream, StreamExt};
 use deno_core::futures::Future;
+use deno_core::futures::FutureExt;
 use notify::event::Event as NotifyEvent;
 use notify::event::EventKind;
 use notify::Config;
@@ -12,35 +13,41 @@ use notify::Error as NotifyError;
 use notify::RecommendedWatcher;
 use notify::RecursiveMode;
 use notify::Watcher;
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use tokio::select;
 use tokio::time::{interval, Interval};
 
 const DEBOUNCE_INTERVAL_MS: Duration = Duration::from_millis(200);
 
+/// How often the watched paths are updated while the process is running, to
+/// pick up modules which are loaded late, e.g. through dynamic imports.
+const UPDATE_PATHS_INTERVAL_MS: Duration = Duration::from_millis(500);
+
 // TODO(bartlomieju): rename
 type WatchFuture = Pin<Box<dyn Future<Output = Result<(), AnyError>>>>;
 
 struct Debounce {
   interval: Interval,
-  event_detected: Arc<AtomicBool>,
+  changed_paths: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl Debounce {
   fn new() -> Self {
     Self {
       interval: interval(DEBOUNCE_INTERVAL_MS),
-      event_detected: Arc::new(AtomicBool::new(false)),
+      changed_paths: Arc::new(Mutex::new(Vec::new())),
     }
   }
 }
 
 impl Stream for Debounce {
-  type Item = ();
+  /// The paths that changed since the last item was yielded.
+  type Item = Vec<PathBuf>;
 
   /// Note that this never returns `Poll::Ready(None)`, which means that file watcher will be alive
   /// until the Deno process is terminated.
@@ -49,9 +56,12 @@ impl Stream for Debounce {
     cx: &mut Context,
   ) -> Poll<Option<Self::Item>> {
     let inner = self.get_mut();
-    if inner.event_detected.load(Ordering::Relaxed) {
-      inner.event_detected.store(false, Ordering::Relaxed);
-      Poll::Ready(Some(()))
+    let mut changed_paths = inner.changed_paths.lock().unwrap();
+    if !changed_paths.is_empty() {
+      let mut paths: Vec<PathBuf> = changed_paths.drain(..).collect();
+      paths.sort();
+      paths.dedup();
+      Poll::Ready(Some(paths))
     } else {
       let _ = inner.interval.poll_tick(cx);
       Poll::Pending
@@ -67,66 +77,116 @@ async fn error_handler(watch_future: WatchFuture) {
   }
 }
 
-pub async fn watch_func<F>(
-  paths: &[PathBuf],
-  closure: F,
+/// Runs `closure` and restarts it whenever a watched file changes. The
+/// closure is called with the paths that changed since the previous run, or
+/// `None` on the first run.
+///
+/// The watched paths are the ones returned by `paths_resolver`, which is
+/// called repeatedly while the process is running, so that files which are
+/// only loaded later on are watched as well. Paths which are no longer
+/// returned are unwatched once the process terminated.
+pub async fn watch_func<F, G>(
+  mut paths_resolver: G,
+  mut closure: F,
 ) -> Result<(), AnyError>
 where
-  F: Fn() -> WatchFuture,
+  F: FnMut(Option<Vec<PathBuf>>) -> WatchFuture,
+  G: FnMut() -> Vec<PathBuf>,
 {
   let mut debounce = Debounce::new();
-  // This binding is required for the watcher to work properly without being dropped.
-  let _watcher = new_watcher(paths, &debounce)?;
+  let mut watcher = new_watcher(&debounce)?;
+  let mut watched_paths = HashSet::new();
+  let mut changed_paths = None;
 
   loop {
-    let func = error_handler(closure());
-    let mut is_file_changed = false;
-    select! {
-      _ = debounce.next() => {
-        is_file_changed = true;
-        info!(
-          "{} File change detected! Restarting!",
-          colors::intense_blue("Watcher"),
-        );
-      },
-      _ = func => {},
+    let mut func = error_handler(closure(changed_paths.take())).boxed_local();
+    let mut update_paths = interval(UPDATE_PATHS_INTERVAL_MS);
+    loop {
+      select! {
+        paths = debounce.next() => {
+          changed_paths = paths;
+          break;
+        },
+        _ = &mut func => break,
+        _ = update_paths.tick() => {
+          let paths = paths_resolver();
+          update_watched_paths(&mut watcher, &mut watched_paths, paths, false);
+        },
+      }
     }
-    if !is_file_changed {
+
+    if changed_paths.is_none() {
+      let paths = paths_resolver();
+      update_watched_paths(&mut watcher, &mut watched_paths, paths, true);
       info!(
         "{} Process terminated! Restarting on file change...",
         colors::intense_blue("Watcher"),
       );
-      debounce.next().await;
-      info!(
-        "{} File change detected! Restarting!",
-        colors::intense_blue("Watcher"),
-      );
+      changed_paths = debounce.next().await;
     }
+    info!(
+      "{} File change detected! Restarting!",
+      colors::intense_blue("Watcher"),
+    );
+
+    // Editors often save a file by replacing it, which ends the watch of the
+    // original file, so the changed paths are watched anew.
+    for path in changed_paths.iter().flatten() {
+      if watched_paths.remove(path) {
+        let _ = watcher.unwatch(path);
+      }
+    }
+    let paths = paths_resolver();
+    update_watched_paths(&mut watcher, &mut watched_paths, paths, false);
   }
 }
 
-fn new_watcher(
-  paths: &[PathBuf],
-  debounce: &Debounce,
-) -> Result<RecommendedWatcher, AnyError> {
-  let event_detected = Arc::clone(&debounce.event_detected);
+/// Starts watching the paths which aren't watched yet. If `unwatch_stale` is
+/// set, watched paths which are not part of `paths` are unwatched.
+fn update_watched_paths(
+  watcher: &mut RecommendedWatcher,
+  watched_paths: &mut HashSet<PathBuf>,
+  paths: Vec<PathBuf>,
+  unwatch_stale: bool,
+) {
+  let paths: HashSet<PathBuf> = paths.into_iter().collect();
+  if unwatch_stale {
+    for path in watched_paths.difference(&paths) {
+      let _ = watcher.unwatch(path);
+    }
+    watched_paths.retain(|path| paths.contains(path));
+  }
+  for path in paths {
+    if watched_paths.contains(&path) {
+      continue;
+    }
+    // Files which don't exist (anymore) can't be watched, they are retried
+    // on the next update.
+    match watcher.watch(&path, RecursiveMode::NonRecursive) {
+      Ok(()) => {
+        watched_paths.insert(path);
+      }
+      Err(err) => debug!("Failed to watch {}: {}", path.display(), err),
+    }
+  }
+}
+
+fn new_watcher(debounce: &Debounce) -> Result<RecommendedWatcher, AnyError> {
+  let changed_paths = Arc::clone(&debounce.changed_paths);
 
-  let mut watcher: RecommendedWatcher = Watcher::new_immediate(
-    move |res: Result<NotifyEvent, NotifyError>| {
+  let mut watcher: RecommendedWatcher =
+    Watcher::new_immediate(move |res: Result<NotifyEvent, NotifyError>| {
       if let Ok(event) = res {
-        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
-        {
-          event_detected.store(true, Ordering::Relaxed);
+        if matches!(
+          event.kind,
+          EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+          changed_paths.lock().unwrap().extend(event.paths);
         }
       }
-    },
-  )?;
+    })?;
 
   watcher.configure(Config::PreciseEvents(true)).unwrap();
 
-  for path in paths {
-    watcher.watch(path, RecursiveMode::NonRecursive)?;
-  }
-
   Ok(watcher)
 }
@@ -1,5 +1,6 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::http_cache::CachePolicy;
 use clap::App;
 use clap::AppSettings;
 use clap::Arg;
@@ -21,6 +22,10 @@ pub enum DenoSubcommand {
     source_file: String,
     out_file: Option<PathBuf>,
   },
+  Compile {
+    source_file: String,
+    output: Option<PathBuf>,
+  },
   Completions {
     buf: Box<[u8]>,
   },
@@ -38,6 +43,14 @@ pub enum DenoSubcommand {
   Cache {
     files: Vec<String>,
   },
+  Coverage {
+    files: Vec<PathBuf>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    reporter: String,
+    output: Option<PathBuf>,
+    threshold: Option<f32>,
+  },
   Fmt {
     check: bool,
     files: Vec<PathBuf>,
@@ -60,6 +73,7 @@ pub enum DenoSubcommand {
     rules: bool,
     json: bool,
   },
+  LanguageServer,
   Repl,
   Run {
     script: String,
@@ -70,6 +84,12 @@ pub enum DenoSubcommand {
     allow_none: bool,
     include: Option<Vec<String>>,
     filter: Option<String>,
+    jobs: Option<usize>,
+    reporter: String,
+    reporter_output: Option<PathBuf>,
+    shard: Option<(usize, usize)>,
+    /// Seed of the test order, if it is shuffled.
+    shuffle: Option<u64>,
   },
   Types,
   Upgrade {
@@ -102,10 +122,22 @@ pub struct Flags {
   pub allow_run: bool,
   pub allow_write: bool,
   pub cache_blocklist: Vec<String>,
+  pub cache_policy: CachePolicy,
   pub ca_file: Option<String>,
   pub cached_only: bool,
   pub config_path: Option<String>,
-  pub coverage: bool,
+  pub coverage_dir: Option<String>,
+  pub coverage_output: Option<PathBuf>,
+  pub coverage_reporter: Option<String>,
+  pub deny_env: bool,
+  pub deny_hrtime: bool,
+  pub deny_net: bool,
+  pub deny_plugin: bool,
+  pub deny_read: bool,
+  pub deny_run: bool,
+  pub deny_write: bool,
+  pub env_allowlist: Vec<String>,
+  pub env_denylist: Vec<String>,
   pub ignore: Vec<PathBuf>,
   pub import_map_path: Option<String>,
   pub inspect: Option<SocketAddr>,
@@ -114,18 +146,26 @@ pub struct Flags {
   pub lock_write: bool,
   pub log_level: Option<Level>,
   pub net_allowlist: Vec<String>,
+  pub net_denylist: Vec<String>,
   pub no_check: bool,
   pub no_prompts: bool,
   pub no_remote: bool,
+  pub permissions_policy: Option<PathBuf>,
   pub read_allowlist: Vec<PathBuf>,
+  pub read_denylist: Vec<PathBuf>,
   pub reload: bool,
   pub repl: bool,
+  pub run_allowlist: Vec<String>,
+  pub run_denylist: Vec<String>,
   pub seed: Option<u64>,
+  pub trace_permissions: bool,
+  pub trace_permissions_file: Option<PathBuf>,
   pub unstable: bool,
   pub v8_flags: Option<Vec<String>>,
   pub version: bool,
   pub watch: bool,
   pub write_allowlist: Vec<PathBuf>,
+  pub write_denylist: Vec<PathBuf>,
 }
 
 fn join_paths(allowlist: &[PathBuf], d: &str) -> String {
@@ -170,10 +210,20 @@ impl Flags {
       args.push("--allow-net".to_string());
     }
 
+    if !self.env_allowlist.is_empty() {
+      let s = format!("--allow-env={}", self.env_allowlist.join(","));
+      args.push(s);
+    }
+
     if self.allow_env {
       args.push("--allow-env".to_string());
     }
 
+    if !self.run_allowlist.is_empty() {
+      let s = format!("--allow-run={}", self.run_allowlist.join(","));
+      args.push(s);
+    }
+
     if self.allow_run {
       args.push("--allow-run".to_string());
     }
@@ -186,6 +236,63 @@ impl Flags {
       args.push("--allow-hrtime".to_string());
     }
 
+    if !self.read_denylist.is_empty() {
+      let s = format!("--deny-read={}", join_paths(&self.read_denylist, ","));
+      args.push(s);
+    }
+
+    if self.deny_read {
+      args.push("--deny-read".to_string());
+    }
+
+    if !self.write_denylist.is_empty() {
+      let s = format!("--deny-write={}", join_paths(&self.write_denylist, ","));
+      args.push(s);
+    }
+
+    if self.deny_write {
+      args.push("--deny-write".to_string());
+    }
+
+    if !self.net_denylist.is_empty() {
+      let s = format!("--deny-net={}", self.net_denylist.join(","));
+      args.push(s);
+    }
+
+    if self.deny_net {
+      args.push("--deny-net".to_string());
+    }
+
+    if !self.env_denylist.is_empty() {
+      let s = format!("--deny-env={}", self.env_denylist.join(","));
+      args.push(s);
+    }
+
+    if self.deny_env {
+      args.push("--deny-env".to_string());
+    }
+
+    if !self.run_denylist.is_empty() {
+      let s = format!("--deny-run={}", self.run_denylist.join(","));
+      args.push(s);
+    }
+
+    if self.deny_run {
+      args.push("--deny-run".to_string());
+    }
+
+    if self.deny_plugin {
+      args.push("--deny-plugin".to_string());
+    }
+
+    if self.deny_hrtime {
+      args.push("--deny-hrtime".to_string());
+    }
+
+    if let Some(policy) = &self.permissions_policy {
+      args.push(format!("--permissions={}", policy.display()));
+    }
+
     args
   }
 }
@@ -194,6 +301,8 @@ static ENV_VARIABLES_HELP: &str = "ENVIRONMENT VARIABLES:
     DENO_DIR             Set the cache directory
     DENO_INSTALL_ROOT    Set deno install's output directory
                          (defaults to $HOME/.deno/bin)
+    DENO_AUTH_TOKENS     Semicolon-separated list of token@host entries
+                         used to fetch private modules
     DENO_CERT            Load certificate authority from PEM encoded file
     NO_COLOR             Set to disable color
     HTTP_PROXY           Proxy address for HTTP requests
@@ -279,8 +388,12 @@ pub fn flags_from_vec_safe(args: Vec<String>) -> clap::Result<Flags> {
     bundle_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("install") {
     install_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("compile") {
+    compile_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("completions") {
     completions_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("coverage") {
+    coverage_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("test") {
     test_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("upgrade") {
@@ -289,6 +402,8 @@ pub fn flags_from_vec_safe(args: Vec<String>) -> clap::Result<Flags> {
     doc_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("lint") {
     lint_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("lsp") {
+    lsp_parse(&mut flags, m);
   } else {
     repl_parse(&mut flags, &matches);
   }
@@ -338,13 +453,16 @@ If the flag is set, restrict these messages to errors.",
     )
     .subcommand(bundle_subcommand())
     .subcommand(cache_subcommand())
+    .subcommand(compile_subcommand())
     .subcommand(completions_subcommand())
+    .subcommand(coverage_subcommand())
     .subcommand(doc_subcommand())
     .subcommand(eval_subcommand())
     .subcommand(fmt_subcommand())
     .subcommand(info_subcommand())
     .subcommand(install_subcommand())
     .subcommand(lint_subcommand())
+    .subcommand(lsp_subcommand())
     .subcommand(repl_subcommand())
     .subcommand(run_subcommand())
     .subcommand(test_subcommand())
@@ -422,6 +540,18 @@ fn bundle_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   };
 }
 
+fn compile_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  runtime_args_parse(flags, matches, true);
+
+  let source_file = matches.value_of("source_file").unwrap().to_string();
+  let output = matches.value_of("output").map(PathBuf::from);
+
+  flags.subcommand = DenoSubcommand::Compile {
+    source_file,
+    output,
+  };
+}
+
 fn completions_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   let shell: &str = matches.value_of("shell").unwrap();
   let mut buf: Vec<u8> = vec![];
@@ -437,6 +567,34 @@ fn completions_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   };
 }
 
+fn coverage_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  let files = match matches.values_of("files") {
+    Some(f) => f.map(PathBuf::from).collect(),
+    None => vec![],
+  };
+  let include = match matches.values_of("include") {
+    Some(f) => f.map(String::from).collect(),
+    None => vec![],
+  };
+  let exclude = match matches.values_of("exclude") {
+    Some(f) => f.map(String::from).collect(),
+    None => vec![],
+  };
+  let reporter = matches.value_of("reporter").unwrap().to_string();
+  let output = matches.value_of("output").map(PathBuf::from);
+  let threshold = matches
+    .value_of("coverage-threshold")
+    .map(|t| t.parse().unwrap());
+  flags.subcommand = DenoSubcommand::Coverage {
+    files,
+    include,
+    exclude,
+    reporter,
+    output,
+    threshold,
+  };
+}
+
 fn repl_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   runtime_args_parse(flags, matches, false);
   flags.repl = true;
@@ -507,6 +665,7 @@ fn compile_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     .arg(config_arg())
     .arg(no_check_arg())
     .arg(reload_arg())
+    .arg(cache_policy_arg())
     .arg(lock_arg())
     .arg(lock_write_arg())
     .arg(ca_file_arg())
@@ -518,6 +677,7 @@ fn compile_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   config_arg_parse(flags, matches);
   no_check_arg_parse(flags, matches);
   reload_arg_parse(flags, matches);
+  cache_policy_arg_parse(flags, matches);
   lock_args_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
 }
@@ -566,6 +726,8 @@ fn run_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   }
 
   flags.watch = matches.is_present("watch");
+  coverage_arg_parse(flags, matches);
+  trace_permissions_arg_parse(flags, matches);
   flags.subcommand = DenoSubcommand::Run { script };
 }
 
@@ -576,11 +738,30 @@ fn test_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   let allow_none = matches.is_present("allow_none");
   let quiet = matches.is_present("quiet");
   let filter = matches.value_of("filter").map(String::from);
-  let coverage = matches.is_present("coverage");
-
-  if coverage {
-    flags.coverage = true;
-  }
+  let jobs = matches.value_of("jobs").map(|j| j.parse().unwrap());
+  let reporter = matches.value_of("reporter").unwrap().to_string();
+  let reporter_output = matches.value_of("reporter-output").map(PathBuf::from);
+  let shard = matches.value_of("shard").map(|s| parse_shard(s).unwrap());
+  let shuffle = if matches.is_present("shuffle") {
+    // The seed is picked here, so that it seeds Math.random() as well.
+    let seed = match matches.value_of("shuffle") {
+      Some(seed) => seed.parse().unwrap(),
+      None => flags.seed.unwrap_or_else(rand::random),
+    };
+    if flags.seed != Some(seed) {
+      set_seed(flags, seed);
+    }
+    Some(seed)
+  } else {
+    None
+  };
+  coverage_arg_parse(flags, matches);
+  flags.coverage_reporter =
+    matches.value_of("coverage-reporter").map(String::from);
+  flags.coverage_output =
+    matches.value_of("coverage-output").map(PathBuf::from);
+  trace_permissions_arg_parse(flags, matches);
+  flags.watch = matches.is_present("watch");
 
   if matches.is_present("script_arg") {
     let script_arg: Vec<String> = matches
@@ -611,6 +792,11 @@ fn test_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
     include,
     filter,
     allow_none,
+    jobs,
+    reporter,
+    reporter_output,
+    shard,
+    shuffle,
   };
 }
 
@@ -671,6 +857,10 @@ fn lint_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   };
 }
 
+fn lsp_parse(flags: &mut Flags, _matches: &clap::ArgMatches) {
+  flags.subcommand = DenoSubcommand::LanguageServer;
+}
+
 fn types_subcommand<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("types")
     .about("Print runtime TypeScript declarations")
@@ -682,6 +872,20 @@ The declaration file could be saved and used for typing information.",
     )
 }
 
+fn lsp_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("lsp")
+    .about("Start the language server")
+    .long_about(
+      "Start the Deno language server which will take input
+from stdin and provide output to stdout.
+  deno lsp
+
+The language server is meant to be started by an editor and provides
+diagnostics, hover information, completions, go to definition and
+formatting for JavaScript and TypeScript code.",
+    )
+}
+
 fn fmt_subcommand<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("fmt")
     .about("Format source files")
@@ -800,6 +1004,39 @@ If no output file is given, the output is written to standard output:
     )
 }
 
+fn compile_subcommand<'a, 'b>() -> App<'a, 'b> {
+  runtime_args(SubCommand::with_name("compile"), true)
+    .arg(
+      Arg::with_name("source_file")
+        .takes_value(true)
+        .required(true),
+    )
+    .arg(
+      Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .help("Output file (defaults to $PWD/<inferred-name>)")
+        .takes_value(true),
+    )
+    .about("Compile the script into a self contained executable")
+    .long_about(
+      "Compiles the given script into a self contained executable.
+  deno compile --unstable -A https://deno.land/std/http/file_server.ts
+  deno compile --unstable --output /usr/local/bin/color_util https://deno.land/std/examples/colors.ts
+
+The executable name is inferred by default:
+  - Attempt to take the file stem of the URL path. The above example would
+    become 'file_server'.
+  - If the file stem is something generic like 'main', 'mod', 'index' or 'cli',
+    and the path has no parent, take the file name of the parent path. Otherwise
+    settle with the generic name.
+  - If the resulting name has an '@...' suffix, strip it.
+
+The permission flags given to this command are embedded into the executable
+and applied every time it runs.",
+    )
+}
+
 fn completions_subcommand<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("completions")
     .setting(AppSettings::DisableHelpSubcommand)
@@ -816,6 +1053,101 @@ fn completions_subcommand<'a, 'b>() -> App<'a, 'b> {
     )
 }
 
+fn coverage_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("coverage")
+    .about("Print coverage reports")
+    .long_about(
+      "Print coverage reports from coverage profiles.
+
+Collect a coverage profile with deno test:
+  deno test --coverage=cov_profile --unstable
+
+Print a report to stdout:
+  deno coverage --unstable cov_profile
+
+Profiles of several runs can be merged by collecting them into the same
+directory, or by passing several directories:
+  deno coverage --unstable cov_profile_1 cov_profile_2
+
+Include modules whose URL matches any of the regular expressions, and exclude
+those matching any other. By default local modules are included and test
+modules are excluded:
+  deno coverage --unstable --include=\"^file:.*/src/\" --exclude=\"_test\\.ts$\" cov_profile
+
+Write an lcov tracefile or a static HTML report:
+  deno coverage --unstable --reporter=lcov --output=cov.lcov cov_profile
+  deno coverage --unstable --reporter=html --output=html_cov cov_profile",
+    )
+    .arg(
+      Arg::with_name("include")
+        .long("include")
+        .takes_value(true)
+        .value_name("regex")
+        .multiple(true)
+        .number_of_values(1)
+        .require_equals(true)
+        .default_value(COVERAGE_INCLUDE_DEFAULT)
+        .help("Include source files in the report"),
+    )
+    .arg(
+      Arg::with_name("exclude")
+        .long("exclude")
+        .takes_value(true)
+        .value_name("regex")
+        .multiple(true)
+        .number_of_values(1)
+        .require_equals(true)
+        .default_value(COVERAGE_EXCLUDE_DEFAULT)
+        .help("Exclude source files from the report"),
+    )
+    .arg(
+      Arg::with_name("reporter")
+        .long("reporter")
+        .takes_value(true)
+        .possible_values(&["pretty", "lcov", "html"])
+        .default_value("pretty")
+        .help("Format of the coverage report")
+        .long_help(
+          "Format of the coverage report.
+  pretty: print the coverage of each module to stdout
+  lcov:   write an lcov tracefile, to coverage/lcov.info by default
+  html:   write a static HTML report, to coverage/html by default",
+        ),
+    )
+    .arg(
+      Arg::with_name("output")
+        .long("output")
+        .takes_value(true)
+        .value_name("PATH")
+        .help("File or directory the lcov or HTML report is written to"),
+    )
+    .arg(
+      Arg::with_name("coverage-threshold")
+        .long("coverage-threshold")
+        .takes_value(true)
+        .value_name("PERCENT")
+        .validator(coverage_threshold_validate)
+        .help("Fail if the coverage is below PERCENT")
+        .long_help(
+          "Fail if the coverage of lines, branches or functions is below
+PERCENT, a number between 0 and 100.",
+        ),
+    )
+    .arg(
+      Arg::with_name("files")
+        .takes_value(true)
+        .multiple(true)
+        .required(true),
+    )
+}
+
+fn coverage_threshold_validate(val: String) -> Result<(), String> {
+  match val.parse::<f32>() {
+    Ok(threshold) if (0.0..=100.0).contains(&threshold) => Ok(()),
+    _ => Err("must be a number between 0 and 100".to_string()),
+  }
+}
+
 fn eval_subcommand<'a, 'b>() -> App<'a, 'b> {
   runtime_args(SubCommand::with_name("eval"), false)
     .about("Eval script")
@@ -1092,11 +1424,19 @@ fn permission_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     .arg(
       Arg::with_name("allow-env")
         .long("allow-env")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
         .help("Allow environment access"),
     )
     .arg(
       Arg::with_name("allow-run")
         .long("allow-run")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
         .help("Allow running subprocesses"),
     )
     .arg(
@@ -1115,11 +1455,77 @@ fn permission_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         .long("allow-all")
         .help("Allow all permissions"),
     )
+    .arg(
+      Arg::with_name("deny-read")
+        .long("deny-read")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny file system read access, overriding --allow-read"),
+    )
+    .arg(
+      Arg::with_name("deny-write")
+        .long("deny-write")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny file system write access, overriding --allow-write"),
+    )
+    .arg(
+      Arg::with_name("deny-net")
+        .long("deny-net")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny network access, overriding --allow-net")
+        .validator(crate::flags_allow_net::validator),
+    )
+    .arg(
+      Arg::with_name("deny-env")
+        .long("deny-env")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny environment access, overriding --allow-env"),
+    )
+    .arg(
+      Arg::with_name("deny-run")
+        .long("deny-run")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny running subprocesses, overriding --allow-run"),
+    )
+    .arg(
+      Arg::with_name("deny-plugin")
+        .long("deny-plugin")
+        .help("Deny loading plugins, overriding --allow-plugin"),
+    )
+    .arg(
+      Arg::with_name("deny-hrtime").long("deny-hrtime").help(
+        "Deny high resolution time measurement, overriding --allow-hrtime",
+      ),
+    )
+    .arg(
+      Arg::with_name("permissions")
+        .long("permissions")
+        .value_name("FILE")
+        .takes_value(true)
+        .require_equals(true)
+        .help("Grant and deny permissions as set in a JSON policy file"),
+    )
 }
 
 fn run_subcommand<'a, 'b>() -> App<'a, 'b> {
   runtime_args(SubCommand::with_name("run"), true)
     .arg(watch_arg())
+    .arg(coverage_arg())
+    .arg(trace_permissions_arg())
     .setting(AppSettings::TrailingVarArg)
     .arg(
         script_arg()
@@ -1170,13 +1576,92 @@ fn test_subcommand<'a, 'b>() -> App<'a, 'b> {
         .help("Run tests with this string or pattern in the test name"),
     )
     .arg(
-      Arg::with_name("coverage")
-        .long("coverage")
-        .takes_value(false)
-        .requires("unstable")
-        .conflicts_with("inspect")
-        .conflicts_with("inspect-brk")
-        .help("Collect coverage information"),
+      Arg::with_name("jobs")
+        .long("jobs")
+        .takes_value(true)
+        .require_equals(true)
+        .value_name("N")
+        .validator(jobs_validate)
+        .help("Run each test module in its own isolate, N modules at a time"),
+    )
+    .arg(
+      Arg::with_name("reporter")
+        .long("reporter")
+        .takes_value(true)
+        .require_equals(true)
+        .possible_values(&["pretty", "junit", "tap"])
+        .default_value("pretty")
+        .help("Format of the test report"),
+    )
+    .arg(
+      Arg::with_name("reporter-output")
+        .long("reporter-output")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Write the test report to FILE instead of stdout"),
+    )
+    .arg(
+      Arg::with_name("shard")
+        .long("shard")
+        .takes_value(true)
+        .require_equals(true)
+        .value_name("INDEX/COUNT")
+        .validator(|val: String| parse_shard(&val).map(|_| ()))
+        .help("Only run the INDEX-th of COUNT shards of the test modules"),
+    )
+    .arg(
+      Arg::with_name("shuffle")
+        .long("shuffle")
+        .takes_value(true)
+        .min_values(0)
+        .max_values(1)
+        .require_equals(true)
+        .value_name("SEED")
+        .validator(|val: String| match val.parse::<u64>() {
+          Ok(_) => Ok(()),
+          Err(_) => Err("Seed should be a number".to_string()),
+        })
+        .help("Shuffle the order of test modules and tests")
+        .long_help(
+          "Shuffle the order of test modules and tests. The order is determined
+by SEED, which also seeds Math.random() like --seed. If no seed is given, the
+seed of --seed or a random seed is used.",
+        ),
+    )
+    .arg(coverage_arg())
+    .arg(
+      Arg::with_name("coverage-reporter")
+        .long("coverage-reporter")
+        .takes_value(true)
+        .possible_values(&["pretty", "lcov", "html"])
+        .requires("coverage")
+        .conflicts_with("watch")
+        .help("Report on the collected coverage after the tests")
+        .long_help(
+          "Report on the coverage collected into the --coverage directory after
+the tests, like the coverage subcommand with its default --include and
+--exclude.
+  pretty: print the coverage of each module after the tests
+  lcov:   write an lcov tracefile, to coverage/lcov.info by default
+  html:   write a static HTML report, to coverage/html by default",
+        ),
+    )
+    .arg(
+      Arg::with_name("coverage-output")
+        .long("coverage-output")
+        .takes_value(true)
+        .value_name("PATH")
+        .requires("coverage-reporter")
+        .help("File or directory the coverage report is written to"),
+    )
+    .arg(trace_permissions_arg())
+    .arg(
+      watch_arg()
+        .help("Watch for file changes and rerun affected tests")
+        .long_help(
+          "Watch for file changes and rerun the test modules affected by them.
+Only local files from the module graphs of the test modules are watched.",
+        ),
     )
     .arg(
       Arg::with_name("files")
@@ -1195,10 +1680,50 @@ report results to standard output:
 
 Directory arguments are expanded to all contained files matching the glob
 {*_,*.,}test.{js,mjs,ts,jsx,tsx}:
-  deno test src/",
+  deno test src/
+
+Run each test module in its own isolate, with up to 4 modules running in
+parallel:
+  deno test --jobs=4 src/
+
+Write a JUnit XML report for CI systems:
+  deno test --reporter=junit --reporter-output=junit.xml src/
+
+Split the test modules into 8 shards, e.g. across CI machines, and only run
+the second one:
+  deno test --shard=2/8 src/
+
+Rerun the test modules that depend on a file whenever it changes:
+  deno test --unstable --watch src/",
     )
 }
 
+/// Parses the value of `--shard`, e.g. `2/8` for the second of eight shards.
+fn parse_shard(val: &str) -> Result<(usize, usize), String> {
+  let error = || "must be INDEX/COUNT with 1 <= INDEX <= COUNT".to_string();
+  let mut parts = val.splitn(2, '/');
+  let index = parts
+    .next()
+    .unwrap()
+    .parse::<usize>()
+    .map_err(|_| error())?;
+  let count = match parts.next() {
+    Some(count) => count.parse::<usize>().map_err(|_| error())?,
+    None => return Err(error()),
+  };
+  if index == 0 || index > count {
+    return Err(error());
+  }
+  Ok((index, count))
+}
+
+fn jobs_validate(val: String) -> Result<(), String> {
+  match val.parse::<usize>() {
+    Ok(jobs) if jobs > 0 => Ok(()),
+    _ => Err("must be a positive integer".to_string()),
+  }
+}
+
 fn script_arg<'a, 'b>() -> Arg<'a, 'b> {
   Arg::with_name("script_arg")
     .multiple(true)
@@ -1338,6 +1863,31 @@ fn reload_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
   }
 }
 
+fn cache_policy_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("cache-policy")
+    .long("cache-policy")
+    .takes_value(true)
+    .require_equals(true)
+    .possible_values(&["forever", "http"])
+    .value_name("POLICY")
+    .help("When to fetch cached remote modules again")
+    .long_help(
+      "When to fetch cached remote modules again.
+  forever: use cached modules until they are reloaded with --reload (default)
+  http:    revalidate cached modules once they are stale according to their
+           Cache-Control header, using their ETag and Last-Modified headers",
+    )
+}
+
+fn cache_policy_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
+  if let Some(cache_policy) = matches.value_of("cache-policy") {
+    flags.cache_policy = match cache_policy {
+      "http" => CachePolicy::Http,
+      _ => CachePolicy::Forever,
+    };
+  }
+}
+
 fn import_map_arg<'a, 'b>() -> Arg<'a, 'b> {
   Arg::with_name("import-map")
     .long("import-map")
@@ -1384,37 +1934,91 @@ fn watch_arg<'a, 'b>() -> Arg<'a, 'b> {
     .help("Watch for file changes and restart process automatically")
     .long_help(
       "Watch for file changes and restart process automatically.
-Only local files from entry point module graph are watched.",
+Only local files of the loaded modules, including dynamic imports, are watched.",
     )
 }
 
-fn seed_arg<'a, 'b>() -> Arg<'a, 'b> {
-  Arg::with_name("seed")
-    .long("seed")
-    .value_name("NUMBER")
-    .help("Seed Math.random()")
+/// Modules the coverage subcommand reports on by default: local modules,
+/// except for test modules.
+pub const COVERAGE_INCLUDE_DEFAULT: &str = r"^file:";
+pub const COVERAGE_EXCLUDE_DEFAULT: &str = r"test\.(js|mjs|ts|jsx|tsx)$";
+
+fn coverage_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("coverage")
+    .long("coverage")
+    .value_name("DIR")
     .takes_value(true)
-    .validator(|val: String| match val.parse::<u64>() {
-      Ok(_) => Ok(()),
-      Err(_) => Err("Seed should be a number".to_string()),
-    })
+    .require_equals(true)
+    .requires("unstable")
+    .conflicts_with("inspect")
+    .conflicts_with("inspect-brk")
+    .help("Collect coverage profile data into DIR")
+    .long_help(
+      "Collect coverage profile data into DIR. The profiles of the main
+module and of every worker are written as JSON files, which can be reported
+on with the coverage subcommand.",
+    )
+}
+
+fn coverage_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  flags.coverage_dir = matches.value_of("coverage").map(String::from);
+}
+
+fn trace_permissions_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("trace-permissions")
+    .long("trace-permissions")
+    .value_name("FILE")
+    .min_values(0)
+    .takes_value(true)
+    .require_equals(true)
+    .help("Trace permission checks")
+    .long_help(
+      "Record every permission check, with the permission, its descriptor, the
+result and the JavaScript stack which triggered it. At exit, the minimal
+--allow-* flags which would have been enough are printed. If FILE is given,
+all checks are written to it as JSON lines instead.",
+    )
+}
+
+fn trace_permissions_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
+  if matches.is_present("trace-permissions") {
+    flags.trace_permissions = true;
+    flags.trace_permissions_file =
+      matches.value_of("trace-permissions").map(PathBuf::from);
+  }
+}
+
+fn seed_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("seed")
+    .long("seed")
+    .value_name("NUMBER")
+    .help("Seed Math.random()")
+    .takes_value(true)
+    .validator(|val: String| match val.parse::<u64>() {
+      Ok(_) => Ok(()),
+      Err(_) => Err("Seed should be a number".to_string()),
+    })
 }
 
 fn seed_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
   if matches.is_present("seed") {
     let seed_string = matches.value_of("seed").unwrap();
     let seed = seed_string.parse::<u64>().unwrap();
-    flags.seed = Some(seed);
+    set_seed(flags, seed);
+  }
+}
 
-    let v8_seed_flag = format!("--random-seed={}", seed);
+fn set_seed(flags: &mut Flags, seed: u64) {
+  flags.seed = Some(seed);
 
-    match flags.v8_flags {
-      Some(ref mut v8_flags) => {
-        v8_flags.push(v8_seed_flag);
-      }
-      None => {
-        flags.v8_flags = Some(svec![v8_seed_flag]);
-      }
+  let v8_seed_flag = format!("--random-seed={}", seed);
+
+  match flags.v8_flags {
+    Some(ref mut v8_flags) => {
+      v8_flags.push(v8_seed_flag);
+    }
+    None => {
+      flags.v8_flags = Some(svec![v8_seed_flag]);
     }
   }
 }
@@ -1488,12 +2092,26 @@ fn permission_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
     }
   }
 
-  if matches.is_present("allow-env") {
-    flags.allow_env = true;
+  if let Some(env_wl) = matches.values_of("allow-env") {
+    let env_allowlist: Vec<String> =
+      env_wl.map(std::string::ToString::to_string).collect();
+    if env_allowlist.is_empty() {
+      flags.allow_env = true;
+    } else {
+      flags.env_allowlist = env_allowlist;
+    }
   }
-  if matches.is_present("allow-run") {
-    flags.allow_run = true;
+
+  if let Some(run_wl) = matches.values_of("allow-run") {
+    let run_allowlist: Vec<String> =
+      run_wl.map(std::string::ToString::to_string).collect();
+    if run_allowlist.is_empty() {
+      flags.allow_run = true;
+    } else {
+      flags.run_allowlist = run_allowlist;
+    }
   }
+
   if matches.is_present("allow-plugin") {
     flags.allow_plugin = true;
   }
@@ -1510,6 +2128,66 @@ fn permission_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
     flags.allow_plugin = true;
     flags.allow_hrtime = true;
   }
+
+  if let Some(read_dl) = matches.values_of("deny-read") {
+    let read_denylist: Vec<PathBuf> = read_dl.map(PathBuf::from).collect();
+    if read_denylist.is_empty() {
+      flags.deny_read = true;
+    } else {
+      flags.read_denylist = read_denylist;
+    }
+  }
+
+  if let Some(write_dl) = matches.values_of("deny-write") {
+    let write_denylist: Vec<PathBuf> = write_dl.map(PathBuf::from).collect();
+    if write_denylist.is_empty() {
+      flags.deny_write = true;
+    } else {
+      flags.write_denylist = write_denylist;
+    }
+  }
+
+  if let Some(net_dl) = matches.values_of("deny-net") {
+    let raw_net_denylist: Vec<String> =
+      net_dl.map(std::string::ToString::to_string).collect();
+    if raw_net_denylist.is_empty() {
+      flags.deny_net = true;
+    } else {
+      flags.net_denylist =
+        crate::flags_allow_net::parse(raw_net_denylist).unwrap();
+      debug!("net denylist: {:#?}", &flags.net_denylist);
+    }
+  }
+
+  if let Some(env_dl) = matches.values_of("deny-env") {
+    let env_denylist: Vec<String> =
+      env_dl.map(std::string::ToString::to_string).collect();
+    if env_denylist.is_empty() {
+      flags.deny_env = true;
+    } else {
+      flags.env_denylist = env_denylist;
+    }
+  }
+
+  if let Some(run_dl) = matches.values_of("deny-run") {
+    let run_denylist: Vec<String> =
+      run_dl.map(std::string::ToString::to_string).collect();
+    if run_denylist.is_empty() {
+      flags.deny_run = true;
+    } else {
+      flags.run_denylist = run_denylist;
+    }
+  }
+
+  if matches.is_present("deny-plugin") {
+    flags.deny_plugin = true;
+  }
+  if matches.is_present("deny-hrtime") {
+    flags.deny_hrtime = true;
+  }
+  if let Some(policy) = matches.value_of("permissions") {
+    flags.permissions_policy = Some(PathBuf::from(policy));
+  }
 }
 
 // TODO(ry) move this to utility module and add test.
@@ -1933,6 +2611,18 @@ mod tests {
     );
   }
 
+  #[test]
+  fn lsp() {
+    let r = flags_from_vec_safe(svec!["deno", "lsp"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::LanguageServer,
+        ..Flags::default()
+      }
+    );
+  }
+
   #[test]
   fn cache() {
     let r = flags_from_vec_safe(svec!["deno", "cache", "script.ts"]);
@@ -2267,6 +2957,153 @@ mod tests {
     );
   }
 
+  #[test]
+  fn allow_env_allowlist() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-env=HOME,PATH",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        allow_env: false,
+        env_allowlist: svec!["HOME", "PATH"],
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn allow_run_allowlist() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-run=git,curl",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        allow_run: false,
+        run_allowlist: svec!["git", "curl"],
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn deny_flags() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-read",
+      "--deny-read=/home/me/.ssh,/etc",
+      "--allow-net",
+      "--deny-net=evil.com,:8000",
+      "--deny-env",
+      "--deny-run=curl",
+      "--deny-hrtime",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        allow_read: true,
+        read_denylist: vec![
+          PathBuf::from("/home/me/.ssh"),
+          PathBuf::from("/etc")
+        ],
+        allow_net: true,
+        net_denylist: svec![
+          "evil.com",
+          "0.0.0.0:8000",
+          "127.0.0.1:8000",
+          "localhost:8000"
+        ],
+        deny_env: true,
+        run_denylist: svec!["curl"],
+        deny_hrtime: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn permissions_policy() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--permissions=policy.json",
+      "--allow-hrtime",
+      "script.ts"
+    ]);
+    let flags = r.unwrap();
+    assert_eq!(
+      flags,
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        permissions_policy: Some(PathBuf::from("policy.json")),
+        allow_hrtime: true,
+        ..Flags::default()
+      }
+    );
+    assert_eq!(
+      flags.to_permission_args(),
+      svec!["--allow-hrtime", "--permissions=policy.json"]
+    );
+  }
+
+  #[test]
+  fn trace_permissions() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--trace-permissions",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        trace_permissions: true,
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--trace-permissions=trace.jsonl",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        trace_permissions: true,
+        trace_permissions_file: Some(PathBuf::from("trace.jsonl")),
+        ..Flags::default()
+      }
+    );
+  }
+
   #[test]
   fn bundle() {
     let r = flags_from_vec_safe(svec!["deno", "bundle", "source.ts"]);
@@ -2759,6 +3596,34 @@ mod tests {
     );
   }
 
+  #[test]
+  fn cache_policy() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "cache",
+      "--cache-policy=http",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Cache {
+          files: svec!["script.ts"],
+        },
+        cache_policy: CachePolicy::Http,
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--cache-policy=always",
+      "script.ts"
+    ]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn allow_net_allowlist_with_ports() {
     let r = flags_from_vec_safe(svec![
@@ -2818,6 +3683,38 @@ mod tests {
     );
   }
 
+  #[test]
+  fn allow_net_allowlist_with_patterns() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-net=10.0.0.0/8,*.internal.example.com,[::1]:8000-8100",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        net_allowlist: svec![
+          "10.0.0.0/8",
+          "*.internal.example.com",
+          "[::1]:8000-8100"
+        ],
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-net=10.0.0.0/33",
+      "script.ts"
+    ]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn lock_write() {
     let r = flags_from_vec_safe(svec![
@@ -2840,6 +3737,212 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_reporter() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--reporter=junit",
+      "--reporter-output",
+      "junit.xml",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "junit".to_string(),
+          reporter_output: Some(PathBuf::from("junit.xml")),
+          shard: None,
+          shuffle: None,
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r =
+      flags_from_vec_safe(svec!["deno", "test", "--reporter=xml", "dir1"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn test_shard() {
+    let r = flags_from_vec_safe(svec!["deno", "test", "--shard=2/8", "dir1"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: Some((2, 8)),
+          shuffle: None,
+        },
+        ..Flags::default()
+      }
+    );
+
+    for shard in &["0/8", "9/8", "2", "a/8", "2/"] {
+      let r = flags_from_vec_safe(svec![
+        "deno",
+        "test",
+        format!("--shard={}", shard),
+        "dir1"
+      ]);
+      assert!(r.is_err(), "{}", shard);
+    }
+  }
+
+  #[test]
+  fn test_shuffle() {
+    let flags =
+      flags_from_vec_safe(svec!["deno", "test", "--shuffle", "dir1"]).unwrap();
+    // A random seed is picked, which also seeds Math.random().
+    let seed = match flags.subcommand {
+      DenoSubcommand::Test {
+        shuffle: Some(seed),
+        ..
+      } => seed,
+      _ => panic!("unexpected subcommand {:?}", flags.subcommand),
+    };
+    assert_eq!(flags.seed, Some(seed));
+    assert_eq!(
+      flags.v8_flags,
+      Some(vec![format!("--random-seed={}", seed)])
+    );
+  }
+
+  #[test]
+  fn test_shuffle_with_seed_flag() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--seed",
+      "250",
+      "--shuffle",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: Some(250),
+        },
+        seed: Some(250),
+        v8_flags: Some(svec!["--random-seed=250"]),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_shuffle_with_seed() {
+    let r = flags_from_vec_safe(svec!["deno", "test", "--shuffle=250", "dir1"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: Some(250),
+        },
+        seed: Some(250),
+        v8_flags: Some(svec!["--random-seed=250"]),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_watch() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--unstable",
+      "--watch",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
+        },
+        watch: true,
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_jobs() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--jobs=4",
+      "--failfast",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: true,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: Some(4),
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "test", "--jobs=0", "dir1"]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn test_with_allow_net() {
     let r = flags_from_vec_safe(svec![
@@ -2859,6 +3962,11 @@ mod tests {
           allow_none: true,
           quiet: false,
           include: Some(svec!["dir1/", "dir2/"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
         },
         allow_net: true,
         ..Flags::default()
@@ -2878,6 +3986,11 @@ mod tests {
           quiet: false,
           filter: Some("foo".to_string()),
           include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
         },
         ..Flags::default()
       }
@@ -2897,6 +4010,11 @@ mod tests {
           quiet: false,
           filter: Some("- foo".to_string()),
           include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
         },
         ..Flags::default()
       }
@@ -2909,7 +4027,40 @@ mod tests {
       "deno",
       "test",
       "--unstable",
-      "--coverage",
+      "--coverage=cov",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
+        },
+        coverage_dir: Some("cov".to_string()),
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_coverage_reporter() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--unstable",
+      "--coverage=cov",
+      "--coverage-reporter=lcov",
+      "--coverage-output=cov.lcov",
       "dir1"
     ]);
     assert_eq!(
@@ -2921,14 +4072,103 @@ mod tests {
           quiet: false,
           filter: None,
           include: Some(svec!["dir1"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
+        },
+        coverage_dir: Some("cov".to_string()),
+        coverage_output: Some(PathBuf::from("cov.lcov")),
+        coverage_reporter: Some("lcov".to_string()),
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn run_coverage() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--unstable",
+      "--coverage=cov",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
         },
-        coverage: true,
+        coverage_dir: Some("cov".to_string()),
         unstable: true,
         ..Flags::default()
       }
     );
   }
 
+  #[test]
+  fn coverage() {
+    let r = flags_from_vec_safe(svec!["deno", "coverage", "foo.json"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Coverage {
+          files: vec![PathBuf::from("foo.json")],
+          include: vec![r"^file:".to_string()],
+          exclude: vec![r"test\.(js|mjs|ts|jsx|tsx)$".to_string()],
+          reporter: "pretty".to_string(),
+          output: None,
+          threshold: None,
+        },
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn coverage_with_flags() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "coverage",
+      "--include=^file:.*/src/",
+      "--exclude=_test\\.ts$",
+      "--exclude=fixtures",
+      "--reporter=lcov",
+      "--output=cov.lcov",
+      "--coverage-threshold=80.5",
+      "cov1",
+      "cov2"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Coverage {
+          files: vec![PathBuf::from("cov1"), PathBuf::from("cov2")],
+          include: svec!["^file:.*/src/"],
+          exclude: svec![r"_test\.ts$", "fixtures"],
+          reporter: "lcov".to_string(),
+          output: Some(PathBuf::from("cov.lcov")),
+          threshold: Some(80.5),
+        },
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn coverage_invalid_threshold() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "coverage",
+      "--coverage-threshold=101",
+      "cov"
+    ]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn test_double_hyphen() {
     let r = flags_from_vec_safe(svec![
@@ -2943,6 +4183,11 @@ mod tests {
           quiet: false,
           filter: None,
           include: Some(svec!["test.ts"]),
+          jobs: None,
+          reporter: "pretty".to_string(),
+          reporter_output: None,
+          shard: None,
+          shuffle: None,
         },
         argv: svec!["arg1", "arg2"],
         ..Flags::default()
@@ -3142,6 +4387,51 @@ mod tests {
     );
   }
 
+  #[test]
+  fn compile() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "compile",
+      "--unstable",
+      "https://deno.land/std/examples/colors.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Compile {
+          source_file: "https://deno.land/std/examples/colors.ts".to_string(),
+          output: None,
+        },
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn compile_with_flags() {
+    #[rustfmt::skip]
+    let r = flags_from_vec_safe(svec!["deno", "compile", "--unstable", "--allow-read", "--allow-net=deno.land", "--output", "colors", "https://deno.land/std/examples/colors.ts"]);
+    let flags = r.unwrap();
+    assert_eq!(
+      flags,
+      Flags {
+        subcommand: DenoSubcommand::Compile {
+          source_file: "https://deno.land/std/examples/colors.ts".to_string(),
+          output: Some(PathBuf::from("colors")),
+        },
+        unstable: true,
+        allow_read: true,
+        net_allowlist: svec!["deno.land"],
+        ..Flags::default()
+      }
+    );
+    assert_eq!(
+      flags.to_permission_args(),
+      svec!["--allow-read", "--allow-net=deno.land"]
+    );
+  }
+
   #[test]
   fn inspect_default_host() {
     let r = flags_from_vec_safe(svec!["deno", "run", "--inspect", "foo.js"]);
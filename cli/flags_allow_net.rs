@@ -1,6 +1,7 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 use deno_core::url::Url;
+use std::fmt;
 use std::net::IpAddr;
 use std::str::FromStr;
 
@@ -26,9 +27,221 @@ impl FromStr for BarePort {
   }
 }
 
+/// The host part of an `--allow-net` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostPattern {
+  /// A host name or an IP address.
+  Exact(String),
+  /// `*.example.com`, which matches every subdomain of `example.com` but not
+  /// `example.com` itself. Holds the domain without the leading `*.`.
+  Subdomains(String),
+  /// An IPv4 or IPv6 address block, e.g. `10.0.0.0/8`.
+  Cidr(IpAddr, u8),
+}
+
+/// A parsed `--allow-net` entry, e.g. `deno.land`, `*.deno.land:443`,
+/// `10.0.0.0/8` or `[::1]:8000-8100`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetPattern {
+  pub host: HostPattern,
+  /// Inclusive range of ports, `None` if any port is allowed.
+  pub ports: Option<(u16, u16)>,
+}
+
+impl NetPattern {
+  /// The pattern for a single host, and a single port if one is given.
+  pub fn from_host_and_port(host: &str, port: Option<u16>) -> Self {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    NetPattern {
+      host: HostPattern::Exact(host.to_string()),
+      ports: port.map(|port| (port, port)),
+    }
+  }
+
+  /// Whether every host and port matched by `other` is matched by `self`.
+  pub fn covers(&self, other: &NetPattern) -> bool {
+    let ports_covered = match (self.ports, other.ports) {
+      (None, _) => true,
+      (Some(_), None) => false,
+      (Some((start, end)), Some((other_start, other_end))) => {
+        start <= other_start && other_end <= end
+      }
+    };
+    ports_covered && self.host.covers(&other.host)
+  }
+
+  /// Whether any host and port is matched by both `self` and `other`.
+  pub fn overlaps(&self, other: &NetPattern) -> bool {
+    let ports_overlap = match (self.ports, other.ports) {
+      (Some((start, end)), Some((other_start, other_end))) => {
+        start <= other_end && other_start <= end
+      }
+      _ => true,
+    };
+    // Host patterns are either nested or disjoint.
+    ports_overlap
+      && (self.host.covers(&other.host) || other.host.covers(&self.host))
+  }
+}
+
+impl HostPattern {
+  fn covers(&self, other: &HostPattern) -> bool {
+    use HostPattern::*;
+    match (self, other) {
+      (Exact(host), Exact(other)) => same_host(host, other),
+      (Subdomains(domain), Exact(host)) => is_subdomain(host, domain),
+      (Subdomains(domain), Subdomains(other)) => {
+        domain.eq_ignore_ascii_case(other) || is_subdomain(other, domain)
+      }
+      (Cidr(net, prefix), Exact(host)) => match host.parse::<IpAddr>() {
+        Ok(addr) => in_cidr(addr, *net, *prefix),
+        Err(_) => false,
+      },
+      (Cidr(net, prefix), Cidr(other, other_prefix)) => {
+        other_prefix >= prefix && in_cidr(*other, *net, *prefix)
+      }
+      _ => false,
+    }
+  }
+}
+
+fn same_host(a: &str, b: &str) -> bool {
+  match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+    (Ok(a), Ok(b)) => a == b,
+    _ => a.eq_ignore_ascii_case(b),
+  }
+}
+
+fn is_subdomain(host: &str, domain: &str) -> bool {
+  host.len() > domain.len() + 1
+    && host
+      .to_ascii_lowercase()
+      .ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+}
+
+fn in_cidr(addr: IpAddr, net: IpAddr, prefix: u8) -> bool {
+  match (addr, net) {
+    (IpAddr::V4(addr), IpAddr::V4(net)) => {
+      let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+      u32::from(addr) & mask == u32::from(net) & mask
+    }
+    (IpAddr::V6(addr), IpAddr::V6(net)) => {
+      let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+      u128::from(addr) & mask == u128::from(net) & mask
+    }
+    _ => false,
+  }
+}
+
+fn parse_host_pattern(host: &str) -> Option<HostPattern> {
+  if let Some(i) = host.find('/') {
+    let net = host[..i].parse::<IpAddr>().ok()?;
+    let prefix = host[i + 1..].parse::<u8>().ok()?;
+    let max_prefix = if net.is_ipv4() { 32 } else { 128 };
+    if prefix > max_prefix {
+      return None;
+    }
+    return Some(HostPattern::Cidr(net, prefix));
+  }
+  if host.parse::<IpAddr>().is_ok() {
+    return Some(HostPattern::Exact(host.to_string()));
+  }
+  let (domain, wildcard) = match host.strip_prefix("*.") {
+    Some(domain) => (domain, true),
+    None => (host, false),
+  };
+  // The URL parser rejects malformed names, but also accepts things like
+  // `user@host`, so the parsed host has to be the whole name.
+  let url = Url::parse(&format!("deno://{}", domain)).ok()?;
+  if domain.is_empty() || !url.host_str()?.eq_ignore_ascii_case(domain) {
+    return None;
+  }
+  if wildcard {
+    Some(HostPattern::Subdomains(domain.to_string()))
+  } else {
+    Some(HostPattern::Exact(domain.to_string()))
+  }
+}
+
+fn parse_port_range(ports: &str) -> Option<(u16, u16)> {
+  match ports.find('-') {
+    Some(i) => {
+      let start = ports[..i].parse::<u16>().ok()?;
+      let end = ports[i + 1..].parse::<u16>().ok()?;
+      if start <= end {
+        Some((start, end))
+      } else {
+        None
+      }
+    }
+    None => {
+      let port = ports.parse::<u16>().ok()?;
+      Some((port, port))
+    }
+  }
+}
+
+impl FromStr for NetPattern {
+  type Err = String;
+  fn from_str(s: &str) -> Result<NetPattern, String> {
+    let bad_pair = || format!("Bad host:port pair: {}", s);
+    // IPv6 addresses contain colons, so they need brackets to have a port.
+    let (host, ports, ipv6) = if let Some(rest) = s.strip_prefix('[') {
+      let end = rest.find(']').ok_or_else(bad_pair)?;
+      let ports = match &rest[end + 1..] {
+        "" => None,
+        ports => Some(ports.strip_prefix(':').ok_or_else(bad_pair)?),
+      };
+      (&rest[..end], ports, true)
+    } else if s.matches(':').count() > 1 {
+      (s, None, true)
+    } else {
+      match s.rfind(':') {
+        Some(i) => (&s[..i], Some(&s[i + 1..]), false),
+        None => (s, None, false),
+      }
+    };
+    let host = parse_host_pattern(host).ok_or_else(bad_pair)?;
+    if ipv6 {
+      let is_ipv6 = match &host {
+        HostPattern::Exact(host) => host.parse::<IpAddr>().is_ok(),
+        HostPattern::Cidr(net, _) => net.is_ipv6(),
+        HostPattern::Subdomains(_) => false,
+      };
+      if !is_ipv6 {
+        return Err(bad_pair());
+      }
+    }
+    let ports = match ports {
+      Some(ports) => Some(parse_port_range(ports).ok_or_else(bad_pair)?),
+      None => None,
+    };
+    Ok(NetPattern { host, ports })
+  }
+}
+
+impl fmt::Display for NetPattern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let host = match &self.host {
+      HostPattern::Exact(host) => host.to_string(),
+      HostPattern::Subdomains(domain) => format!("*.{}", domain),
+      HostPattern::Cidr(net, prefix) => format!("{}/{}", net, prefix),
+    };
+    if self.ports.is_some() && host.contains(':') {
+      write!(f, "[{}]", host)?;
+    } else {
+      write!(f, "{}", host)?;
+    }
+    match self.ports {
+      Some((start, end)) if start == end => write!(f, ":{}", start),
+      Some((start, end)) => write!(f, ":{}-{}", start, end),
+      None => Ok(()),
+    }
+  }
+}
+
 pub fn validator(host_and_port: String) -> Result<(), String> {
-  if Url::parse(&format!("deno://{}", host_and_port)).is_ok()
-    || host_and_port.parse::<IpAddr>().is_ok()
+  if host_and_port.parse::<NetPattern>().is_ok()
     || host_and_port.parse::<BarePort>().is_ok()
   {
     Ok(())
@@ -43,9 +256,7 @@ pub fn validator(host_and_port: String) -> Result<(), String> {
 pub fn parse(paths: Vec<String>) -> clap::Result<Vec<String>> {
   let mut out: Vec<String> = vec![];
   for host_and_port in paths.iter() {
-    if Url::parse(&format!("deno://{}", host_and_port)).is_ok()
-      || host_and_port.parse::<IpAddr>().is_ok()
-    {
+    if host_and_port.parse::<NetPattern>().is_ok() {
       out.push(host_and_port.to_owned())
     } else if let Ok(port) = host_and_port.parse::<BarePort>() {
       // we got bare port, let's add default hosts
@@ -106,6 +317,76 @@ mod bare_port_tests {
   }
 }
 
+#[cfg(test)]
+mod net_pattern_tests {
+  use super::NetPattern;
+
+  #[test]
+  fn net_pattern_covers() {
+    let cases = vec![
+      ("10.0.0.0/8", "10.1.2.3:80", true),
+      ("10.0.0.0/8", "11.0.0.1", false),
+      ("10.0.0.0/8", "10.1.0.0/16", true),
+      ("10.1.0.0/16", "10.0.0.0/8", false),
+      ("0.0.0.0/0", "192.168.0.1", true),
+      ("fd00::/8", "[fd12::1]:443", true),
+      ("fd00::/8", "10.0.0.1", false),
+      ("*.example.com", "api.example.com", true),
+      ("*.example.com", "a.b.EXAMPLE.com:443", true),
+      ("*.example.com", "example.com", false),
+      ("*.example.com", "badexample.com", false),
+      ("*.example.com", "*.api.example.com", true),
+      ("[::1]:8000-8100", "[::1]:8080", true),
+      ("[::1]:8000-8100", "[::1]:8101", false),
+      ("[::1]:8000-8100", "::1", false),
+      ("deno.land", "DENO.LAND:443", true),
+      ("deno.land:443", "deno.land", false),
+    ];
+    for (pattern, other, expected) in cases {
+      let pattern: NetPattern = pattern.parse().unwrap();
+      let other: NetPattern = other.parse().unwrap();
+      assert_eq!(
+        pattern.covers(&other),
+        expected,
+        "{} covers {}",
+        pattern,
+        other
+      );
+    }
+  }
+
+  #[test]
+  fn net_pattern_overlaps() {
+    let cases = vec![
+      ("10.0.0.0/8", "10.1.0.0/16:80", true),
+      ("10.1.2.3", "10.0.0.0/8", true),
+      ("deno.land:80", "deno.land:443", false),
+      ("deno.land:80-90", "deno.land:85-100", true),
+      ("*.a.com", "b.com", false),
+    ];
+    for (pattern, other, expected) in cases {
+      let pattern: NetPattern = pattern.parse().unwrap();
+      let other: NetPattern = other.parse().unwrap();
+      assert_eq!(pattern.overlaps(&other), expected);
+      assert_eq!(other.overlaps(&pattern), expected);
+    }
+  }
+
+  #[test]
+  fn net_pattern_display() {
+    for entry in &[
+      "deno.land",
+      "*.deno.land:443",
+      "10.0.0.0/8",
+      "fd00::/8",
+      "[fd00::/8]:80",
+      "[::1]:8000-8100",
+    ] {
+      assert_eq!(entry.parse::<NetPattern>().unwrap().to_string(), *entry);
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::parse;
@@ -198,4 +479,34 @@ mod tests {
     let entries = svec!["[::q]:8080"];
     assert!(parse(entries).is_err());
   }
+
+  #[test]
+  fn parse_net_args_patterns() {
+    let entries = svec![
+      "10.0.0.0/8",
+      "10.0.0.0/8:443",
+      "*.internal.example.com",
+      "*.example.com:8000-8100",
+      "[::1]:8000-8100",
+      "fd00::/8",
+      "[fd00::/8]:80"
+    ];
+    let actual = parse(entries.clone()).unwrap();
+    assert_eq!(actual, entries);
+  }
+
+  #[test]
+  fn parse_net_args_pattern_errors() {
+    for entry in &[
+      "10.0.0.0/33",
+      "::/129",
+      "*.",
+      "deno.land:8100-8000",
+      "deno.land:80-",
+      "[deno.land]:80",
+      "*.[::1]",
+    ] {
+      assert!(parse(svec![entry]).is_err(), "{}", entry);
+    }
+  }
 }
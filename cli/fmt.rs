@@ -212,6 +212,12 @@ fn format_stdin(check: bool) -> Result<(), AnyError> {
   Ok(())
 }
 
+/// Formats the given source text with the same configuration as `deno fmt`.
+/// The file name is used to determine the syntax of the source.
+pub fn format_text(file_name: &Path, source: &str) -> Result<String, AnyError> {
+  dprint::format_text(file_name, source, &get_config()).map_err(generic_error)
+}
+
 fn files_str(len: usize) -> &'static str {
   if len <= 1 {
     "file"
@@ -16,6 +16,8 @@ use std::fs::File;
 use std::io;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
 
 pub const CACHE_PERM: u32 = 0o644;
 
@@ -72,9 +74,26 @@ pub fn url_to_filename(url: &Url) -> PathBuf {
   cache_filename
 }
 
+/// Decides when cached remote modules are fetched again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CachePolicy {
+  /// Cached modules are used until they are reloaded with `--reload`.
+  Forever,
+  /// Cached modules are revalidated with the server once they are stale
+  /// according to their `Cache-Control` header.
+  Http,
+}
+
+impl Default for CachePolicy {
+  fn default() -> Self {
+    CachePolicy::Forever
+  }
+}
+
 #[derive(Clone)]
 pub struct HttpCache {
   pub location: PathBuf,
+  pub cache_policy: CachePolicy,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -91,7 +110,6 @@ impl Metadata {
     Ok(())
   }
 
-  #[cfg(test)]
   pub fn read(cache_filename: &Path) -> Result<Metadata, AnyError> {
     let metadata_filename = Metadata::filename(&cache_filename);
     let metadata = fs::read_to_string(metadata_filename)?;
@@ -113,6 +131,7 @@ impl HttpCache {
     assert!(location.is_absolute());
     Self {
       location: location.to_owned(),
+      cache_policy: CachePolicy::default(),
     }
   }
 
@@ -148,6 +167,53 @@ impl HttpCache {
     Ok((file, metadata.headers))
   }
 
+  /// Returns whether the cached response for `url` can be used without
+  /// revalidating it. With `CachePolicy::Http` a response is fresh for the
+  /// `max-age` of its `Cache-Control` header. Its age is taken from the
+  /// metadata file, which is rewritten whenever the response is revalidated.
+  pub fn is_fresh(&self, url: &Url) -> bool {
+    if self.cache_policy == CachePolicy::Forever {
+      return true;
+    }
+    let cache_filename = self.get_cache_filename(url);
+    let max_age = match Metadata::read(&cache_filename) {
+      Ok(metadata) => metadata
+        .headers
+        .get("cache-control")
+        .and_then(|cache_control| parse_max_age(cache_control)),
+      Err(_) => None,
+    };
+    let modified = fs::metadata(Metadata::filename(&cache_filename))
+      .and_then(|metadata| metadata.modified());
+    match (max_age, modified) {
+      (Some(max_age), Ok(modified)) => {
+        let age = SystemTime::now()
+          .duration_since(modified)
+          .unwrap_or_default();
+        age < max_age
+      }
+      _ => false,
+    }
+  }
+
+  /// Merges the caching headers of a `304 Not Modified` response into the
+  /// metadata of the cached response for `url`. Other headers describe the
+  /// cached content, like its `Content-Type`, and are kept.
+  pub fn update_headers(
+    &self,
+    url: &Url,
+    headers_map: HeadersMap,
+  ) -> Result<(), AnyError> {
+    let cache_filename = self.get_cache_filename(url);
+    let mut metadata = Metadata::read(&cache_filename)?;
+    metadata.headers.extend(
+      headers_map
+        .into_iter()
+        .filter(|(name, _)| REVALIDATED_HEADERS.contains(&name.as_str())),
+    );
+    metadata.write(&cache_filename)
+  }
+
   pub fn set(
     &self,
     url: &Url,
@@ -171,6 +237,30 @@ impl HttpCache {
   }
 }
 
+/// Headers of a `304 Not Modified` response which update the cached response.
+const REVALIDATED_HEADERS: &[&str] =
+  &["cache-control", "date", "etag", "expires", "last-modified"];
+
+/// Parses the freshness lifetime from a `Cache-Control` header. Responses
+/// with `no-cache` or `no-store` have to be revalidated every time.
+fn parse_max_age(cache_control: &str) -> Option<Duration> {
+  let mut max_age = None;
+  for directive in cache_control.split(',') {
+    let directive = directive.trim().to_lowercase();
+    if directive == "no-cache" || directive == "no-store" {
+      return Some(Duration::from_secs(0));
+    }
+    if let Some(seconds) = directive.strip_prefix("max-age=") {
+      max_age = seconds
+        .trim_matches('"')
+        .parse()
+        .ok()
+        .map(Duration::from_secs);
+    }
+  }
+  max_age
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -234,6 +324,49 @@ mod tests {
     assert_eq!(headers.get("foobar"), None);
   }
 
+  #[test]
+  fn test_is_fresh() {
+    let dir = TempDir::new().unwrap();
+    let mut cache = HttpCache::new(dir.path());
+    let set = |path: &str, cache_control: Option<&str>| {
+      let url = Url::parse("https://deno.land/x/")
+        .unwrap()
+        .join(path)
+        .unwrap();
+      let mut headers = HashMap::new();
+      if let Some(cache_control) = cache_control {
+        headers.insert("cache-control".to_string(), cache_control.to_string());
+      }
+      HttpCache::new(dir.path()).set(&url, headers, b"").unwrap();
+      url
+    };
+    let max_age = set("max_age.ts", Some("public, max-age=3600"));
+    let expired = set("expired.ts", Some("max-age=0"));
+    let no_cache = set("no_cache.ts", Some("max-age=3600, no-cache"));
+    let no_header = set("no_header.ts", None);
+    let not_cached = Url::parse("https://deno.land/x/not_cached.ts").unwrap();
+
+    // By default cached modules are used until they are reloaded.
+    assert!(cache.is_fresh(&no_header));
+
+    cache.cache_policy = CachePolicy::Http;
+    assert!(cache.is_fresh(&max_age));
+    assert!(!cache.is_fresh(&expired));
+    assert!(!cache.is_fresh(&no_cache));
+    assert!(!cache.is_fresh(&no_header));
+    assert!(!cache.is_fresh(&not_cached));
+
+    let mut headers = HashMap::new();
+    headers.insert("cache-control".to_string(), "max-age=60".to_string());
+    headers.insert("etag".to_string(), "as5625rqdsfb".to_string());
+    headers.insert("content-type".to_string(), "text/html".to_string());
+    cache.update_headers(&no_header, headers).unwrap();
+    assert!(cache.is_fresh(&no_header));
+    let (_, headers) = cache.get(&no_header).unwrap();
+    assert_eq!(headers.get("etag").unwrap(), "as5625rqdsfb");
+    assert_eq!(headers.get("content-type"), None);
+  }
+
   #[test]
   fn test_url_to_filename() {
     let test_cases = [
@@ -2,6 +2,7 @@
 
 use crate::flags::Flags;
 use crate::fs::canonicalize_path;
+use crate::http_cache::CachePolicy;
 use deno_core::error::generic_error;
 use deno_core::error::AnyError;
 use deno_core::url::Url;
@@ -104,7 +105,7 @@ fn get_installer_root() -> Result<PathBuf, io::Error> {
   Ok(home_path)
 }
 
-fn infer_name_from_url(url: &Url) -> Option<String> {
+pub fn infer_name_from_url(url: &Url) -> Option<String> {
   let path = PathBuf::from(url.path());
   let mut stem = match path.file_stem() {
     Some(stem) => stem.to_string_lossy().to_string(),
@@ -120,7 +121,7 @@ fn infer_name_from_url(url: &Url) -> Option<String> {
 }
 
 pub fn install(
-  flags: Flags,
+  mut flags: Flags,
   module_url: &str,
   args: Vec<String>,
   name: Option<String>,
@@ -181,6 +182,12 @@ pub fn install(
 
   let mut extra_files: Vec<(PathBuf, String)> = vec![];
 
+  // The policy file is referenced instead of copied, since paths in it are
+  // relative to its location.
+  if let Some(policy) = flags.permissions_policy.as_mut() {
+    *policy = canonicalize_path(policy)?;
+  }
+
   let mut executable_args = vec!["run".to_string()];
   executable_args.extend_from_slice(&flags.to_permission_args());
   if let Some(ca_file) = flags.ca_file {
@@ -223,6 +230,10 @@ pub fn install(
     executable_args.push("--cached_only".to_string());
   }
 
+  if flags.cache_policy == CachePolicy::Http {
+    executable_args.push("--cache-policy=http".to_string());
+  }
+
   if let Some(v8_flags) = flags.v8_flags {
     executable_args.push(format!("--v8-flags={}", v8_flags.join(",")));
   }
@@ -737,4 +748,47 @@ mod tests {
     let content = fs::read_to_string(file_path).unwrap();
     assert!(content == "{}");
   }
+
+  #[test]
+  fn install_with_permissions_policy() {
+    let temp_dir = TempDir::new().expect("tempdir fail");
+    let bin_dir = temp_dir.path().join("bin");
+    let policy_path = temp_dir.path().join("policy.json");
+    fs::write(&policy_path, "{}").unwrap();
+
+    install(
+      Flags {
+        permissions_policy: Some(policy_path.clone()),
+        ..Flags::default()
+      },
+      "http://localhost:4545/cli/tests/cat.ts",
+      vec![],
+      Some("echo_test".to_string()),
+      Some(temp_dir.path().to_path_buf()),
+      false,
+    )
+    .expect("Install failed");
+
+    let mut file_path = bin_dir.join("echo_test");
+    if cfg!(windows) {
+      file_path = file_path.with_extension("cmd");
+    }
+    let content = fs::read_to_string(file_path).unwrap();
+    let policy_path = canonicalize_path(&policy_path).unwrap();
+    assert!(content
+      .contains(&format!(r#""--permissions={}""#, policy_path.display())));
+
+    let missing_policy_result = install(
+      Flags {
+        permissions_policy: Some(temp_dir.path().join("missing.json")),
+        ..Flags::default()
+      },
+      "http://localhost:4545/cli/tests/cat.ts",
+      vec![],
+      Some("echo_test".to_string()),
+      Some(temp_dir.path().to_path_buf()),
+      true,
+    );
+    assert!(missing_policy_result.is_err());
+  }
 }
@@ -135,15 +135,30 @@ fn lint_file(
   let file_name = file_path.to_string_lossy().to_string();
   let source_code = fs::read_to_string(&file_path)?;
   let media_type = MediaType::from(&file_path);
-  let syntax = ast::get_syntax(&media_type);
+  let file_diagnostics = lint_source(&file_name, &source_code, &media_type)?;
 
+  Ok((file_diagnostics, source_code))
+}
+
+/// Lint the source code of a single module with the recommended rules.
+/// The source doesn't have to be saved to disk, which allows the language
+/// server to lint documents while they are edited.
+pub fn lint_source(
+  file_name: &str,
+  source_code: &str,
+  media_type: &MediaType,
+) -> Result<Vec<LintDiagnostic>, AnyError> {
+  let syntax = ast::get_syntax(media_type);
   let lint_rules = rules::get_recommended_rules();
   let mut linter = create_linter(syntax, lint_rules);
 
-  let file_diagnostics =
-    linter.lint(file_name, source_code.clone(), FileType::Module)?;
+  let diagnostics = linter.lint(
+    file_name.to_string(),
+    source_code.to_string(),
+    FileType::Module,
+  )?;
 
-  Ok((file_diagnostics, source_code))
+  Ok(diagnostics)
 }
 
 /// Lint stdin and write result to stdout.
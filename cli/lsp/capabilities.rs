@@ -0,0 +1,39 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Provides information about what capabilities that are supported by the
+//! language server, which helps determine what messages are sent from the
+//! client.
+
+use lsp_types::ClientCapabilities;
+use lsp_types::CompletionOptions;
+use lsp_types::HoverProviderCapability;
+use lsp_types::OneOf;
+use lsp_types::ServerCapabilities;
+use lsp_types::TextDocumentSyncCapability;
+use lsp_types::TextDocumentSyncKind;
+
+pub fn server_capabilities(
+  _client_capabilities: &ClientCapabilities,
+) -> ServerCapabilities {
+  ServerCapabilities {
+    text_document_sync: Some(TextDocumentSyncCapability::Kind(
+      TextDocumentSyncKind::Full,
+    )),
+    hover_provider: Some(HoverProviderCapability::Simple(true)),
+    completion_provider: Some(CompletionOptions {
+      trigger_characters: Some(vec![
+        ".".to_string(),
+        "\"".to_string(),
+        "'".to_string(),
+        "`".to_string(),
+        "/".to_string(),
+        "@".to_string(),
+        "<".to_string(),
+      ]),
+      ..Default::default()
+    }),
+    definition_provider: Some(OneOf::Left(true)),
+    document_formatting_provider: Some(OneOf::Left(true)),
+    ..Default::default()
+  }
+}
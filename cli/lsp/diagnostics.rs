@@ -0,0 +1,147 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use super::utils;
+
+use crate::diagnostics;
+use crate::lint;
+use crate::media_type::MediaType;
+use crate::module_graph::CheckOptions;
+use crate::module_graph::Graph;
+use crate::module_graph::TypeLib;
+
+use deno_core::error::AnyError;
+use deno_core::ModuleSpecifier;
+use deno_lint::diagnostic::LintDiagnostic;
+use lsp_types::Diagnostic;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::NumberOrString;
+use lsp_types::Range;
+use std::path::PathBuf;
+
+/// Type check the module graph and return the diagnostics which belong to the
+/// supplied specifier.
+pub fn generate_ts_diagnostics(
+  graph: Graph,
+  specifier: &ModuleSpecifier,
+  lib: TypeLib,
+  maybe_config_path: Option<String>,
+) -> Result<Vec<Diagnostic>, AnyError> {
+  let result_info = graph.check(CheckOptions {
+    debug: false,
+    emit: false,
+    lib,
+    maybe_config_path,
+    reload: false,
+  })?;
+  let diagnostics = result_info
+    .diagnostics
+    .iter()
+    .filter(|d| d.file_name.as_deref() == Some(specifier.as_str()))
+    .map(to_lsp_diagnostic)
+    .collect();
+  Ok(diagnostics)
+}
+
+fn to_lsp_diagnostic(diagnostic: &diagnostics::Diagnostic) -> Diagnostic {
+  let severity = match diagnostic.category {
+    diagnostics::DiagnosticCategory::Error => DiagnosticSeverity::Error,
+    diagnostics::DiagnosticCategory::Warning => DiagnosticSeverity::Warning,
+    diagnostics::DiagnosticCategory::Suggestion => DiagnosticSeverity::Hint,
+    diagnostics::DiagnosticCategory::Message => DiagnosticSeverity::Information,
+  };
+  let start = diagnostic
+    .start
+    .as_ref()
+    .map(|p| utils::to_position(p.line, p.character))
+    .unwrap_or_default();
+  let end = diagnostic
+    .end
+    .as_ref()
+    .map(|p| utils::to_position(p.line, p.character))
+    .unwrap_or(start);
+  let message = match (&diagnostic.message_text, &diagnostic.message_chain) {
+    (Some(message_text), _) => message_text.clone(),
+    (None, Some(message_chain)) => message_chain.format_message(0),
+    (None, None) => String::new(),
+  };
+  Diagnostic {
+    range: Range { start, end },
+    severity: Some(severity),
+    code: Some(NumberOrString::Number(diagnostic.code as _)),
+    source: Some("deno-ts".to_string()),
+    message,
+    ..Default::default()
+  }
+}
+
+/// Lint the text of a document, returning the lint diagnostics.  Errors
+/// while parsing the document are not reported, as they are already part of
+/// the TypeScript diagnostics.
+pub fn generate_lint_diagnostics(
+  specifier: &ModuleSpecifier,
+  text: &str,
+) -> Vec<Diagnostic> {
+  let media_type = MediaType::from(&PathBuf::from(specifier.as_url().path()));
+  match lint::lint_source(specifier.as_str(), text, &media_type) {
+    Ok(diagnostics) => diagnostics.iter().map(lint_to_lsp_diagnostic).collect(),
+    Err(err) => {
+      debug!("Could not lint {}: {}", specifier, err);
+      Vec::new()
+    }
+  }
+}
+
+fn lint_to_lsp_diagnostic(diagnostic: &LintDiagnostic) -> Diagnostic {
+  // Lines of lint diagnostics are one based, while columns are zero based.
+  let start = utils::to_position(
+    diagnostic.range.start.line.saturating_sub(1) as u64,
+    diagnostic.range.start.col as u64,
+  );
+  let end = utils::to_position(
+    diagnostic.range.end.line.saturating_sub(1) as u64,
+    diagnostic.range.end.col as u64,
+  );
+  let message = match &diagnostic.hint {
+    Some(hint) => format!("{}\n\nhint: {}", diagnostic.message, hint),
+    None => diagnostic.message.clone(),
+  };
+  Diagnostic {
+    range: Range { start, end },
+    severity: Some(DiagnosticSeverity::Warning),
+    code: Some(NumberOrString::String(diagnostic.code.clone())),
+    source: Some("deno-lint".to_string()),
+    message,
+    ..Default::default()
+  }
+}
+
+/// Convert an error that occurred while building the module graph into a
+/// diagnostic located at the start of the document.
+pub fn from_error(err: &AnyError) -> Diagnostic {
+  Diagnostic {
+    range: Range::default(),
+    severity: Some(DiagnosticSeverity::Error),
+    source: Some("deno".to_string()),
+    message: err.to_string(),
+    ..Default::default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_generate_lint_diagnostics() {
+    let specifier = ModuleSpecifier::resolve_url("file:///a/mod.ts").unwrap();
+    let diagnostics = generate_lint_diagnostics(&specifier, "\ndebugger;\n");
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(
+      diagnostic.code,
+      Some(NumberOrString::String("no-debugger".to_string()))
+    );
+    assert_eq!(diagnostic.source, Some("deno-lint".to_string()));
+    assert_eq!(diagnostic.range.start, utils::to_position(1, 0));
+  }
+}
@@ -0,0 +1,85 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use deno_core::ModuleSpecifier;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+struct DocumentData {
+  version: i64,
+  text: String,
+}
+
+/// The documents that are currently open in the client.  Their content
+/// supersedes what is stored on disk.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentCache {
+  docs: HashMap<ModuleSpecifier, DocumentData>,
+}
+
+impl DocumentCache {
+  pub fn open(
+    &mut self,
+    specifier: ModuleSpecifier,
+    version: i64,
+    text: String,
+  ) {
+    self.docs.insert(specifier, DocumentData { version, text });
+  }
+
+  /// Replace the content of an open document.  Documents are synchronized in
+  /// full, so every change contains the complete text of the document.  The
+  /// version is the one the client gave the changed document.
+  pub fn change(
+    &mut self,
+    specifier: &ModuleSpecifier,
+    version: i64,
+    text: String,
+  ) {
+    if let Some(doc) = self.docs.get_mut(specifier) {
+      doc.version = version;
+      doc.text = text;
+    }
+  }
+
+  pub fn close(&mut self, specifier: &ModuleSpecifier) {
+    self.docs.remove(specifier);
+  }
+
+  pub fn contains(&self, specifier: &ModuleSpecifier) -> bool {
+    self.docs.contains_key(specifier)
+  }
+
+  pub fn get_text(&self, specifier: &ModuleSpecifier) -> Option<&str> {
+    self.docs.get(specifier).map(|doc| doc.text.as_str())
+  }
+
+  pub fn get_version(&self, specifier: &ModuleSpecifier) -> Option<i64> {
+    self.docs.get(specifier).map(|doc| doc.version)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&ModuleSpecifier, &str)> {
+    self.docs.iter().map(|(s, doc)| (s, doc.text.as_str()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_document_cache() {
+    let specifier =
+      ModuleSpecifier::resolve_url("file:///a/b.ts").expect("bad specifier");
+    let mut document_cache = DocumentCache::default();
+    assert!(!document_cache.contains(&specifier));
+    document_cache.open(specifier.clone(), 1, "console.log(1);".to_string());
+    assert!(document_cache.contains(&specifier));
+    assert_eq!(document_cache.get_text(&specifier), Some("console.log(1);"));
+    document_cache.change(&specifier, 5, "console.log(2);".to_string());
+    assert_eq!(document_cache.get_text(&specifier), Some("console.log(2);"));
+    assert_eq!(document_cache.get_version(&specifier), Some(5));
+    document_cache.close(&specifier);
+    assert!(!document_cache.contains(&specifier));
+    assert_eq!(document_cache.get_text(&specifier), None);
+  }
+}
@@ -0,0 +1,211 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use super::state::ServerState;
+use super::utils;
+
+use crate::fmt;
+use crate::tsc::LanguageServiceRequest;
+
+use deno_core::error::custom_error;
+use deno_core::error::AnyError;
+use deno_core::serde::Deserialize;
+use deno_core::serde_json;
+use deno_core::ModuleSpecifier;
+use lsp_types::CompletionItem;
+use lsp_types::CompletionItemKind;
+use lsp_types::CompletionParams;
+use lsp_types::CompletionResponse;
+use lsp_types::DocumentFormattingParams;
+use lsp_types::GotoDefinitionParams;
+use lsp_types::GotoDefinitionResponse;
+use lsp_types::Hover;
+use lsp_types::HoverContents;
+use lsp_types::HoverParams;
+use lsp_types::LanguageString;
+use lsp_types::Location;
+use lsp_types::MarkedString;
+use lsp_types::Range;
+use lsp_types::TextDocumentPositionParams;
+use lsp_types::TextEdit;
+use std::path::PathBuf;
+
+#[derive(Debug, Deserialize)]
+struct TsPosition {
+  line: u64,
+  character: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TsSpan {
+  file_name: String,
+  start: TsPosition,
+  end: TsPosition,
+}
+
+impl TsSpan {
+  fn range(&self) -> Range {
+    Range::new(
+      utils::to_position(self.start.line, self.start.character),
+      utils::to_position(self.end.line, self.end.character),
+    )
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TsQuickInfo {
+  display_string: String,
+  documentation: String,
+  #[serde(flatten)]
+  span: TsSpan,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TsCompletionEntry {
+  name: String,
+  kind: String,
+  sort_text: String,
+}
+
+/// Map a `ts.ScriptElementKind` to the kind of a completion item.
+fn to_completion_item_kind(kind: &str) -> CompletionItemKind {
+  match kind {
+    "primitive type" | "keyword" => CompletionItemKind::Keyword,
+    "var" | "let" | "local var" | "parameter" => CompletionItemKind::Variable,
+    "const" => CompletionItemKind::Constant,
+    "property" | "getter" | "setter" => CompletionItemKind::Field,
+    "function" | "local function" => CompletionItemKind::Function,
+    "method" | "construct" | "call" | "index" => CompletionItemKind::Method,
+    "enum" => CompletionItemKind::Enum,
+    "enum member" => CompletionItemKind::EnumMember,
+    "module" | "external module name" => CompletionItemKind::Module,
+    "class" | "type" => CompletionItemKind::Class,
+    "interface" => CompletionItemKind::Interface,
+    "type parameter" => CompletionItemKind::TypeParameter,
+    "directory" => CompletionItemKind::Folder,
+    "script" => CompletionItemKind::File,
+    _ => CompletionItemKind::Property,
+  }
+}
+
+fn to_request_args(
+  params: &TextDocumentPositionParams,
+) -> Result<(ModuleSpecifier, u64, u64), AnyError> {
+  let specifier = utils::uri_to_specifier(&params.text_document.uri)?;
+  Ok((
+    specifier,
+    params.position.line as u64,
+    params.position.character as u64,
+  ))
+}
+
+pub fn handle_hover(
+  state: &mut ServerState,
+  params: HoverParams,
+) -> Result<Option<Hover>, AnyError> {
+  let (specifier, line, character) =
+    to_request_args(&params.text_document_position_params)?;
+  let response = state.language_service_request(
+    &specifier,
+    LanguageServiceRequest::GetQuickInfo {
+      specifier: specifier.to_string(),
+      line,
+      character,
+    },
+  )?;
+  let quick_info: Option<TsQuickInfo> = serde_json::from_value(response)?;
+  Ok(quick_info.map(|quick_info| {
+    let mut contents = vec![MarkedString::LanguageString(LanguageString {
+      language: "typescript".to_string(),
+      value: quick_info.display_string,
+    })];
+    if !quick_info.documentation.is_empty() {
+      contents.push(MarkedString::String(quick_info.documentation));
+    }
+    Hover {
+      contents: HoverContents::Array(contents),
+      range: Some(quick_info.span.range()),
+    }
+  }))
+}
+
+pub fn handle_completion(
+  state: &mut ServerState,
+  params: CompletionParams,
+) -> Result<Option<CompletionResponse>, AnyError> {
+  let (specifier, line, character) =
+    to_request_args(&params.text_document_position)?;
+  let response = state.language_service_request(
+    &specifier,
+    LanguageServiceRequest::GetCompletions {
+      specifier: specifier.to_string(),
+      line,
+      character,
+    },
+  )?;
+  let entries: Vec<TsCompletionEntry> = serde_json::from_value(response)?;
+  let items = entries
+    .into_iter()
+    .map(|entry| CompletionItem {
+      kind: Some(to_completion_item_kind(&entry.kind)),
+      label: entry.name,
+      sort_text: Some(entry.sort_text),
+      ..Default::default()
+    })
+    .collect();
+  Ok(Some(CompletionResponse::Array(items)))
+}
+
+pub fn handle_goto_definition(
+  state: &mut ServerState,
+  params: GotoDefinitionParams,
+) -> Result<Option<GotoDefinitionResponse>, AnyError> {
+  let (specifier, line, character) =
+    to_request_args(&params.text_document_position_params)?;
+  let response = state.language_service_request(
+    &specifier,
+    LanguageServiceRequest::GetDefinition {
+      specifier: specifier.to_string(),
+      line,
+      character,
+    },
+  )?;
+  let definitions: Vec<TsSpan> = serde_json::from_value(response)?;
+  // Definitions in the bundled type libraries (`asset:///`) can't be opened
+  // by the client, so they are skipped.
+  let locations: Vec<Location> = definitions
+    .iter()
+    .filter_map(|definition| {
+      let specifier =
+        ModuleSpecifier::resolve_url(&definition.file_name).ok()?;
+      let uri = state.specifier_to_uri(&specifier)?;
+      Some(Location::new(uri, definition.range()))
+    })
+    .collect();
+  if locations.is_empty() {
+    Ok(None)
+  } else {
+    Ok(Some(GotoDefinitionResponse::Array(locations)))
+  }
+}
+
+pub fn handle_formatting(
+  state: &mut ServerState,
+  params: DocumentFormattingParams,
+) -> Result<Option<Vec<TextEdit>>, AnyError> {
+  let specifier = utils::uri_to_specifier(&params.text_document.uri)?;
+  let text = state.documents().get_text(&specifier).ok_or_else(|| {
+    custom_error("NotFound", format!("Document not open: {}", specifier))
+  })?;
+  let file_name = PathBuf::from(specifier.as_url().path());
+  let formatted = fmt::format_text(&file_name, text)?;
+  if formatted == text {
+    return Ok(None);
+  }
+  Ok(Some(vec![TextEdit::new(
+    utils::full_range(text),
+    formatted,
+  )]))
+}
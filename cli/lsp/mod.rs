@@ -0,0 +1,66 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! The Deno language server, started with `deno lsp`.  It speaks the
+//! Language Server Protocol over stdio and reuses the module graph, the
+//! TypeScript compiler, the linter and the formatter of the CLI.
+
+mod capabilities;
+mod diagnostics;
+mod documents;
+mod handlers;
+mod state;
+mod utils;
+
+use crate::version;
+use deno_core::error::AnyError;
+use deno_core::serde_json;
+use lsp_server::Connection;
+use lsp_types::InitializeParams;
+use lsp_types::InitializeResult;
+use lsp_types::ServerInfo;
+use state::ServerState;
+
+pub fn start() -> Result<(), AnyError> {
+  info!("Starting Deno language server...");
+
+  let (connection, io_threads) = Connection::stdio();
+  let (initialize_id, initialize_params) = connection.initialize_start()?;
+  let initialize_params: InitializeParams =
+    serde_json::from_value(initialize_params)?;
+
+  let capabilities =
+    capabilities::server_capabilities(&initialize_params.capabilities);
+
+  let version = format!(
+    "{} ({}, {})",
+    version::DENO,
+    env!("PROFILE"),
+    env!("TARGET")
+  );
+  info!("  version: {}", version);
+
+  let initialize_result = InitializeResult {
+    capabilities,
+    server_info: Some(ServerInfo {
+      name: "deno-language-server".to_string(),
+      version: Some(version),
+    }),
+  };
+  let initialize_result = serde_json::to_value(initialize_result)?;
+  connection.initialize_finish(initialize_id, initialize_result)?;
+
+  if let Some(client_info) = &initialize_params.client_info {
+    info!(
+      "Connected to \"{}\" {}",
+      client_info.name,
+      client_info.version.clone().unwrap_or_default()
+    );
+  }
+
+  let mut server_state = ServerState::new(initialize_params)?;
+  server_state.run(&connection)?;
+  io_threads.join()?;
+
+  info!("Stop language server");
+  Ok(())
+}
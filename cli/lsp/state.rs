@@ -0,0 +1,511 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use super::diagnostics;
+use super::documents::DocumentCache;
+use super::handlers;
+use super::utils;
+
+use crate::file_fetcher::SourceFile;
+use crate::flags::Flags;
+use crate::js;
+use crate::media_type::MediaType;
+use crate::module_graph::Graph;
+use crate::module_graph::GraphBuilder;
+use crate::module_graph::TypeLib;
+use crate::permissions::Permissions;
+use crate::program_state::ProgramState;
+use crate::specifier_handler::FetchHandler;
+use crate::tokio_util;
+use crate::tsc;
+use crate::tsc_config::TsConfig;
+
+use deno_core::error::AnyError;
+use deno_core::serde::de::DeserializeOwned;
+use deno_core::serde::Deserialize;
+use deno_core::serde::Serialize;
+use deno_core::serde_json;
+use deno_core::serde_json::json;
+use deno_core::serde_json::Value;
+use deno_core::ModuleSpecifier;
+use lsp_server::Connection;
+use lsp_server::ErrorCode;
+use lsp_server::Message;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::Response;
+use lsp_types::notification::DidChangeConfiguration;
+use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidCloseTextDocument;
+use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::DidSaveTextDocument;
+use lsp_types::notification::Notification as _;
+use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::Completion;
+use lsp_types::request::Formatting;
+use lsp_types::request::GotoDefinition;
+use lsp_types::request::HoverRequest;
+use lsp_types::InitializeParams;
+use lsp_types::PublishDiagnosticsParams;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Settings of the language server that can be provided by the client, either
+/// as `initializationOptions` or through the `deno` section of the workspace
+/// configuration.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+  /// Path to a `tsconfig.json` that should be applied to all modules.
+  pub config: Option<String>,
+  /// Path to an import map, resolved relative to the workspace root.
+  pub import_map: Option<String>,
+  /// Provide lint diagnostics along with the TypeScript diagnostics.
+  pub lint: bool,
+  /// Type check with the unstable Deno APIs.
+  pub unstable: bool,
+}
+
+pub struct ServerState {
+  documents: DocumentCache,
+  language_service: tsc::LanguageService,
+  maybe_root_path: Option<PathBuf>,
+  /// Documents whose diagnostics have to be published once the client stops
+  /// sending messages, so that a burst of changes is only checked once.
+  pending_diagnostics: HashSet<ModuleSpecifier>,
+  program_state: Arc<ProgramState>,
+  runtime: tokio::runtime::Runtime,
+  settings: Settings,
+  outgoing: Vec<Message>,
+}
+
+impl ServerState {
+  pub fn new(params: InitializeParams) -> Result<Self, AnyError> {
+    let maybe_root_path = params
+      .root_uri
+      .as_ref()
+      .and_then(|uri| uri.to_file_path().ok());
+    let settings = match params.initialization_options {
+      Some(value) => serde_json::from_value(value)?,
+      None => Settings::default(),
+    };
+    let language_service =
+      tsc::LanguageService::new(js::compiler_isolate_init())?;
+    let program_state =
+      ProgramState::new(create_flags(&settings, &maybe_root_path))?;
+
+    Ok(ServerState {
+      documents: DocumentCache::default(),
+      language_service,
+      maybe_root_path,
+      pending_diagnostics: HashSet::new(),
+      program_state,
+      runtime: tokio_util::create_basic_runtime(),
+      settings,
+      outgoing: Vec::new(),
+    })
+  }
+
+  /// Process messages from the client until the client requests a shutdown
+  /// and exits.
+  pub fn run(&mut self, connection: &Connection) -> Result<(), AnyError> {
+    for message in &connection.receiver {
+      match message {
+        Message::Request(request) => {
+          if connection.handle_shutdown(&request)? {
+            return Ok(());
+          }
+          self.on_request(request);
+        }
+        Message::Notification(notification) => {
+          // A notification can't be answered with an error, and one which
+          // fails mustn't end the server.
+          let method = notification.method.clone();
+          if let Err(err) = self.on_notification(notification) {
+            error!("Failed to handle notification \"{}\": {}", method, err);
+          }
+        }
+        Message::Response(response) => {
+          debug!("Received response: {:?}", response);
+        }
+      }
+      if connection.receiver.is_empty() {
+        self.publish_pending_diagnostics();
+      }
+      for message in self.outgoing.drain(..) {
+        connection.sender.send(message)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn on_request(&mut self, request: Request) {
+    RequestDispatcher {
+      request: Some(request),
+      state: self,
+    }
+    .on::<Completion>(handlers::handle_completion)
+    .on::<Formatting>(handlers::handle_formatting)
+    .on::<GotoDefinition>(handlers::handle_goto_definition)
+    .on::<HoverRequest>(handlers::handle_hover)
+    .finish();
+  }
+
+  fn on_notification(
+    &mut self,
+    notification: Notification,
+  ) -> Result<(), AnyError> {
+    let notification = match notification.extract(DidOpenTextDocument::METHOD) {
+      Ok(params) => {
+        let params: lsp_types::DidOpenTextDocumentParams = params;
+        let specifier = utils::uri_to_specifier(&params.text_document.uri)?;
+        self.documents.open(
+          specifier.clone(),
+          params.text_document.version as i64,
+          params.text_document.text,
+        );
+        self.pending_diagnostics.insert(specifier);
+        return Ok(());
+      }
+      Err(notification) => notification,
+    };
+    let notification = match notification.extract(DidChangeTextDocument::METHOD)
+    {
+      Ok(params) => {
+        let params: lsp_types::DidChangeTextDocumentParams = params;
+        let specifier = utils::uri_to_specifier(&params.text_document.uri)?;
+        // The server requested full document synchronization, so the last
+        // change contains the complete content of the document.
+        if let Some(change) = params.content_changes.into_iter().last() {
+          self.documents.change(
+            &specifier,
+            params.text_document.version as i64,
+            change.text,
+          );
+        }
+        self.pending_diagnostics.insert(specifier);
+        return Ok(());
+      }
+      Err(notification) => notification,
+    };
+    let notification = match notification.extract(DidSaveTextDocument::METHOD) {
+      Ok(params) => {
+        let params: lsp_types::DidSaveTextDocumentParams = params;
+        // Saving a document can change the diagnostics of other open
+        // documents which depend on it.
+        let specifier = utils::uri_to_specifier(&params.text_document.uri)?;
+        self.invalidate_local_file(&specifier);
+        let specifiers: Vec<ModuleSpecifier> = self
+          .documents
+          .iter()
+          .map(|(s, _)| s.clone())
+          .filter(|s| s != &specifier)
+          .collect();
+        self.pending_diagnostics.extend(specifiers);
+        return Ok(());
+      }
+      Err(notification) => notification,
+    };
+    let notification = match notification.extract(DidCloseTextDocument::METHOD)
+    {
+      Ok(params) => {
+        let params: lsp_types::DidCloseTextDocumentParams = params;
+        let specifier = utils::uri_to_specifier(&params.text_document.uri)?;
+        self.documents.close(&specifier);
+        self.pending_diagnostics.remove(&specifier);
+        // The content on disk is used again for modules which import it.
+        self.invalidate_local_file(&specifier);
+        // Clear the diagnostics of the closed document.
+        self.send_notification::<PublishDiagnostics>(
+          PublishDiagnosticsParams {
+            uri: params.text_document.uri,
+            diagnostics: Vec::new(),
+            version: None,
+          },
+        );
+        return Ok(());
+      }
+      Err(notification) => notification,
+    };
+    let notification =
+      match notification.extract(DidChangeConfiguration::METHOD) {
+        Ok(params) => {
+          let params: lsp_types::DidChangeConfigurationParams = params;
+          let settings = match params.settings {
+            Value::Object(mut settings) if settings.contains_key("deno") => {
+              settings.remove("deno").unwrap()
+            }
+            settings => settings,
+          };
+          match serde_json::from_value(settings) {
+            Ok(settings) => self.settings = settings,
+            Err(err) => {
+              error!("Invalid settings: {}", err);
+              return Ok(());
+            }
+          }
+          self.program_state = ProgramState::new(create_flags(
+            &self.settings,
+            &self.maybe_root_path,
+          ))?;
+          return Ok(());
+        }
+        Err(notification) => notification,
+      };
+    if !notification.method.starts_with("$/") {
+      debug!("Unhandled notification: {}", notification.method);
+    }
+    Ok(())
+  }
+
+  fn send_notification<N: lsp_types::notification::Notification>(
+    &mut self,
+    params: N::Params,
+  ) where
+    N::Params: Serialize,
+  {
+    let notification = Notification::new(N::METHOD.to_string(), params);
+    self.outgoing.push(notification.into());
+  }
+
+  fn publish_pending_diagnostics(&mut self) {
+    let specifiers: Vec<ModuleSpecifier> =
+      self.pending_diagnostics.drain().collect();
+    for specifier in specifiers {
+      if let Err(err) = self.publish_diagnostics(&specifier) {
+        error!("Failed to publish diagnostics for {}: {}", specifier, err);
+      }
+    }
+  }
+
+  fn publish_diagnostics(
+    &mut self,
+    specifier: &ModuleSpecifier,
+  ) -> Result<(), AnyError> {
+    let text = match self.documents.get_text(specifier) {
+      Some(text) => text.to_string(),
+      None => return Ok(()),
+    };
+    let mut diagnostics = match self.build_graph(specifier) {
+      Ok(graph) => diagnostics::generate_ts_diagnostics(
+        graph,
+        specifier,
+        self.lib(),
+        self.settings.config.clone(),
+      )?,
+      // Errors while building the graph are usually unresolvable or
+      // uncached imports.
+      Err(err) => vec![diagnostics::from_error(&err)],
+    };
+    if self.settings.lint {
+      diagnostics
+        .extend(diagnostics::generate_lint_diagnostics(specifier, &text));
+    }
+
+    let uri = lsp_types::Url::parse(specifier.as_str())?;
+    let version = self.documents.get_version(specifier);
+    self.send_notification::<PublishDiagnostics>(PublishDiagnosticsParams {
+      uri,
+      diagnostics,
+      version: version.map(|v| v as _),
+    });
+    Ok(())
+  }
+
+  pub fn documents(&self) -> &DocumentCache {
+    &self.documents
+  }
+
+  /// The type library used for checking the documents.
+  pub fn lib(&self) -> TypeLib {
+    if self.settings.unstable {
+      TypeLib::UnstableDenoWindow
+    } else {
+      TypeLib::DenoWindow
+    }
+  }
+
+  /// Drop the in-memory copy of a local module, so that it is read from disk
+  /// again.
+  fn invalidate_local_file(&self, specifier: &ModuleSpecifier) {
+    if let Ok(path) = specifier.as_url().to_file_path() {
+      self
+        .program_state
+        .file_fetcher
+        .invalidate_local_files(&[path]);
+    }
+  }
+
+  /// Build the module graph of the supplied root module, where the content
+  /// of open documents replaces the content of the files on disk.
+  pub fn build_graph(
+    &mut self,
+    specifier: &ModuleSpecifier,
+  ) -> Result<Graph, AnyError> {
+    let program_state = self.program_state.clone();
+    for (specifier, text) in self.documents.iter() {
+      let url = specifier.as_url().clone();
+      let media_type = MediaType::from(&PathBuf::from(url.path()));
+      program_state.file_fetcher.save_source_file_in_cache(
+        specifier,
+        SourceFile {
+          filename: url.to_file_path().unwrap_or_default(),
+          url,
+          types_header: None,
+          media_type,
+          source_code: text.to_string(),
+        },
+      );
+    }
+    self
+      .runtime
+      .block_on(build_graph(program_state, specifier.clone()))
+  }
+
+  /// Send a request to the language service, which works on the module graph
+  /// of the supplied specifier.
+  pub fn language_service_request(
+    &mut self,
+    specifier: &ModuleSpecifier,
+    request: tsc::LanguageServiceRequest,
+  ) -> Result<Value, AnyError> {
+    let graph = self.build_graph(specifier)?;
+    let mut config = TsConfig::new(json!({
+      "allowJs": true,
+      "esModuleInterop": true,
+      "experimentalDecorators": true,
+      "isolatedModules": true,
+      "jsx": "react",
+      "lib": self.lib(),
+      "module": "esnext",
+      "noEmit": true,
+      "strict": true,
+      "target": "esnext",
+    }));
+    config.merge_tsconfig(self.settings.config.clone())?;
+    let debug = log::max_level() >= log::LevelFilter::Debug;
+    self.language_service.request(config, debug, graph, request)
+  }
+
+  /// Map a specifier returned by the language service to a URI the client can
+  /// open.  Remote modules are mapped to their location in the cache of
+  /// `DENO_DIR`.
+  pub fn specifier_to_uri(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> Option<lsp_types::Url> {
+    let url = specifier.as_url();
+    match url.scheme() {
+      "file" => lsp_types::Url::parse(url.as_str()).ok(),
+      "http" | "https" => {
+        let path = self
+          .program_state
+          .file_fetcher
+          .http_cache
+          .get_cache_filename(url);
+        lsp_types::Url::from_file_path(path).ok()
+      }
+      _ => None,
+    }
+  }
+}
+
+fn create_flags(
+  settings: &Settings,
+  maybe_root_path: &Option<PathBuf>,
+) -> Flags {
+  // Remote modules are only read from the cache of `DENO_DIR`, they are
+  // downloaded by `deno cache`.
+  let mut flags = Flags {
+    cached_only: true,
+    config_path: settings.config.clone(),
+    unstable: settings.unstable,
+    ..Default::default()
+  };
+  if let Some(import_map) = &settings.import_map {
+    // Import maps are unstable, and `ProgramState` would exit the process
+    // if it was given one without the unstable flag.
+    if settings.unstable {
+      let import_map = match maybe_root_path {
+        Some(root_path) => root_path.join(import_map),
+        None => PathBuf::from(import_map),
+      };
+      flags.import_map_path = Some(import_map.to_string_lossy().to_string());
+    } else {
+      warn!("Import maps require the \"unstable\" setting, ignoring it.");
+    }
+  }
+  flags
+}
+
+async fn build_graph(
+  program_state: Arc<ProgramState>,
+  specifier: ModuleSpecifier,
+) -> Result<Graph, AnyError> {
+  let handler = Rc::new(RefCell::new(FetchHandler::new(
+    &program_state,
+    Permissions::allow_all(),
+  )?));
+  let mut builder = GraphBuilder::new(
+    handler,
+    program_state.maybe_import_map.clone(),
+    program_state.lockfile.clone(),
+  );
+  builder.add(&specifier, false).await?;
+  Ok(builder.get_graph())
+}
+
+/// Dispatches a request to the handler for its method, replying with the
+/// result of the handler.
+struct RequestDispatcher<'a> {
+  request: Option<Request>,
+  state: &'a mut ServerState,
+}
+
+impl<'a> RequestDispatcher<'a> {
+  fn on<R>(
+    &mut self,
+    handler: fn(&mut ServerState, R::Params) -> Result<R::Result, AnyError>,
+  ) -> &mut Self
+  where
+    R: lsp_types::request::Request,
+    R::Params: DeserializeOwned,
+    R::Result: Serialize,
+  {
+    let request = match self.request.take() {
+      Some(request) => request,
+      None => return self,
+    };
+    match request.extract::<R::Params>(R::METHOD) {
+      Ok((id, params)) => {
+        let response = match handler(self.state, params) {
+          Ok(result) => Response::new_ok(id, result),
+          Err(err) => {
+            error!("{} failed: {}", R::METHOD, err);
+            Response::new_err(
+              id,
+              ErrorCode::InternalError as i32,
+              err.to_string(),
+            )
+          }
+        };
+        self.state.outgoing.push(response.into());
+      }
+      Err(request) => self.request = Some(request),
+    }
+    self
+  }
+
+  fn finish(&mut self) {
+    if let Some(request) = self.request.take() {
+      let response = Response::new_err(
+        request.id,
+        ErrorCode::MethodNotFound as i32,
+        format!("Unknown request: {}", request.method),
+      );
+      self.state.outgoing.push(response.into());
+    }
+  }
+}
@@ -0,0 +1,50 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use deno_core::error::AnyError;
+use deno_core::ModuleSpecifier;
+use lsp_types::Position;
+use lsp_types::Range;
+use lsp_types::Url;
+
+/// Convert a document URI of the client into a module specifier.
+pub fn uri_to_specifier(uri: &Url) -> Result<ModuleSpecifier, AnyError> {
+  Ok(ModuleSpecifier::resolve_url(uri.as_str())?)
+}
+
+/// Convert a position of the TypeScript compiler or the linter into a
+/// position of the Language Server Protocol.  Both count lines and
+/// characters from zero.
+pub fn to_position(line: u64, character: u64) -> Position {
+  Position::new(line as _, character as _)
+}
+
+/// Returns the range that covers the whole text, which is used to replace
+/// the content of a document.
+pub fn full_range(text: &str) -> Range {
+  let lines: Vec<&str> = text.split('\n').collect();
+  let last_line = lines.last().unwrap();
+  Range::new(
+    to_position(0, 0),
+    to_position(
+      (lines.len() - 1) as u64,
+      last_line.encode_utf16().count() as u64,
+    ),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_full_range() {
+    assert_eq!(
+      full_range("const a = 1;\nconst b = \"ü\";"),
+      Range::new(to_position(0, 0), to_position(1, 14))
+    );
+    assert_eq!(
+      full_range("a\n"),
+      Range::new(to_position(0, 0), to_position(1, 0))
+    );
+  }
+}
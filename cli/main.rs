@@ -8,6 +8,7 @@ extern crate lazy_static;
 extern crate log;
 
 mod ast;
+mod auth_tokens;
 mod checksum;
 mod colors;
 mod coverage;
@@ -33,11 +34,14 @@ mod installer;
 mod js;
 mod lint;
 mod lockfile;
+mod lsp;
 mod media_type;
 mod metrics;
 mod module_graph;
 mod module_loader;
 mod ops;
+mod permission_policy;
+mod permission_trace;
 mod permissions;
 mod program_state;
 mod repl;
@@ -45,6 +49,7 @@ mod resolve_addr;
 mod signal;
 mod source_maps;
 mod specifier_handler;
+mod standalone;
 mod test_runner;
 mod text_encoding;
 mod tokio_util;
@@ -54,15 +59,17 @@ mod upgrade;
 mod version;
 mod worker;
 
-use crate::coverage::CoverageCollector;
-use crate::coverage::PrettyCoverageReporter;
+use crate::coverage::CoverageSummary;
+use crate::coverage::FileCoverage;
 use crate::file_fetcher::SourceFile;
 use crate::file_fetcher::SourceFileFetcher;
 use crate::fs as deno_fs;
 use crate::media_type::MediaType;
 use crate::permissions::Permissions;
 use crate::program_state::ProgramState;
+use crate::source_maps::SourceMapGetter;
 use crate::specifier_handler::FetchHandler;
+use crate::test_runner::TestOptions;
 use crate::worker::MainWorker;
 use deno_core::error::generic_error;
 use deno_core::error::AnyError;
@@ -75,6 +82,8 @@ use deno_core::v8_set_flags;
 use deno_core::ModuleSpecifier;
 use deno_doc as doc;
 use deno_doc::parser::DocFileLoader;
+use deno_fetch::proxy;
+use deno_fetch::proxy::ProxyConfig;
 use flags::DenoSubcommand;
 use flags::Flags;
 use import_map::ImportMap;
@@ -82,6 +91,8 @@ use log::Level;
 use log::LevelFilter;
 use program_state::exit_unstable;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::env;
 use std::io::Read;
 use std::io::Write;
@@ -119,12 +130,22 @@ fn print_cache_info(
   let deno_dir = &state.dir.root;
   let modules_cache = &state.file_fetcher.http_cache.location;
   let typescript_cache = &state.dir.gen_cache.location;
+  // The proxy settings are only shown if there are any.
+  let proxy_config = ProxyConfig::from_env()?;
+  let has_proxy_config = proxy_config != ProxyConfig::default();
   if json {
-    let output = json!({
+    let mut output = json!({
         "denoDir": deno_dir,
         "modulesCache": modules_cache,
         "typescriptCache": typescript_cache,
     });
+    if has_proxy_config {
+      output["proxy"] = json!({
+        "http": proxy_config.http.as_ref().map(proxy::redact),
+        "https": proxy_config.https.as_ref().map(proxy::redact),
+        "noProxy": proxy_config.no_proxy,
+      });
+    }
     write_json_to_stdout(&output)
   } else {
     println!("{} {:?}", colors::bold("DENO_DIR location:"), deno_dir);
@@ -138,6 +159,9 @@ fn print_cache_info(
       colors::bold("TypeScript compiler cache:"),
       typescript_cache
     );
+    if has_proxy_config {
+      println!("{} {}", colors::bold("Proxy:"), proxy_config);
+    }
     Ok(())
   }
 }
@@ -208,7 +232,7 @@ async fn install_command(
   let mut preload_flags = flags.clone();
   preload_flags.inspect = None;
   preload_flags.inspect_brk = None;
-  let permissions = Permissions::from_flags(&preload_flags);
+  let permissions = Permissions::from_flags(&preload_flags)?;
   let program_state = ProgramState::new(preload_flags)?;
   let main_module = ModuleSpecifier::resolve_url_or_path(&module_url)?;
   let mut worker =
@@ -273,7 +297,7 @@ async fn eval_command(
   // Force TypeScript compile.
   let main_module =
     ModuleSpecifier::resolve_url_or_path("./$deno$eval.ts").unwrap();
-  let permissions = Permissions::from_flags(&flags);
+  let permissions = Permissions::from_flags(&flags)?;
   let program_state = ProgramState::new(flags)?;
   let mut worker =
     MainWorker::new(&program_state, main_module.clone(), permissions);
@@ -310,22 +334,11 @@ async fn eval_command(
   Ok(())
 }
 
-async fn bundle_command(
-  flags: Flags,
-  source_file: String,
-  out_file: Option<PathBuf>,
-) -> Result<(), AnyError> {
-  let module_specifier = ModuleSpecifier::resolve_url_or_path(&source_file)?;
-
-  debug!(">>>>> bundle START");
-  let program_state = ProgramState::new(flags.clone())?;
-
-  info!(
-    "{} {}",
-    colors::green("Bundle"),
-    module_specifier.to_string()
-  );
-
+async fn create_module_graph_and_maybe_check(
+  module_specifier: ModuleSpecifier,
+  program_state: Arc<ProgramState>,
+  debug: bool,
+) -> Result<module_graph::Graph, AnyError> {
   let handler = Rc::new(RefCell::new(FetchHandler::new(
     &program_state,
     // when bundling, dynamic imports are only access for their type safety,
@@ -340,21 +353,19 @@ async fn bundle_command(
   builder.add(&module_specifier, false).await?;
   let graph = builder.get_graph();
 
-  let debug = flags.log_level == Some(log::Level::Debug);
-  if !flags.no_check {
+  if !program_state.flags.no_check {
     // TODO(@kitsonk) support bundling for workers
-    let lib = if flags.unstable {
+    let lib = if program_state.flags.unstable {
       module_graph::TypeLib::UnstableDenoWindow
     } else {
       module_graph::TypeLib::DenoWindow
     };
-    let graph = graph.clone();
-    let result_info = graph.check(module_graph::CheckOptions {
+    let result_info = graph.clone().check(module_graph::CheckOptions {
       debug,
       emit: false,
       lib,
-      maybe_config_path: flags.config_path.clone(),
-      reload: flags.reload,
+      maybe_config_path: program_state.flags.config_path.clone(),
+      reload: program_state.flags.reload,
     })?;
 
     debug!("{}", result_info.stats);
@@ -366,8 +377,16 @@ async fn bundle_command(
     }
   }
 
+  Ok(graph)
+}
+
+fn bundle_module_graph(
+  module_graph: module_graph::Graph,
+  flags: Flags,
+  debug: bool,
+) -> Result<String, AnyError> {
   let (output, stats, maybe_ignored_options) =
-    graph.bundle(module_graph::BundleOptions {
+    module_graph.bundle(module_graph::BundleOptions {
       debug,
       maybe_config_path: flags.config_path,
     })?;
@@ -377,6 +396,33 @@ async fn bundle_command(
     eprintln!("{}", ignored_options);
   }
   debug!("{}", stats);
+  Ok(output)
+}
+
+async fn bundle_command(
+  flags: Flags,
+  source_file: String,
+  out_file: Option<PathBuf>,
+) -> Result<(), AnyError> {
+  let debug = flags.log_level == Some(log::Level::Debug);
+  let module_specifier = ModuleSpecifier::resolve_url_or_path(&source_file)?;
+
+  debug!(">>>>> bundle START");
+  let program_state = ProgramState::new(flags.clone())?;
+
+  info!(
+    "{} {}",
+    colors::green("Bundle"),
+    module_specifier.to_string()
+  );
+
+  let module_graph = create_module_graph_and_maybe_check(
+    module_specifier,
+    program_state.clone(),
+    debug,
+  )
+  .await?;
+  let output = bundle_module_graph(module_graph, flags, debug)?;
 
   debug!(">>>>> bundle END");
 
@@ -396,6 +442,53 @@ async fn bundle_command(
   Ok(())
 }
 
+async fn compile_command(
+  flags: Flags,
+  source_file: String,
+  output: Option<PathBuf>,
+) -> Result<(), AnyError> {
+  if !flags.unstable {
+    exit_unstable("compile");
+  }
+
+  let debug = flags.log_level == Some(log::Level::Debug);
+  let module_specifier = ModuleSpecifier::resolve_url_or_path(&source_file)?;
+  let program_state = ProgramState::new(flags.clone())?;
+
+  let output = output
+    .or_else(|| {
+      installer::infer_name_from_url(module_specifier.as_url())
+        .map(PathBuf::from)
+    })
+    .ok_or_else(|| {
+      generic_error("An executable name was not provided. One could not be inferred from the URL. Aborting.")
+    })?;
+
+  info!(
+    "{} {}",
+    colors::green("Bundle"),
+    module_specifier.to_string()
+  );
+  let module_graph = create_module_graph_and_maybe_check(
+    module_specifier.clone(),
+    program_state.clone(),
+    debug,
+  )
+  .await?;
+  let bundle_str = bundle_module_graph(module_graph, flags.clone(), debug)?;
+
+  info!(
+    "{} {}",
+    colors::green("Compile"),
+    module_specifier.to_string()
+  );
+  let output =
+    standalone::create_standalone_binary(bundle_str, &flags, output)?;
+  info!("{} {}", colors::green("Emit"), output.display());
+
+  Ok(())
+}
+
 struct DocLoader {
   fetcher: SourceFileFetcher,
   maybe_import_map: Option<ImportMap>,
@@ -520,7 +613,7 @@ async fn doc_command(
 async fn run_repl(flags: Flags) -> Result<(), AnyError> {
   let main_module =
     ModuleSpecifier::resolve_url_or_path("./$deno$repl.ts").unwrap();
-  let permissions = Permissions::from_flags(&flags);
+  let permissions = Permissions::from_flags(&flags)?;
   let program_state = ProgramState::new(flags)?;
   let mut worker =
     MainWorker::new(&program_state, main_module.clone(), permissions);
@@ -531,7 +624,7 @@ async fn run_repl(flags: Flags) -> Result<(), AnyError> {
 
 async fn run_from_stdin(flags: Flags) -> Result<(), AnyError> {
   let program_state = ProgramState::new(flags.clone())?;
-  let permissions = Permissions::from_flags(&flags);
+  let permissions = Permissions::from_flags(&flags)?;
   let main_module =
     ModuleSpecifier::resolve_url_or_path("./$deno$stdin.ts").unwrap();
   let mut worker =
@@ -562,50 +655,71 @@ async fn run_from_stdin(flags: Flags) -> Result<(), AnyError> {
   Ok(())
 }
 
+/// Returns the `ProgramState` to use for a restart in `--watch` mode, once
+/// the state depending on the changed files was invalidated.
+fn refresh_program_state(
+  program_state: &RefCell<Arc<ProgramState>>,
+  maybe_changed_paths: Option<Vec<PathBuf>>,
+) -> Result<Arc<ProgramState>, AnyError> {
+  if let Some(changed_paths) = maybe_changed_paths {
+    let new_program_state =
+      program_state.borrow().invalidate_files(&changed_paths)?;
+    *program_state.borrow_mut() = new_program_state;
+  }
+  Ok(program_state.borrow().clone())
+}
+
 async fn run_with_watch(flags: Flags, script: String) -> Result<(), AnyError> {
   let main_module = ModuleSpecifier::resolve_url_or_path(&script)?;
-  let program_state = ProgramState::new(flags.clone())?;
-
-  let handler = Rc::new(RefCell::new(FetchHandler::new(
-    &program_state,
-    Permissions::allow_all(),
-  )?));
-  let mut builder = module_graph::GraphBuilder::new(
-    handler,
-    program_state.maybe_import_map.clone(),
-    program_state.lockfile.clone(),
-  );
-  builder.add(&main_module, false).await?;
-  let module_graph = builder.get_graph();
-
-  // Find all local files in graph
-  let mut paths_to_watch: Vec<PathBuf> = module_graph
-    .get_modules()
-    .iter()
-    .filter(|specifier| specifier.as_url().scheme() == "file")
-    .map(|specifier| specifier.as_url().to_file_path().unwrap())
-    .collect();
+  let program_state = Rc::new(RefCell::new(ProgramState::new(flags.clone())?));
+  let maybe_import_map_path = match flags.import_map_path.as_ref() {
+    Some(path) => Some(fs::resolve_from_cwd(std::path::Path::new(path))?),
+    None => None,
+  };
 
-  if let Some(import_map) = program_state.flags.import_map_path.clone() {
-    paths_to_watch
-      .push(fs::resolve_from_cwd(std::path::Path::new(&import_map)).unwrap());
-  }
+  let paths_resolver = {
+    let program_state = program_state.clone();
+    let main_module = main_module.clone();
+    move || {
+      // Only the local files of the modules which were actually loaded are
+      // watched, including dynamically imported ones.
+      let mut paths_to_watch = program_state.borrow().get_loaded_files();
+      if let Ok(path) = main_module.as_url().to_file_path() {
+        paths_to_watch.push(path);
+      }
+      if let Some(import_map_path) = maybe_import_map_path.clone() {
+        paths_to_watch.push(import_map_path);
+      }
+      paths_to_watch
+    }
+  };
 
-  // FIXME(bartlomieju): new file watcher is created on after each restart
-  file_watcher::watch_func(&paths_to_watch, move || {
-    // FIXME(bartlomieju): ProgramState must be created on each restart - otherwise file fetcher
-    // will use cached source files
-    let gs = ProgramState::new(flags.clone()).unwrap();
+  file_watcher::watch_func(paths_resolver, move |changed_paths| {
     let permissions = Permissions::from_flags(&flags);
     let main_module = main_module.clone();
+    let program_state = program_state.clone();
     async move {
-      let mut worker = MainWorker::new(&gs, main_module.clone(), permissions);
-      debug!("main_module {}", main_module);
-      worker.execute_module(&main_module).await?;
-      worker.execute("window.dispatchEvent(new Event('load'))")?;
-      worker.run_event_loop().await?;
-      worker.execute("window.dispatchEvent(new Event('unload'))")?;
-      Ok(())
+      let permissions = permissions?;
+      let gs = refresh_program_state(&program_state, changed_paths)?;
+      // The files to watch are recomputed from the modules loaded by this
+      // run. If it fails, the files of the previous run stay watched as well,
+      // so that fixing an error in one of them restarts it.
+      let previous_modules =
+        std::mem::take(&mut *gs.loaded_modules.lock().unwrap());
+      let result: Result<(), AnyError> = async {
+        let mut worker = MainWorker::new(&gs, main_module.clone(), permissions);
+        debug!("main_module {}", main_module);
+        worker.execute_module(&main_module).await?;
+        worker.execute("window.dispatchEvent(new Event('load'))")?;
+        worker.run_event_loop().await?;
+        worker.execute("window.dispatchEvent(new Event('unload'))")?;
+        Ok(())
+      }
+      .await;
+      if result.is_err() {
+        gs.loaded_modules.lock().unwrap().extend(previous_modules);
+      }
+      result
     }
     .boxed_local()
   })
@@ -624,30 +738,107 @@ async fn run_command(flags: Flags, script: String) -> Result<(), AnyError> {
 
   let main_module = ModuleSpecifier::resolve_url_or_path(&script)?;
   let program_state = ProgramState::new(flags.clone())?;
-  let permissions = Permissions::from_flags(&flags);
+  let permissions = Permissions::from_flags(&flags)?;
   let mut worker =
     MainWorker::new(&program_state, main_module.clone(), permissions);
   debug!("main_module {}", main_module);
+
+  let mut maybe_coverage_collector =
+    coverage::start_coverage_collector(&program_state, &mut worker).await?;
+
   worker.execute_module(&main_module).await?;
   worker.execute("window.dispatchEvent(new Event('load'))")?;
   worker.run_event_loop().await?;
   worker.execute("window.dispatchEvent(new Event('unload'))")?;
+
+  if let Some(coverage_collector) = maybe_coverage_collector.as_mut() {
+    coverage_collector.stop_collecting().await?;
+  }
+  Ok(())
+}
+
+async fn coverage_command(
+  flags: Flags,
+  files: Vec<PathBuf>,
+  include: Vec<String>,
+  exclude: Vec<String>,
+  reporter: String,
+  output: Option<PathBuf>,
+  threshold: Option<f32>,
+) -> Result<(), AnyError> {
+  if !flags.unstable {
+    exit_unstable("coverage");
+  }
+
+  let program_state = ProgramState::new(flags.clone())?;
+  let coverages = coverage::read_profiles(&files)?;
+  let coverages = coverage::filter_coverages(coverages, include, exclude)?;
+  if coverages.is_empty() {
+    return Err(generic_error("No matching coverage profiles found"));
+  }
+
+  let quiet = flags.log_level == Some(log::Level::Error);
+  let mut coverage_reporter =
+    coverage::create_reporter(&reporter, quiet, output);
+  let mut summary = CoverageSummary::default();
+  for script_coverages in coverages {
+    let url = &script_coverages[0].script_coverage.url;
+    let maybe_source_map = program_state.get_source_map(url);
+    let mut maybe_original_source = ModuleSpecifier::resolve_url(url)
+      .ok()
+      .and_then(|specifier| {
+        program_state
+          .file_fetcher
+          .fetch_cached_source_file(&specifier, Permissions::allow_all())
+      })
+      .map(|source_file| source_file.source_code);
+    // Lines of a source that changed since the coverage was collected can't
+    // be mapped, so the executed script is reported on instead.
+    if let Some(original_source) = &maybe_original_source {
+      let source_hash = checksum::gen(&[original_source.as_bytes()]);
+      let source_changed = script_coverages
+        .iter()
+        .any(|coverage| coverage.source_hash.as_ref() != Some(&source_hash));
+      if source_changed {
+        eprintln!(
+          "Reporting on the executed script of {} as its source changed.",
+          url
+        );
+        maybe_original_source = None;
+      }
+    }
+    let file_coverage = FileCoverage::new(
+      &script_coverages,
+      maybe_source_map,
+      maybe_original_source,
+    );
+    summary.add(&file_coverage.summary());
+    coverage_reporter.visit_coverage(&file_coverage)?;
+  }
+  coverage_reporter.done()?;
+
+  if let Some(threshold) = threshold {
+    summary.check_threshold(threshold)?;
+  }
   Ok(())
 }
 
 async fn test_command(
   flags: Flags,
   include: Option<Vec<String>>,
-  fail_fast: bool,
-  quiet: bool,
   allow_none: bool,
-  filter: Option<String>,
+  shard: Option<(usize, usize)>,
+  options: TestOptions,
 ) -> Result<(), AnyError> {
-  let program_state = ProgramState::new(flags.clone())?;
-  let permissions = Permissions::from_flags(&flags);
   let cwd = std::env::current_dir().expect("No current directory");
   let include = include.unwrap_or_else(|| vec![".".to_string()]);
-  let test_modules = test_runner::prepare_test_modules_urls(include, &cwd)?;
+  let mut test_modules = test_runner::prepare_test_modules_urls(include, &cwd)?;
+  if let Some((index, count)) = shard {
+    test_modules = test_runner::shard_test_modules(test_modules, index, count);
+  }
+  if let Some(seed) = options.shuffle {
+    test_runner::shuffle_test_modules(&mut test_modules, seed);
+  }
 
   if test_modules.is_empty() {
     println!("No matching test modules found");
@@ -657,64 +848,194 @@ async fn test_command(
     return Ok(());
   }
 
-  let test_file_path = cwd.join("$deno$test.ts");
-  let test_file_url =
-    Url::from_file_path(&test_file_path).expect("Should be valid file url");
-  let test_file = test_runner::render_test_file(
-    test_modules.clone(),
-    fail_fast,
-    quiet,
-    filter,
+  if flags.watch {
+    return test_with_watch(flags, test_modules, options).await;
+  }
+
+  let program_state = ProgramState::new(flags.clone())?;
+  let permissions = Permissions::from_flags(&flags)?;
+  let success =
+    test_runner::run_tests(&program_state, permissions, test_modules, options)
+      .await?;
+  if let (Some(coverage_dir), Some(reporter)) =
+    (&flags.coverage_dir, &flags.coverage_reporter)
+  {
+    coverage_command(
+      flags.clone(),
+      vec![PathBuf::from(coverage_dir)],
+      vec![flags::COVERAGE_INCLUDE_DEFAULT.to_string()],
+      vec![flags::COVERAGE_EXCLUDE_DEFAULT.to_string()],
+      reporter.clone(),
+      flags.coverage_output.clone(),
+      None,
+    )
+    .await?;
+  }
+  if !success {
+    std::process::exit(1);
+  }
+  Ok(())
+}
+
+/// Returns the local files each test module depends on, directly or
+/// transitively, including the test module itself.
+async fn get_test_dependencies(
+  program_state: &Arc<ProgramState>,
+  test_modules: &[Url],
+) -> Result<HashMap<Url, HashSet<PathBuf>>, AnyError> {
+  let handler = Rc::new(RefCell::new(FetchHandler::new(
+    program_state,
+    Permissions::allow_all(),
+  )?));
+  let mut builder = module_graph::GraphBuilder::new(
+    handler,
+    program_state.maybe_import_map.clone(),
+    program_state.lockfile.clone(),
   );
-  let main_module =
-    ModuleSpecifier::resolve_url(&test_file_url.to_string()).unwrap();
-  let mut worker =
-    MainWorker::new(&program_state, main_module.clone(), permissions);
-  // Create a dummy source file.
-  let source_file = SourceFile {
-    filename: test_file_url.to_file_path().unwrap(),
-    url: test_file_url.clone(),
-    types_header: None,
-    media_type: MediaType::TypeScript,
-    source_code: test_file.clone(),
-  };
-  // Save our fake file into file fetcher cache
-  // to allow module access by TS compiler
-  program_state
-    .file_fetcher
-    .save_source_file_in_cache(&main_module, source_file);
+  for test_module in test_modules {
+    builder
+      .add(&ModuleSpecifier::from(test_module.clone()), false)
+      .await?;
+  }
+  let module_graph = builder.get_graph();
 
-  let mut maybe_coverage_collector = if flags.coverage {
-    let session = worker.create_inspector_session();
-    let mut coverage_collector = CoverageCollector::new(session);
-    coverage_collector.start_collecting().await?;
+  let dependencies = test_modules
+    .iter()
+    .map(|test_module| {
+      let paths = module_graph
+        .get_dependencies(&ModuleSpecifier::from(test_module.clone()))
+        .iter()
+        .filter(|specifier| specifier.as_url().scheme() == "file")
+        .filter_map(|specifier| specifier.as_url().to_file_path().ok())
+        .collect();
+      (test_module.clone(), paths)
+    })
+    .collect();
+  Ok(dependencies)
+}
 
-    Some(coverage_collector)
-  } else {
-    None
+/// Runs the test modules, then reruns the ones which depend on a changed
+/// file whenever one of their local dependencies changes.
+async fn test_with_watch(
+  flags: Flags,
+  test_modules: Vec<Url>,
+  options: TestOptions,
+) -> Result<(), AnyError> {
+  let program_state = Rc::new(RefCell::new(ProgramState::new(flags.clone())?));
+  let maybe_import_map_path = match flags.import_map_path.as_ref() {
+    Some(path) => Some(fs::resolve_from_cwd(std::path::Path::new(path))?),
+    None => None,
+  };
+  // The local dependencies of each test module, as of the last time their
+  // module graph could be built.
+  let dependencies: Rc<RefCell<HashMap<Url, HashSet<PathBuf>>>> =
+    Rc::new(RefCell::new(HashMap::new()));
+  // The test modules which were due to run, but whose run did not finish.
+  let pending_modules: Rc<RefCell<HashSet<Url>>> =
+    Rc::new(RefCell::new(HashSet::new()));
+
+  let paths_resolver = {
+    let program_state = program_state.clone();
+    let dependencies = dependencies.clone();
+    let test_modules = test_modules.clone();
+    move || {
+      // The test modules themselves are always watched, so that a test module
+      // whose module graph could not be built is rerun once it is fixed.
+      let mut paths_to_watch: Vec<PathBuf> = test_modules
+        .iter()
+        .filter_map(|test_module| test_module.to_file_path().ok())
+        .collect();
+      for paths in dependencies.borrow().values() {
+        paths_to_watch.extend(paths.iter().cloned());
+      }
+      // Modules which are only loaded by the tests, e.g. through dynamic
+      // imports, are not part of the module graphs.
+      paths_to_watch.extend(program_state.borrow().get_loaded_files());
+      if let Some(import_map_path) = maybe_import_map_path.clone() {
+        paths_to_watch.push(import_map_path);
+      }
+      paths_to_watch
+    }
   };
 
-  let execute_result = worker.execute_module(&main_module).await;
-  execute_result?;
-  worker.execute("window.dispatchEvent(new Event('load'))")?;
-  worker.run_event_loop().await?;
-  worker.execute("window.dispatchEvent(new Event('unload'))")?;
-  worker.run_event_loop().await?;
-
-  if let Some(coverage_collector) = maybe_coverage_collector.as_mut() {
-    let coverages = coverage_collector.collect().await?;
-    coverage_collector.stop_collecting().await?;
+  file_watcher::watch_func(paths_resolver, move |changed_paths| {
+    let modules_to_run: Vec<Url> = match &changed_paths {
+      Some(changed_paths) => {
+        let dependencies = dependencies.borrow();
+        let pending_modules = pending_modules.borrow();
+        // It is unknown which test modules depend on a file which is not
+        // part of any module graph, like the import map or a dynamically
+        // imported module, so all of them are rerun.
+        let affects_all = changed_paths.iter().any(|changed_path| {
+          !dependencies
+            .values()
+            .any(|paths| paths.contains(changed_path))
+        });
+        test_modules
+          .iter()
+          .filter(|test_module| {
+            affects_all
+              || pending_modules.contains(test_module)
+              || match dependencies.get(test_module) {
+                Some(paths) => changed_paths.iter().any(|p| paths.contains(p)),
+                None => true,
+              }
+          })
+          .cloned()
+          .collect()
+      }
+      None => test_modules.clone(),
+    };
+    let flags = flags.clone();
+    let options = options.clone();
+    let test_modules = test_modules.clone();
+    let program_state = program_state.clone();
+    let dependencies = dependencies.clone();
+    let pending_modules = pending_modules.clone();
 
-    let filtered_coverages =
-      coverage::filter_script_coverages(coverages, test_file_url, test_modules);
+    async move {
+      let program_state = refresh_program_state(&program_state, changed_paths)?;
+      *pending_modules.borrow_mut() = modules_to_run.iter().cloned().collect();
+      let test_dependencies =
+        get_test_dependencies(&program_state, &test_modules).await?;
+      *dependencies.borrow_mut() = test_dependencies;
+      if !modules_to_run.is_empty() {
+        let permissions = Permissions::from_flags(&flags)?;
+        test_runner::run_tests(
+          &program_state,
+          permissions,
+          modules_to_run,
+          options,
+        )
+        .await?;
+      }
+      pending_modules.borrow_mut().clear();
+      Ok(())
+    }
+    .boxed_local()
+  })
+  .await
+}
 
-    let mut coverage_reporter = PrettyCoverageReporter::new(quiet);
-    for coverage in filtered_coverages {
-      coverage_reporter.visit_coverage(&coverage);
+/// Runs the embedded program and exits if the current executable was produced
+/// by `deno compile`.
+fn try_run_standalone_binary(args: Vec<String>) {
+  match standalone::extract_standalone() {
+    Ok(Some((source_code, metadata))) => {
+      let result =
+        tokio_util::run_basic(standalone::run(source_code, metadata, args));
+      if let Err(err) = result {
+        eprintln!("{}: {}", colors::red_bold("error"), err.to_string());
+        std::process::exit(1);
+      }
+      std::process::exit(0);
+    }
+    Ok(None) => {}
+    Err(err) => {
+      eprintln!("{}: {}", colors::red_bold("error"), err.to_string());
+      std::process::exit(1);
     }
   }
-
-  Ok(())
 }
 
 pub fn main() {
@@ -722,6 +1043,7 @@ pub fn main() {
   colors::enable_ansi(); // For Windows 10
 
   let args: Vec<String> = env::args().collect();
+  try_run_standalone_binary(args.clone());
   let flags = flags::flags_from_vec(args);
 
   if let Some(ref v8_flags) = flags.v8_flags {
@@ -780,11 +1102,23 @@ pub fn main() {
   })
   .init();
 
+  if flags.trace_permissions {
+    let maybe_file = flags.trace_permissions_file.as_deref();
+    if let Err(err) = permission_trace::init(maybe_file) {
+      eprintln!("{}: {}", colors::red_bold("error"), err.to_string());
+      std::process::exit(1);
+    }
+  }
+
   let fut = match flags.clone().subcommand {
     DenoSubcommand::Bundle {
       source_file,
       out_file,
     } => bundle_command(flags, source_file, out_file).boxed_local(),
+    DenoSubcommand::Compile {
+      source_file,
+      output,
+    } => compile_command(flags, source_file, output).boxed_local(),
     DenoSubcommand::Doc {
       source_file,
       json,
@@ -830,8 +1164,39 @@ pub fn main() {
       include,
       allow_none,
       filter,
-    } => test_command(flags, include, fail_fast, quiet, allow_none, filter)
-      .boxed_local(),
+      jobs,
+      reporter,
+      reporter_output,
+      shard,
+      shuffle,
+    } => {
+      if let Some(seed) = shuffle {
+        // Printed even with --quiet, so that a failing order can always be
+        // reproduced.
+        eprintln!("Shuffling the test order with --shuffle={}", seed);
+      }
+      let options = TestOptions {
+        fail_fast,
+        quiet,
+        filter,
+        jobs,
+        reporter,
+        reporter_output,
+        shuffle,
+      };
+      test_command(flags, include, allow_none, shard, options).boxed_local()
+    }
+    DenoSubcommand::Coverage {
+      files,
+      include,
+      exclude,
+      reporter,
+      output,
+      threshold,
+    } => coverage_command(
+      flags, files, include, exclude, reporter, output, threshold,
+    )
+    .boxed_local(),
     DenoSubcommand::Completions { buf } => {
       if let Err(e) = write_to_stdout_ignore_sigpipe(&buf) {
         eprintln!("{}", e);
@@ -839,6 +1204,15 @@ pub fn main() {
       }
       return;
     }
+    DenoSubcommand::LanguageServer => {
+      // The language server runs its own event loop, and must not be started
+      // on the runtime below.
+      if let Err(err) = lsp::start() {
+        eprintln!("{}: {}", colors::red_bold("error"), err.to_string());
+        std::process::exit(1);
+      }
+      return;
+    }
     DenoSubcommand::Types => {
       let types = get_types(flags.unstable);
       if let Err(e) = write_to_stdout_ignore_sigpipe(types.as_bytes()) {
@@ -859,6 +1233,7 @@ pub fn main() {
   };
 
   let result = tokio_util::run_basic(fut);
+  permission_trace::finish();
   if let Err(err) = result {
     let msg = format!("{}: {}", colors::red_bold("error"), err.to_string(),);
     eprintln!("{}", msg);
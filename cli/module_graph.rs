@@ -462,9 +462,10 @@ impl Module {
       );
     }
 
-    // Disallow a remote URL from trying to import a local URL
+    // Disallow a remote URL from trying to import a local URL. Data and blob
+    // URLs are self-contained, so they can be imported from anywhere.
     if (referrer_scheme == "https" || referrer_scheme == "http")
-      && !(specifier_scheme == "https" || specifier_scheme == "http")
+      && !matches!(specifier_scheme, "https" | "http" | "data" | "blob")
     {
       return Err(
         GraphError::InvalidLocalImport(specifier.clone(), location).into(),
@@ -1145,11 +1146,46 @@ impl Graph {
     self.modules.keys().map(|s| s.to_owned()).collect()
   }
 
+  /// Return the specifiers of the modules a module depends on, directly or
+  /// transitively, including the module itself. Both runtime code and type
+  /// only dependencies are followed. If the module is not part of the graph,
+  /// the result is empty.
+  pub fn get_dependencies(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> HashSet<ModuleSpecifier> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![specifier.clone()];
+    while let Some(specifier) = stack.pop() {
+      let specifier = self.resolve_specifier(&specifier).clone();
+      if seen.contains(&specifier) {
+        continue;
+      }
+      let module = match self.modules.get(&specifier) {
+        Some(module) => module,
+        None => continue,
+      };
+      for (_, dep) in module.dependencies.iter() {
+        if let Some(code_dep) = &dep.maybe_code {
+          stack.push(code_dep.clone());
+        }
+        if let Some(type_dep) = &dep.maybe_type {
+          stack.push(type_dep.clone());
+        }
+      }
+      if let Some((_, types_dep)) = &module.maybe_types {
+        stack.push(types_dep.clone());
+      }
+      seen.insert(specifier);
+    }
+    seen
+  }
+
   /// Transform `self.roots` into something that works for `tsc`, because `tsc`
   /// doesn't like root names without extensions that match its expectations,
   /// nor does it have any concept of redirection, so we have to resolve all
   /// that upfront before feeding it to `tsc`.
-  fn get_root_names(&self) -> Vec<(ModuleSpecifier, MediaType)> {
+  pub fn get_root_names(&self) -> Vec<(ModuleSpecifier, MediaType)> {
     self
       .roots
       .iter()
@@ -2074,6 +2110,41 @@ pub mod tests {
     assert_eq!(info.total_size, 344);
   }
 
+  #[tokio::test]
+  async fn test_graph_get_dependencies() {
+    let specifier =
+      ModuleSpecifier::resolve_url_or_path("file:///a.ts").unwrap();
+    let graph = setup_memory(
+      specifier.clone(),
+      map!(
+        "/a.ts" => r#"
+        import * as b from "./b.ts";
+
+        console.log(b);
+      "#,
+        "/b.ts" => r#"
+        export * from "./c.ts";
+      "#,
+        "/c.ts" => r#"
+        export const c = "c";
+      "#
+      ),
+    )
+    .await;
+    let b = ModuleSpecifier::resolve_url_or_path("file:///b.ts").unwrap();
+    let c = ModuleSpecifier::resolve_url_or_path("file:///c.ts").unwrap();
+    let dependencies = graph.get_dependencies(&specifier);
+    assert_eq!(dependencies.len(), 3);
+    assert!(dependencies.contains(&specifier));
+    assert!(dependencies.contains(&b));
+    assert!(dependencies.contains(&c));
+    let dependencies = graph.get_dependencies(&c);
+    assert_eq!(dependencies.len(), 1);
+    assert!(dependencies.contains(&c));
+    let d = ModuleSpecifier::resolve_url_or_path("file:///d.ts").unwrap();
+    assert!(graph.get_dependencies(&d).is_empty());
+  }
+
   #[tokio::test]
   async fn test_graph_import_json() {
     let specifier =
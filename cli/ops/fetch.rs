@@ -1,14 +1,20 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use crate::permissions::Permissions;
 use deno_fetch::reqwest;
+use deno_fetch::BlobUrlStore;
 
-pub fn init(rt: &mut deno_core::JsRuntime, maybe_ca_file: Option<&str>) {
+pub fn init(
+  rt: &mut deno_core::JsRuntime,
+  maybe_ca_file: Option<&str>,
+  blob_url_store: BlobUrlStore,
+) {
   {
     let op_state = rt.op_state();
     let mut state = op_state.borrow_mut();
     state.put::<reqwest::Client>({
       crate::http_util::create_http_client(maybe_ca_file).unwrap()
     });
+    state.put::<BlobUrlStore>(blob_url_store);
   }
   super::reg_json_async(rt, "op_fetch", deno_fetch::op_fetch::<Permissions>);
   super::reg_json_async(rt, "op_fetch_read", deno_fetch::op_fetch_read);
@@ -17,4 +23,14 @@ pub fn init(rt: &mut deno_core::JsRuntime, maybe_ca_file: Option<&str>) {
     "op_create_http_client",
     deno_fetch::op_create_http_client::<Permissions>,
   );
+  super::reg_json_sync(
+    rt,
+    "op_create_object_url",
+    deno_fetch::op_create_object_url,
+  );
+  super::reg_json_sync(
+    rt,
+    "op_revoke_object_url",
+    deno_fetch::op_revoke_object_url,
+  );
 }
@@ -0,0 +1,364 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use super::io::StreamResource;
+use super::io::StreamResourceHolder;
+use deno_core::error::bad_resource;
+use deno_core::error::bad_resource_id;
+use deno_core::error::custom_error;
+use deno_core::error::generic_error;
+use deno_core::error::type_error;
+use deno_core::error::AnyError;
+use deno_core::futures::channel::mpsc;
+use deno_core::futures::channel::oneshot;
+use deno_core::futures::future::poll_fn;
+use deno_core::futures::ready;
+use deno_core::futures::StreamExt;
+use deno_core::serde_json;
+use deno_core::serde_json::json;
+use deno_core::serde_json::Value;
+use deno_core::BufVec;
+use deno_core::OpState;
+use deno_core::ZeroCopyBuf;
+use hyper::body::Bytes;
+use hyper::body::HttpBody;
+use hyper::header::HeaderName;
+use hyper::header::HeaderValue;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::Poll;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+pub fn init(rt: &mut deno_core::JsRuntime) {
+  super::reg_json_sync(rt, "op_http_start", op_http_start);
+  super::reg_json_async(rt, "op_http_request_next", op_http_request_next);
+  super::reg_json_async(rt, "op_http_request_read", op_http_request_read);
+  super::reg_json_sync(rt, "op_http_respond", op_http_respond);
+  super::reg_json_async(rt, "op_http_response_write", op_http_response_write);
+}
+
+/// A request received by hyper, together with the channel its response is
+/// sent back on.
+type NextRequest = (Request<Body>, oneshot::Sender<Response<Body>>);
+
+/// An HTTP connection served by hyper. The connection itself is driven by a
+/// separate task, which forwards the requests it receives to this resource.
+/// Closing the resource gracefully closes the connection.
+struct HttpConnResource {
+  /// Either "http" or "https", used to reconstruct the request URLs.
+  scheme: &'static str,
+  local_addr: SocketAddr,
+  requests_rx: mpsc::UnboundedReceiver<NextRequest>,
+  /// Dropping this sender shuts the connection down.
+  _shutdown_tx: oneshot::Sender<()>,
+}
+
+/// The body of a request, read through `op_http_request_read`.
+struct RequestBodyResource {
+  body: Body,
+  /// The part of the last received chunk which did not fit into the buffer
+  /// of the previous read.
+  remainder: Bytes,
+}
+
+struct ResponseSenderResource(oneshot::Sender<Response<Body>>);
+
+/// The body of a response which is streamed through
+/// `op_http_response_write`. Closing the resource ends the body.
+struct ResponseBodyResource(hyper::body::Sender);
+
+fn start_http<S>(
+  stream: S,
+  requests_tx: mpsc::UnboundedSender<NextRequest>,
+) -> oneshot::Sender<()>
+where
+  S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+  let service = service_fn(move |request: Request<Body>| {
+    let requests_tx = requests_tx.clone();
+    async move {
+      let (response_tx, response_rx) = oneshot::channel();
+      requests_tx
+        .unbounded_send((request, response_tx))
+        .map_err(|_| generic_error("HTTP connection has been closed"))?;
+      response_rx
+        .await
+        .map_err(|_| generic_error("Request was dropped without a response"))
+    }
+  });
+  // Keep-alive and pipelining are handled by hyper, which only reads the
+  // next request of a connection once the previous response was sent.
+  let conn = Http::new().serve_connection(stream, service);
+  let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+  tokio::spawn(async move {
+    tokio::pin!(conn);
+    let result = tokio::select! {
+      result = &mut conn => result,
+      _ = shutdown_rx => {
+        // Responses which are already being sent are finished first.
+        conn.as_mut().graceful_shutdown();
+        conn.await
+      }
+    };
+    if let Err(err) = result {
+      debug!("HTTP connection error: {}", err);
+    }
+  });
+  shutdown_tx
+}
+
+#[derive(Deserialize)]
+struct StartArgs {
+  rid: u32,
+}
+
+fn op_http_start(
+  state: &mut OpState,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  super::check_unstable(state, "Deno.serveHttp");
+  let args: StartArgs = serde_json::from_value(args)?;
+  let resource_holder = state
+    .resource_table
+    .remove::<StreamResourceHolder>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let (requests_tx, requests_rx) = mpsc::unbounded();
+  let (scheme, local_addr, shutdown_tx) = match resource_holder.resource {
+    StreamResource::TcpStream(Some(tcp_stream)) => {
+      let local_addr = tcp_stream.local_addr()?;
+      ("http", local_addr, start_http(tcp_stream, requests_tx))
+    }
+    StreamResource::ServerTlsStream(tls_stream) => {
+      let (tcp_stream, _) = tls_stream.get_ref();
+      let local_addr = tcp_stream.local_addr()?;
+      ("https", local_addr, start_http(tls_stream, requests_tx))
+    }
+    _ => return Err(bad_resource_id()),
+  };
+
+  let rid = state.resource_table.add(
+    "httpConn",
+    Box::new(HttpConnResource {
+      scheme,
+      local_addr,
+      requests_rx,
+      _shutdown_tx: shutdown_tx,
+    }),
+  );
+  Ok(json!(rid))
+}
+
+#[derive(Deserialize)]
+struct NextRequestArgs {
+  rid: u32,
+}
+
+async fn op_http_request_next(
+  state: Rc<RefCell<OpState>>,
+  args: Value,
+  _zero_copy: BufVec,
+) -> Result<Value, AnyError> {
+  let args: NextRequestArgs = serde_json::from_value(args)?;
+  let rid = args.rid;
+
+  let (maybe_request, scheme, local_addr) = poll_fn(|cx| {
+    let mut state = state.borrow_mut();
+    let conn_resource =
+      state
+        .resource_table
+        .get_mut::<HttpConnResource>(rid)
+        .ok_or_else(|| bad_resource("HTTP connection has been closed"))?;
+    let maybe_request = ready!(conn_resource.requests_rx.poll_next_unpin(cx));
+    Poll::Ready(Ok::<_, AnyError>((
+      maybe_request,
+      conn_resource.scheme,
+      conn_resource.local_addr,
+    )))
+  })
+  .await?;
+  // The connection was closed by the client.
+  let (request, response_tx) = match maybe_request {
+    Some(next_request) => next_request,
+    None => return Ok(json!(null)),
+  };
+
+  let (parts, body) = request.into_parts();
+  let url = if parts.uri.scheme().is_some() {
+    parts.uri.to_string()
+  } else {
+    let host = match parts.headers.get(hyper::header::HOST) {
+      Some(host) => host.to_str()?.to_string(),
+      None => local_addr.to_string(),
+    };
+    let path = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+    format!("{}://{}{}", scheme, host, path)
+  };
+  let headers: Vec<(String, String)> = parts
+    .headers
+    .iter()
+    .map(|(name, value)| {
+      let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+      (name.to_string(), value)
+    })
+    .collect();
+
+  let mut state = state.borrow_mut();
+  let body_rid = if body.is_end_stream() {
+    None
+  } else {
+    Some(state.resource_table.add(
+      "httpRequestBody",
+      Box::new(RequestBodyResource {
+        body,
+        remainder: Bytes::new(),
+      }),
+    ))
+  };
+  let response_rid = state.resource_table.add(
+    "httpResponseSender",
+    Box::new(ResponseSenderResource(response_tx)),
+  );
+
+  Ok(json!({
+    "bodyRid": body_rid,
+    "responseRid": response_rid,
+    "method": parts.method.as_str(),
+    "url": url,
+    "headers": headers,
+  }))
+}
+
+#[derive(Deserialize)]
+struct RequestReadArgs {
+  rid: u32,
+}
+
+async fn op_http_request_read(
+  state: Rc<RefCell<OpState>>,
+  args: Value,
+  zero_copy: BufVec,
+) -> Result<Value, AnyError> {
+  assert_eq!(zero_copy.len(), 1, "Invalid number of arguments");
+  let args: RequestReadArgs = serde_json::from_value(args)?;
+  let mut buf = zero_copy[0].clone();
+
+  poll_fn(|cx| {
+    let mut state = state.borrow_mut();
+    let resource = state
+      .resource_table
+      .get_mut::<RequestBodyResource>(args.rid)
+      .ok_or_else(bad_resource_id)?;
+    while resource.remainder.is_empty() {
+      match ready!(Pin::new(&mut resource.body).poll_data(cx)) {
+        Some(Ok(chunk)) => resource.remainder = chunk,
+        Some(Err(err)) => return Poll::Ready(Err(AnyError::from(err))),
+        None => return Poll::Ready(Ok(json!({ "nread": 0 }))),
+      }
+    }
+    let nread = buf.len().min(resource.remainder.len());
+    let chunk = resource.remainder.split_to(nread);
+    buf[..nread].copy_from_slice(&chunk);
+    Poll::Ready(Ok(json!({ "nread": nread })))
+  })
+  .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RespondArgs {
+  rid: u32,
+  status: u16,
+  headers: Vec<(String, String)>,
+  /// If set, the body is streamed through a resource instead of being passed
+  /// as a buffer.
+  stream_body: bool,
+}
+
+fn op_http_respond(
+  state: &mut OpState,
+  args: Value,
+  zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  let args: RespondArgs = serde_json::from_value(args)?;
+  let response_sender = state
+    .resource_table
+    .remove::<ResponseSenderResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let mut builder = Response::builder().status(args.status);
+  for (name, value) in &args.headers {
+    let name = HeaderName::from_bytes(name.as_bytes())
+      .map_err(|_| type_error(format!("Invalid header name: {}", name)))?;
+    let value = HeaderValue::from_str(value)
+      .map_err(|_| type_error(format!("Invalid header value: {}", value)))?;
+    builder = builder.header(name, value);
+  }
+
+  let (body, maybe_body_sender) = if args.stream_body {
+    let (body_sender, body) = Body::channel();
+    (body, Some(body_sender))
+  } else {
+    match zero_copy.len() {
+      0 => (Body::empty(), None),
+      1 => (Body::from(zero_copy[0].to_vec()), None),
+      _ => panic!("Invalid number of arguments"),
+    }
+  };
+  let response = builder.body(body)?;
+  response_sender.0.send(response).map_err(|_| {
+    custom_error("BrokenPipe", "HTTP connection has been closed")
+  })?;
+
+  let body_rid = maybe_body_sender.map(|body_sender| {
+    state.resource_table.add(
+      "httpResponseBody",
+      Box::new(ResponseBodyResource(body_sender)),
+    )
+  });
+  Ok(json!({ "bodyRid": body_rid }))
+}
+
+#[derive(Deserialize)]
+struct ResponseWriteArgs {
+  rid: u32,
+}
+
+async fn op_http_response_write(
+  state: Rc<RefCell<OpState>>,
+  args: Value,
+  zero_copy: BufVec,
+) -> Result<Value, AnyError> {
+  assert_eq!(zero_copy.len(), 1, "Invalid number of arguments");
+  let args: ResponseWriteArgs = serde_json::from_value(args)?;
+  let mut maybe_chunk = Some(Bytes::from(zero_copy[0].to_vec()));
+
+  poll_fn(|cx| {
+    let mut state = state.borrow_mut();
+    let resource = state
+      .resource_table
+      .get_mut::<ResponseBodyResource>(args.rid)
+      .ok_or_else(bad_resource_id)?;
+    let body_sender = &mut resource.0;
+    // Waits until hyper wrote the previous chunk to the connection.
+    ready!(body_sender.poll_ready(cx)).map_err(|_| {
+      custom_error("BrokenPipe", "HTTP connection has been closed")
+    })?;
+    if let Some(chunk) = maybe_chunk.take() {
+      body_sender.try_send_data(chunk).map_err(|_| {
+        custom_error("BrokenPipe", "HTTP connection has been closed")
+      })?;
+    }
+    Poll::Ready(Ok(json!({})))
+  })
+  .await
+}
@@ -7,6 +7,7 @@ pub mod errors;
 pub mod fetch;
 pub mod fs;
 pub mod fs_events;
+pub mod http;
 pub mod io;
 pub mod net;
 #[cfg(unix)]
@@ -19,6 +20,7 @@ pub mod random;
 pub mod runtime;
 pub mod runtime_compiler;
 pub mod signal;
+pub mod testing;
 pub mod timers;
 pub mod tls;
 pub mod tty;
@@ -27,6 +29,7 @@ pub mod websocket;
 pub mod worker_host;
 
 use crate::metrics::metrics_op;
+use crate::permission_trace;
 use crate::program_state::ProgramState;
 use deno_core::error::AnyError;
 use deno_core::json_op_async;
@@ -46,7 +49,12 @@ where
   F: Fn(Rc<RefCell<OpState>>, Value, BufVec) -> R + 'static,
   R: Future<Output = Result<Value, AnyError>> + 'static,
 {
-  rt.register_op(name, metrics_op(json_op_async(op_fn)));
+  rt.register_op(
+    name,
+    metrics_op(json_op_async(move |state, args, bufs| {
+      permission_trace::with_op_stack_async(op_fn(state, args, bufs))
+    })),
+  );
 }
 
 pub fn reg_json_sync<F>(rt: &mut JsRuntime, name: &'static str, op_fn: F)
@@ -54,7 +62,12 @@ where
   F: Fn(&mut OpState, Value, &mut [ZeroCopyBuf]) -> Result<Value, AnyError>
     + 'static,
 {
-  rt.register_op(name, metrics_op(json_op_sync(op_fn)));
+  rt.register_op(
+    name,
+    metrics_op(json_op_sync(move |state, args, bufs| {
+      permission_trace::with_op_stack(|| op_fn(state, args, bufs))
+    })),
+  );
 }
 
 /// Helper for checking unstable features. Used for sync ops.
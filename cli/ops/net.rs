@@ -20,6 +20,7 @@ use deno_core::OpState;
 use deno_core::ZeroCopyBuf;
 use serde::Deserialize;
 use std::cell::RefCell;
+use std::net::IpAddr;
 use std::net::Shutdown;
 use std::net::SocketAddr;
 use std::rc::Rc;
@@ -28,6 +29,15 @@ use std::task::Poll;
 use tokio::net::TcpListener;
 use tokio::net::TcpStream;
 use tokio::net::UdpSocket;
+use trust_dns_resolver::config::NameServerConfigGroup;
+use trust_dns_resolver::config::ResolverConfig;
+use trust_dns_resolver::config::ResolverOpts;
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::proto::rr::RData;
+use trust_dns_resolver::proto::rr::RecordType;
+use trust_dns_resolver::proto::xfer::DnsRequestOptions;
+use trust_dns_resolver::system_conf;
+use trust_dns_resolver::TokioAsyncResolver;
 
 #[cfg(unix)]
 use super::net_unix;
@@ -41,6 +51,7 @@ pub fn init(rt: &mut deno_core::JsRuntime) {
   super::reg_json_sync(rt, "op_listen", op_listen);
   super::reg_json_async(rt, "op_datagram_receive", op_datagram_receive);
   super::reg_json_async(rt, "op_datagram_send", op_datagram_send);
+  super::reg_json_async(rt, "op_dns_resolve", op_dns_resolve);
 }
 
 #[derive(Deserialize)]
@@ -563,3 +574,125 @@ fn op_listen(
     _ => Err(type_error("Wrong argument format!")),
   }
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResolveDnsArgs {
+  query: String,
+  record_type: String,
+  options: Option<ResolveDnsOptions>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResolveDnsOptions {
+  name_server: Option<NameServer>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NameServer {
+  ip_addr: String,
+  #[serde(default = "default_dns_port")]
+  port: u16,
+}
+
+fn default_dns_port() -> u16 {
+  53
+}
+
+fn parse_record_type(record_type: &str) -> Result<RecordType, AnyError> {
+  match record_type {
+    "A" => Ok(RecordType::A),
+    "AAAA" => Ok(RecordType::AAAA),
+    "CNAME" => Ok(RecordType::CNAME),
+    "MX" => Ok(RecordType::MX),
+    "NS" => Ok(RecordType::NS),
+    "SRV" => Ok(RecordType::SRV),
+    "TXT" => Ok(RecordType::TXT),
+    _ => Err(type_error(format!(
+      "Unsupported record type: {}",
+      record_type
+    ))),
+  }
+}
+
+fn rdata_to_json(rdata: &RData) -> Option<Value> {
+  let value = match rdata {
+    RData::A(ip) => json!(ip.to_string()),
+    RData::AAAA(ip) => json!(ip.to_string()),
+    RData::CNAME(name) | RData::NS(name) => json!(name.to_utf8()),
+    RData::MX(mx) => json!({
+      "preference": mx.preference(),
+      "exchange": mx.exchange().to_utf8(),
+    }),
+    RData::SRV(srv) => json!({
+      "priority": srv.priority(),
+      "weight": srv.weight(),
+      "port": srv.port(),
+      "target": srv.target().to_utf8(),
+    }),
+    RData::TXT(txt) => {
+      let strings: Vec<String> = txt
+        .iter()
+        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+        .collect();
+      json!(strings)
+    }
+    _ => return None,
+  };
+  Some(value)
+}
+
+async fn op_dns_resolve(
+  state: Rc<RefCell<OpState>>,
+  args: Value,
+  _zero_copy: BufVec,
+) -> Result<Value, AnyError> {
+  super::check_unstable2(&state, "Deno.resolveDns");
+  let args: ResolveDnsArgs = serde_json::from_value(args)?;
+  let record_type = parse_record_type(&args.record_type)?;
+
+  let maybe_name_server = args.options.and_then(|options| options.name_server);
+  let (config, opts) = match maybe_name_server {
+    Some(name_server) => {
+      let ip_addr: IpAddr = name_server.ip_addr.parse().map_err(|_| {
+        type_error(format!("Invalid IP address: {}", name_server.ip_addr))
+      })?;
+      let name_servers =
+        NameServerConfigGroup::from_ips_clear(&[ip_addr], name_server.port);
+      let config = ResolverConfig::from_parts(None, vec![], name_servers);
+      (config, ResolverOpts::default())
+    }
+    None => system_conf::read_system_conf()?,
+  };
+
+  {
+    let s = state.borrow();
+    let permissions = s.borrow::<Permissions>();
+    for name_server in config.name_servers() {
+      let addr = name_server.socket_addr;
+      permissions.check_net(&addr.ip().to_string(), addr.port())?;
+    }
+  }
+
+  let resolver = TokioAsyncResolver::tokio(config, opts).await?;
+  let lookup = resolver
+    .lookup(args.query, record_type, DnsRequestOptions::default())
+    .await
+    .map_err(|err| match err.kind() {
+      ResolveErrorKind::NoRecordsFound { .. } => {
+        custom_error("NotFound", err.to_string())
+      }
+      _ => generic_error(err.to_string()),
+    })?;
+
+  // A lookup also contains the records the answer was resolved through, e.g.
+  // the CNAME records of an A lookup.
+  let records: Vec<Value> = lookup
+    .iter()
+    .filter(|rdata| rdata.to_record_type() == record_type)
+    .filter_map(rdata_to_json)
+    .collect();
+  Ok(json!(records))
+}
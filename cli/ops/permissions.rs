@@ -1,5 +1,6 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::permission_trace;
 use crate::permissions::Permissions;
 use deno_core::error::custom_error;
 use deno_core::error::AnyError;
@@ -15,6 +16,11 @@ pub fn init(rt: &mut deno_core::JsRuntime) {
   super::reg_json_sync(rt, "op_query_permission", op_query_permission);
   super::reg_json_sync(rt, "op_revoke_permission", op_revoke_permission);
   super::reg_json_sync(rt, "op_request_permission", op_request_permission);
+  super::reg_json_sync(
+    rt,
+    "op_set_permission_trace_stack",
+    op_set_permission_trace_stack,
+  );
 }
 
 #[derive(Deserialize)]
@@ -22,6 +28,8 @@ struct PermissionArgs {
   name: String,
   url: Option<String>,
   path: Option<String>,
+  variable: Option<String>,
+  command: Option<String>,
 }
 
 pub fn op_query_permission(
@@ -36,8 +44,8 @@ pub fn op_query_permission(
     "read" => permissions.query_read(&path.as_deref().map(Path::new)),
     "write" => permissions.query_write(&path.as_deref().map(Path::new)),
     "net" => permissions.query_net_url(&args.url.as_deref())?,
-    "env" => permissions.query_env(),
-    "run" => permissions.query_run(),
+    "env" => permissions.query_env(&args.variable.as_deref()),
+    "run" => permissions.query_run(&args.command.as_deref()),
     "plugin" => permissions.query_plugin(),
     "hrtime" => permissions.query_hrtime(),
     n => {
@@ -62,8 +70,8 @@ pub fn op_revoke_permission(
     "read" => permissions.revoke_read(&path.as_deref().map(Path::new)),
     "write" => permissions.revoke_write(&path.as_deref().map(Path::new)),
     "net" => permissions.revoke_net(&args.url.as_deref())?,
-    "env" => permissions.revoke_env(),
-    "run" => permissions.revoke_run(),
+    "env" => permissions.revoke_env(&args.variable.as_deref()),
+    "run" => permissions.revoke_run(&args.command.as_deref()),
     "plugin" => permissions.revoke_plugin(),
     "hrtime" => permissions.revoke_hrtime(),
     n => {
@@ -88,8 +96,8 @@ pub fn op_request_permission(
     "read" => permissions.request_read(&path.as_deref().map(Path::new)),
     "write" => permissions.request_write(&path.as_deref().map(Path::new)),
     "net" => permissions.request_net(&args.url.as_deref())?,
-    "env" => permissions.request_env(),
-    "run" => permissions.request_run(),
+    "env" => permissions.request_env(&args.variable.as_deref()),
+    "run" => permissions.request_run(&args.command.as_deref()),
     "plugin" => permissions.request_plugin(),
     "hrtime" => permissions.request_hrtime(),
     n => {
@@ -101,3 +109,19 @@ pub fn op_request_permission(
   };
   Ok(json!({ "state": perm.to_string() }))
 }
+
+#[derive(Deserialize)]
+struct TraceStackArgs {
+  stack: String,
+}
+
+/// Sets the JS stack of the next op call, used by `--trace-permissions`.
+pub fn op_set_permission_trace_stack(
+  _state: &mut OpState,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  let args: TraceStackArgs = serde_json::from_value(args)?;
+  permission_trace::set_pending_stack(args.stack);
+  Ok(json!({}))
+}
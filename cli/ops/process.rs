@@ -1,6 +1,7 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 use super::io::{std_file_resource, StreamResource, StreamResourceHolder};
+use crate::permissions::resolve_cmd;
 use crate::permissions::Permissions;
 use crate::signal::kill;
 use deno_core::error::bad_resource_id;
@@ -16,6 +17,9 @@ use deno_core::OpState;
 use deno_core::ZeroCopyBuf;
 use serde::Deserialize;
 use std::cell::RefCell;
+use std::env;
+use std::ffi::OsString;
+use std::path::Path;
 use std::rc::Rc;
 use tokio::process::Command;
 
@@ -71,13 +75,42 @@ fn op_run(
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<Value, AnyError> {
   let run_args: RunArgs = serde_json::from_value(args)?;
-  state.borrow::<Permissions>().check_run()?;
+  let cmd = run_args
+    .cmd
+    .get(0)
+    .ok_or_else(|| type_error("Empty command"))?;
+  // The command is resolved with the `PATH` and working directory of the
+  // subprocess, and exactly the binary which was checked is spawned.
+  let path_env = run_args
+    .env
+    .iter()
+    .rev()
+    .find(|(key, _)| {
+      if cfg!(windows) {
+        key.eq_ignore_ascii_case("PATH")
+      } else {
+        key == "PATH"
+      }
+    })
+    .map(|(_, value)| OsString::from(value))
+    .or_else(|| env::var_os("PATH"));
+  let resolved_cmd = resolve_cmd(
+    cmd,
+    path_env.as_deref(),
+    run_args.cwd.as_deref().map(Path::new),
+  );
+  state
+    .borrow::<Permissions>()
+    .check_run(cmd, resolved_cmd.as_deref())?;
 
   let args = run_args.cmd;
   let env = run_args.env;
   let cwd = run_args.cwd;
 
-  let mut c = Command::new(args.get(0).unwrap());
+  let mut c = match resolved_cmd {
+    Some(resolved_cmd) => Command::new(resolved_cmd),
+    None => Command::new(args.get(0).unwrap()),
+  };
   (1..args.len()).for_each(|i| {
     let arg = args.get(i).unwrap();
     c.arg(arg);
@@ -181,11 +214,8 @@ async fn op_run_status(
   let args: RunStatusArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
 
-  {
-    let s = state.borrow();
-    s.borrow::<Permissions>().check_run()?;
-  }
-
+  // The child resource only exists if `op_run` was allowed to spawn it, so
+  // its status can be awaited without checking the command again.
   let run_status = poll_fn(|cx| {
     let mut state = state.borrow_mut();
     let child_resource = state
@@ -228,7 +258,7 @@ fn op_kill(
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<Value, AnyError> {
   super::check_unstable(state, "Deno.kill");
-  state.borrow::<Permissions>().check_run()?;
+  state.borrow::<Permissions>().check_run_all()?;
 
   let args: KillArgs = serde_json::from_value(args)?;
   kill(args.pid, args.signo)?;
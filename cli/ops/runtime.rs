@@ -41,6 +41,7 @@ fn op_start(
     "pid": std::process::id(),
     "ppid": ppid(),
     "target": env!("TARGET"),
+    "tracePermissions": crate::permission_trace::is_enabled(),
     "tsVersion": version::TYPESCRIPT,
     "unstableFlag": gs.flags.unstable,
     "v8Version": version::v8(),
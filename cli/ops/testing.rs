@@ -0,0 +1,28 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use crate::test_runner::TestEventSender;
+use crate::test_runner::TestMessage;
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::serde_json;
+use deno_core::serde_json::json;
+use deno_core::serde_json::Value;
+use deno_core::OpState;
+use deno_core::ZeroCopyBuf;
+
+pub fn init(rt: &mut deno_core::JsRuntime) {
+  super::reg_json_sync(rt, "op_post_test_message", op_post_test_message);
+}
+
+fn op_post_test_message(
+  state: &mut OpState,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  let message: TestMessage = serde_json::from_value(args)?;
+  let sender = state.try_borrow::<TestEventSender>().ok_or_else(|| {
+    generic_error("Test messages can only be posted by isolated test modules")
+  })?;
+  sender.send(message)?;
+  Ok(json!({}))
+}
@@ -1,8 +1,12 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use super::io::StreamResource;
+use super::io::StreamResourceHolder;
+use crate::http_util::proxy_authorization;
 use crate::permissions::Permissions;
 use core::task::Poll;
 use deno_core::error::bad_resource_id;
+use deno_core::error::generic_error;
 use deno_core::error::type_error;
 use deno_core::error::AnyError;
 use deno_core::futures::future::poll_fn;
@@ -14,6 +18,9 @@ use deno_core::serde_json::Value;
 use deno_core::url;
 use deno_core::BufVec;
 use deno_core::OpState;
+use deno_fetch::proxy::redact;
+use deno_fetch::proxy::ProxyConfig;
+use http::header::HeaderValue;
 use http::{Method, Request, Uri};
 use serde::Deserialize;
 use std::borrow::Cow;
@@ -22,26 +29,30 @@ use std::fs::File;
 use std::io::BufReader;
 use std::rc::Rc;
 use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
-use tokio_rustls::{rustls::ClientConfig, TlsConnector};
+use tokio_rustls::{rustls::ClientConfig, TlsConnector, TlsStream};
 use tokio_tungstenite::stream::Stream as StreamSwitcher;
-use tokio_tungstenite::tungstenite::Error as TungsteniteError;
 use tokio_tungstenite::tungstenite::{
-  handshake::client::Response, protocol::frame::coding::CloseCode,
-  protocol::CloseFrame, Message,
+  handshake::client::Response, handshake::server::ErrorResponse,
+  protocol::frame::coding::CloseCode, protocol::CloseFrame, Message,
 };
-use tokio_tungstenite::{client_async, WebSocketStream};
+use tokio_tungstenite::{accept_hdr_async, client_async, WebSocketStream};
 use webpki::DNSNameRef;
 
 pub fn init(rt: &mut deno_core::JsRuntime) {
   super::reg_json_async(rt, "op_ws_create", op_ws_create);
+  super::reg_json_async(rt, "op_ws_accept", op_ws_accept);
   super::reg_json_async(rt, "op_ws_send", op_ws_send);
   super::reg_json_async(rt, "op_ws_close", op_ws_close);
   super::reg_json_async(rt, "op_ws_next_event", op_ws_next_event);
 }
 
-type MaybeTlsStream =
-  StreamSwitcher<TcpStream, tokio_rustls::client::TlsStream<TcpStream>>;
+/// The stream of either side of a WebSocket connection. Client and server
+/// TLS streams share the `TlsStream` variant, so that the same ops can be
+/// used for dialed and accepted WebSockets.
+type MaybeTlsStream = StreamSwitcher<TcpStream, TlsStream<TcpStream>>;
 
 type WsStream = WebSocketStream<MaybeTlsStream>;
 
@@ -58,10 +69,10 @@ pub async fn op_ws_create(
   _bufs: BufVec,
 ) -> Result<Value, AnyError> {
   let args: CreateArgs = serde_json::from_value(args)?;
+  let url = url::Url::parse(&args.url)?;
   {
     let s = state.borrow();
-    s.borrow::<Permissions>()
-      .check_net_url(&url::Url::parse(&args.url)?)?;
+    s.borrow::<Permissions>().check_net_url(&url)?;
   }
   let ca_file = {
     let cli_state = super::global_state2(&state);
@@ -81,11 +92,20 @@ pub async fn op_ws_create(
     Some("ws") => 80,
     _ => unreachable!(),
   });
-  let addr = format!("{}:{}", domain, port);
-  let try_socket = TcpStream::connect(addr).await;
-  let tcp_socket = match try_socket.map_err(TungsteniteError::Io) {
+  let proxy_config = ProxyConfig::from_env()?;
+  let try_socket = match proxy_config.for_url(&url) {
+    Some(proxy) => connect_through_proxy(proxy, domain, *port).await,
+    None => {
+      let addr = format!("{}:{}", domain, port);
+      TcpStream::connect(addr).await.map_err(AnyError::from)
+    }
+  };
+  let tcp_socket = match try_socket {
     Ok(socket) => socket,
-    Err(_) => return Ok(json!({"success": false})),
+    Err(err) => {
+      debug!("Failed to connect to WebSocket: {}", err);
+      return Ok(json!({"success": false}));
+    }
   };
 
   let socket: MaybeTlsStream = match uri.scheme_str() {
@@ -106,7 +126,7 @@ pub async fn op_ws_create(
       let dnsname =
         DNSNameRef::try_from_ascii_str(&domain).expect("Invalid DNS lookup");
       let tls_socket = tls_connector.connect(dnsname, tcp_socket).await?;
-      StreamSwitcher::Tls(tls_socket)
+      StreamSwitcher::Tls(tls_socket.into())
     }
     _ => unreachable!(),
   };
@@ -142,6 +162,139 @@ pub async fn op_ws_create(
   }))
 }
 
+/// Opens a tunnel to `host:port` through an HTTP proxy with a `CONNECT`
+/// request.
+async fn connect_through_proxy(
+  proxy: &url::Url,
+  host: &str,
+  port: u16,
+) -> Result<TcpStream, AnyError> {
+  if proxy.scheme() != "http" {
+    return Err(type_error(format!(
+      "Unsupported proxy for WebSockets: {}",
+      redact(proxy)
+    )));
+  }
+  let proxy_host = proxy
+    .host_str()
+    .ok_or_else(|| type_error("Proxy URL has no host"))?;
+  let proxy_port = proxy.port_or_known_default().unwrap();
+  let mut socket = TcpStream::connect((proxy_host, proxy_port)).await?;
+
+  let mut request = format!(
+    "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n",
+    host = host,
+    port = port
+  );
+  if let Some(authorization) = proxy_authorization(proxy) {
+    request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+  }
+  request.push_str("\r\n");
+  socket.write_all(request.as_bytes()).await?;
+
+  // The response head is read byte by byte, so that nothing which belongs to
+  // the tunneled connection is consumed.
+  let mut head = Vec::new();
+  while !head.ends_with(b"\r\n\r\n") {
+    let mut byte = [0u8];
+    if socket.read(&mut byte).await? == 0 || head.len() > 8192 {
+      return Err(generic_error("Invalid response from proxy"));
+    }
+    head.push(byte[0]);
+  }
+  let head = String::from_utf8_lossy(&head);
+  let status_line = head.lines().next().unwrap_or("");
+  if status_line.split_whitespace().nth(1) != Some("200") {
+    return Err(generic_error(format!(
+      "Proxy refused to connect to {}:{}: {}",
+      host, port, status_line
+    )));
+  }
+  Ok(socket)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AcceptArgs {
+  rid: u32,
+  protocols: Vec<String>,
+}
+
+/// Performs the server side of the WebSocket handshake on an accepted TCP or
+/// TLS connection. The connection resource is replaced by the WebSocket
+/// stream, which is then driven by the same ops as a dialed WebSocket.
+pub async fn op_ws_accept(
+  state: Rc<RefCell<OpState>>,
+  args: Value,
+  _bufs: BufVec,
+) -> Result<Value, AnyError> {
+  super::check_unstable2(&state, "Deno.acceptWebSocket");
+  let args: AcceptArgs = serde_json::from_value(args)?;
+  let resource_holder = state
+    .borrow_mut()
+    .resource_table
+    .remove::<StreamResourceHolder>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let (scheme, local_addr, socket) = match resource_holder.resource {
+    StreamResource::TcpStream(Some(tcp_stream)) => {
+      let local_addr = tcp_stream.local_addr()?;
+      ("ws", local_addr, StreamSwitcher::Plain(tcp_stream))
+    }
+    StreamResource::ServerTlsStream(tls_stream) => {
+      let local_addr = tls_stream.get_ref().0.local_addr()?;
+      (
+        "wss",
+        local_addr,
+        StreamSwitcher::Tls(TlsStream::from(*tls_stream)),
+      )
+    }
+    _ => return Err(bad_resource_id()),
+  };
+
+  let mut url = String::new();
+  let mut protocol = None;
+  let callback = |request: &Request<()>, mut response: Response| {
+    let host = match request.headers().get("Host") {
+      Some(host) => String::from_utf8_lossy(host.as_bytes()).into_owned(),
+      None => local_addr.to_string(),
+    };
+    url = format!("{}://{}{}", scheme, host, request.uri());
+    // Selects the first protocol offered by the client which is supported.
+    protocol = request
+      .headers()
+      .get_all("Sec-WebSocket-Protocol")
+      .iter()
+      .filter_map(|header| header.to_str().ok())
+      .flat_map(|header| header.split(','))
+      .map(|offered| offered.trim())
+      .find(|offered| args.protocols.iter().any(|p| p == offered))
+      .map(String::from);
+    if let Some(protocol) = &protocol {
+      response.headers_mut().insert(
+        "Sec-WebSocket-Protocol",
+        HeaderValue::from_str(protocol).unwrap(),
+      );
+    }
+    Ok::<_, ErrorResponse>(response)
+  };
+  let stream: WsStream =
+    accept_hdr_async(socket, callback).await.map_err(|err| {
+      type_error(format!("failed to accept WebSocket: {}", err.to_string()))
+    })?;
+
+  let rid = state
+    .borrow_mut()
+    .resource_table
+    .add("webSocketStream", Box::new(stream));
+  Ok(json!({
+    "rid": rid,
+    "url": url,
+    "protocol": protocol.unwrap_or_default(),
+    "extensions": "",
+  }))
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct SendArgs {
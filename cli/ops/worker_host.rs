@@ -1,9 +1,11 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 use crate::colors;
+use crate::coverage::CoverageCollector;
 use crate::fmt_errors::JsError;
 use crate::ops::io::get_stdio;
 use crate::permissions::Permissions;
+use crate::permissions::WorkerPermissionsArg;
 use crate::program_state::ProgramState;
 use crate::tokio_util::create_basic_runtime;
 use crate::worker::WebWorker;
@@ -24,6 +26,7 @@ use serde::Deserialize;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::From;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::thread::JoinHandle;
@@ -166,6 +169,26 @@ fn run_worker_thread(
 
     let mut rt = create_basic_runtime();
 
+    let mut maybe_coverage_collector =
+      if let Some(ref coverage_dir) = program_state.flags.coverage_dir {
+        let session = worker.create_inspector_session();
+        let coverage_dir = PathBuf::from(coverage_dir);
+        let mut coverage_collector =
+          CoverageCollector::new(coverage_dir, session, program_state.clone());
+        match rt.block_on(coverage_collector.start_collecting()) {
+          Ok(()) => Some(coverage_collector),
+          Err(err) => {
+            eprintln!(
+              "Failed to collect coverage of worker \"{}\": {}",
+              name, err
+            );
+            None
+          }
+        }
+      } else {
+        None
+      };
+
     // TODO: run with using select with terminate
 
     // Execute provided source code immediately
@@ -207,6 +230,19 @@ fn run_worker_thread(
     // that it actually terminates.
     rt.block_on(worker.run_event_loop())
       .expect("Panic in event loop");
+
+    if let Some(coverage_collector) = maybe_coverage_collector.as_mut() {
+      // A worker which was terminated by its host can't execute code anymore,
+      // but its coverage can still be taken.
+      worker
+        .js_runtime
+        .v8_isolate()
+        .thread_safe_handle()
+        .cancel_terminate_execution();
+      if let Err(err) = rt.block_on(coverage_collector.stop_collecting()) {
+        eprintln!("Failed to collect coverage of worker \"{}\": {}", name, err);
+      }
+    }
     debug!("Worker thread shuts down {}", &name);
   })?;
 
@@ -222,6 +258,7 @@ struct CreateWorkerArgs {
   has_source_code: bool,
   source_code: String,
   use_deno_namespace: bool,
+  permissions: Option<WorkerPermissionsArg>,
 }
 
 /// Create worker as the host
@@ -243,7 +280,14 @@ fn op_create_worker(
   if use_deno_namespace {
     super::check_unstable(state, "Worker.deno");
   }
-  let permissions = state.borrow::<Permissions>().clone();
+  let parent_permissions = state.borrow::<Permissions>();
+  let permissions = match args.permissions {
+    Some(ref permissions_arg) => {
+      super::check_unstable(state, "Worker.deno.permissions");
+      parent_permissions.for_worker(permissions_arg)?
+    }
+    None => parent_permissions.clone(),
+  };
   let worker_id = state.take::<WorkerId>();
   state.put::<WorkerId>(worker_id + 1);
 
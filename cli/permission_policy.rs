@@ -0,0 +1,344 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Permission policy files for `--permissions=<FILE>`.
+//!
+//! A policy file is a JSON object which configures each kind of permission:
+//!
+//! ```json
+//! {
+//!   "read": { "grant": ["./data"], "deny": ["./data/secrets"] },
+//!   "net": { "grant": ["deno.land", "10.0.0.0/8"], "prompt": false },
+//!   "hrtime": { "deny": true }
+//! }
+//! ```
+//!
+//! `grant` and `deny` are either `true` for the whole permission, or a list of
+//! the descriptors accepted by the matching `--allow-*` flag. Relative paths
+//! are resolved from the directory of the policy file. `"prompt": false`
+//! denies whatever isn't granted, instead of leaving it up to a prompt.
+
+use crate::flags::Flags;
+use crate::flags_allow_net;
+use crate::fs::resolve_from_cwd;
+use crate::permissions::PermissionState;
+use crate::permissions::Permissions;
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::serde_json;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+enum PolicyValue {
+  All(bool),
+  List(Vec<String>),
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct PolicyEntry {
+  grant: Option<PolicyValue>,
+  deny: Option<PolicyValue>,
+  prompt: Option<bool>,
+}
+
+impl PolicyEntry {
+  fn prompt_disabled(&self) -> bool {
+    self.prompt == Some(false)
+  }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionPolicy {
+  #[serde(default)]
+  read: PolicyEntry,
+  #[serde(default)]
+  write: PolicyEntry,
+  #[serde(default)]
+  net: PolicyEntry,
+  #[serde(default)]
+  env: PolicyEntry,
+  #[serde(default)]
+  run: PolicyEntry,
+  #[serde(default)]
+  plugin: PolicyEntry,
+  #[serde(default)]
+  hrtime: PolicyEntry,
+  /// The directory of the policy file.
+  #[serde(skip)]
+  base_dir: PathBuf,
+}
+
+impl PermissionPolicy {
+  /// Reads the policy file at `path`, returning its absolute path and its
+  /// contents.
+  pub fn read(path: &Path) -> Result<(PathBuf, String), AnyError> {
+    let path = resolve_from_cwd(path)?;
+    let source = fs::read_to_string(&path).map_err(|err| {
+      generic_error(format!(
+        "Unable to read permissions policy \"{}\": {}",
+        path.display(),
+        err
+      ))
+    })?;
+    Ok((path, source))
+  }
+
+  pub fn load(path: &Path) -> Result<Self, AnyError> {
+    let (path, source) = Self::read(path)?;
+    Self::parse(&path, &source)
+  }
+
+  /// Parses the contents of the policy file at the absolute `path`, from
+  /// whose directory relative paths are resolved.
+  pub fn parse(path: &Path, source: &str) -> Result<Self, AnyError> {
+    let mut policy: PermissionPolicy =
+      serde_json::from_str(source).map_err(|err| {
+        generic_error(format!(
+          "Invalid permissions policy \"{}\": {}",
+          path.display(),
+          err
+        ))
+      })?;
+    policy.base_dir = path.parent().unwrap().to_path_buf();
+    Ok(policy)
+  }
+
+  /// Adds the grants and denials of the policy to `flags`, as if they had
+  /// been given as `--allow-*` and `--deny-*` flags.
+  pub fn merge_into(&self, flags: &mut Flags) -> Result<(), AnyError> {
+    let resolve_paths = |paths: &[String]| -> Result<Vec<PathBuf>, AnyError> {
+      Ok(paths.iter().map(|path| self.base_dir.join(path)).collect())
+    };
+    let parse_net = |entries: &[String]| -> Result<Vec<String>, AnyError> {
+      for entry in entries {
+        flags_allow_net::validator(entry.to_string()).map_err(generic_error)?;
+      }
+      Ok(flags_allow_net::parse(entries.to_vec()).unwrap())
+    };
+    let keep = |entries: &[String]| -> Result<Vec<String>, AnyError> {
+      Ok(entries.to_vec())
+    };
+    // Commands given as paths are resolved like other paths, bare names are
+    // still looked up in `PATH`.
+    let resolve_cmds = |cmds: &[String]| -> Result<Vec<String>, AnyError> {
+      Ok(
+        cmds
+          .iter()
+          .map(|cmd| {
+            if Path::new(cmd).components().count() > 1 {
+              self.base_dir.join(cmd).to_string_lossy().to_string()
+            } else {
+              cmd.to_string()
+            }
+          })
+          .collect(),
+      )
+    };
+
+    merge_value(
+      &self.read.grant,
+      &mut flags.allow_read,
+      &mut flags.read_allowlist,
+      resolve_paths,
+    )?;
+    merge_value(
+      &self.read.deny,
+      &mut flags.deny_read,
+      &mut flags.read_denylist,
+      resolve_paths,
+    )?;
+    merge_value(
+      &self.write.grant,
+      &mut flags.allow_write,
+      &mut flags.write_allowlist,
+      resolve_paths,
+    )?;
+    merge_value(
+      &self.write.deny,
+      &mut flags.deny_write,
+      &mut flags.write_denylist,
+      resolve_paths,
+    )?;
+    merge_value(
+      &self.net.grant,
+      &mut flags.allow_net,
+      &mut flags.net_allowlist,
+      parse_net,
+    )?;
+    merge_value(
+      &self.net.deny,
+      &mut flags.deny_net,
+      &mut flags.net_denylist,
+      parse_net,
+    )?;
+    merge_value(
+      &self.env.grant,
+      &mut flags.allow_env,
+      &mut flags.env_allowlist,
+      keep,
+    )?;
+    merge_value(
+      &self.env.deny,
+      &mut flags.deny_env,
+      &mut flags.env_denylist,
+      keep,
+    )?;
+    merge_value(
+      &self.run.grant,
+      &mut flags.allow_run,
+      &mut flags.run_allowlist,
+      resolve_cmds,
+    )?;
+    merge_value(
+      &self.run.deny,
+      &mut flags.deny_run,
+      &mut flags.run_denylist,
+      resolve_cmds,
+    )?;
+    merge_flag("plugin", &self.plugin.grant, &mut flags.allow_plugin)?;
+    merge_flag("plugin", &self.plugin.deny, &mut flags.deny_plugin)?;
+    merge_flag("hrtime", &self.hrtime.grant, &mut flags.allow_hrtime)?;
+    merge_flag("hrtime", &self.hrtime.deny, &mut flags.deny_hrtime)?;
+    Ok(())
+  }
+
+  /// Denies whatever isn't granted for the permissions which have prompts
+  /// disabled.
+  pub fn apply_prompts(&self, permissions: &mut Permissions) {
+    permissions.read.prompt &= !self.read.prompt_disabled();
+    permissions.write.prompt &= !self.write.prompt_disabled();
+    permissions.net.prompt &= !self.net.prompt_disabled();
+    permissions.env.prompt &= !self.env.prompt_disabled();
+    permissions.run.prompt &= !self.run.prompt_disabled();
+    if self.plugin.prompt_disabled()
+      && permissions.plugin == PermissionState::Prompt
+    {
+      permissions.plugin = PermissionState::Denied;
+    }
+    if self.hrtime.prompt_disabled()
+      && permissions.hrtime == PermissionState::Prompt
+    {
+      permissions.hrtime = PermissionState::Denied;
+    }
+  }
+}
+
+fn merge_value<T>(
+  value: &Option<PolicyValue>,
+  all: &mut bool,
+  list: &mut Vec<T>,
+  parse: impl Fn(&[String]) -> Result<Vec<T>, AnyError>,
+) -> Result<(), AnyError> {
+  match value {
+    None | Some(PolicyValue::All(false)) => {}
+    Some(PolicyValue::All(true)) => *all = true,
+    Some(PolicyValue::List(entries)) => list.extend(parse(entries)?),
+  }
+  Ok(())
+}
+
+fn merge_flag(
+  name: &str,
+  value: &Option<PolicyValue>,
+  flag: &mut bool,
+) -> Result<(), AnyError> {
+  match value {
+    None | Some(PolicyValue::All(false)) => {}
+    Some(PolicyValue::All(true)) => *flag = true,
+    Some(PolicyValue::List(_)) => {
+      return Err(generic_error(format!(
+        "Invalid permissions policy: \"{}\" can't be given a list",
+        name
+      )))
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use deno_core::serde_json::json;
+
+  #[test]
+  fn test_merge_into() {
+    let mut policy: PermissionPolicy = serde_json::from_value(json!({
+      "read": { "grant": ["./data", "/etc/hosts"], "deny": ["data/secrets"] },
+      "net": { "grant": ["deno.land", ":4545"], "prompt": false },
+      "env": { "grant": true },
+      "run": { "grant": ["git", "./bin/tool"] },
+      "hrtime": { "deny": true },
+    }))
+    .unwrap();
+    policy.base_dir = PathBuf::from("/policy");
+    let mut flags = Flags {
+      read_allowlist: vec![PathBuf::from("/tmp")],
+      ..Default::default()
+    };
+    policy.merge_into(&mut flags).unwrap();
+
+    assert_eq!(
+      flags.read_allowlist,
+      vec![
+        PathBuf::from("/tmp"),
+        PathBuf::from("/policy/./data"),
+        PathBuf::from("/etc/hosts"),
+      ]
+    );
+    assert_eq!(
+      flags.read_denylist,
+      vec![PathBuf::from("/policy/data/secrets")]
+    );
+    assert_eq!(
+      flags.net_allowlist,
+      vec![
+        "deno.land",
+        "0.0.0.0:4545",
+        "127.0.0.1:4545",
+        "localhost:4545"
+      ]
+    );
+    assert!(flags.allow_env);
+    assert_eq!(
+      flags.run_allowlist,
+      vec![
+        "git".to_string(),
+        PathBuf::from("/policy/./bin/tool")
+          .to_string_lossy()
+          .to_string()
+      ]
+    );
+    assert!(flags.deny_hrtime);
+    assert!(!flags.allow_read);
+    assert!(!flags.allow_write);
+
+    let mut permissions = Permissions::from_flags(&flags).unwrap();
+    assert!(permissions.net.prompt);
+    policy.apply_prompts(&mut permissions);
+    assert!(!permissions.net.prompt);
+    assert!(permissions.read.prompt);
+  }
+
+  #[test]
+  fn test_invalid_policy() {
+    let unknown_kind: Result<PermissionPolicy, _> =
+      serde_json::from_value(json!({ "network": { "grant": true } }));
+    assert!(unknown_kind.is_err());
+    let unknown_field: Result<PermissionPolicy, _> =
+      serde_json::from_value(json!({ "net": { "allow": true } }));
+    assert!(unknown_field.is_err());
+
+    let policy: PermissionPolicy =
+      serde_json::from_value(json!({ "hrtime": { "grant": ["x"] } })).unwrap();
+    assert!(policy.merge_into(&mut Flags::default()).is_err());
+    let policy: PermissionPolicy =
+      serde_json::from_value(json!({ "net": { "grant": ["10.0.0.0/33"] } }))
+        .unwrap();
+    assert!(policy.merge_into(&mut Flags::default()).is_err());
+  }
+}
@@ -0,0 +1,224 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Recording of permission checks for `--trace-permissions`.
+//!
+//! Every check made through `Permissions` is recorded together with the JS
+//! stack of the op call which triggered it. The JS runtime sends that stack
+//! through `op_set_permission_trace_stack` right before it dispatches an op,
+//! and `with_op_stack()`/`with_op_stack_async()` make it the current stack
+//! while the op runs.
+
+use crate::colors;
+use crate::permissions::PermissionState;
+use deno_core::error::AnyError;
+use deno_core::futures::future::poll_fn;
+use deno_core::futures::future::Either;
+use deno_core::serde_json::json;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::File;
+use std::future::Future;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+  static ref TRACER: Mutex<Option<Tracer>> = Mutex::new(None);
+}
+
+thread_local! {
+  /// The JS stack of the op call which is about to be dispatched.
+  static PENDING_STACK: RefCell<Option<String>> = RefCell::new(None);
+  /// The JS stack of the op which is currently running.
+  static CURRENT_STACK: RefCell<Option<String>> = RefCell::new(None);
+}
+
+struct Tracer {
+  /// If set, every check is written to this file as a JSON line and no
+  /// summary is printed.
+  file: Option<File>,
+  /// The descriptors which were checked, by permission name. `None` stands
+  /// for a check of the whole permission.
+  used: BTreeMap<&'static str, BTreeSet<Option<String>>>,
+}
+
+/// Starts tracing permission checks. Checks are written to `maybe_file` as
+/// JSON lines if it is given, otherwise `finish()` prints a summary.
+pub fn init(maybe_file: Option<&Path>) -> Result<(), AnyError> {
+  let file = match maybe_file {
+    Some(path) => Some(File::create(path)?),
+    None => None,
+  };
+  *TRACER.lock().unwrap() = Some(Tracer {
+    file,
+    used: BTreeMap::new(),
+  });
+  ENABLED.store(true, Ordering::SeqCst);
+  Ok(())
+}
+
+pub fn is_enabled() -> bool {
+  ENABLED.load(Ordering::Relaxed)
+}
+
+/// Records a permission check. `descriptor` is `None` for checks of the
+/// whole permission, e.g. for `--allow-hrtime`.
+pub fn record(
+  name: &'static str,
+  descriptor: Option<&dyn fmt::Display>,
+  state: PermissionState,
+) {
+  if !is_enabled() {
+    return;
+  }
+  let descriptor = descriptor.map(|d| d.to_string());
+  let stack = CURRENT_STACK.with(|s| s.borrow().clone());
+  let mut maybe_tracer = TRACER.lock().unwrap();
+  let tracer = match maybe_tracer.as_mut() {
+    Some(tracer) => tracer,
+    None => return,
+  };
+  if let Some(file) = tracer.file.as_mut() {
+    let line = json!({
+      "permission": name,
+      "descriptor": descriptor,
+      "result": state.to_string(),
+      "stack": stack,
+    });
+    // The process may exit at any time, so every line is written right away.
+    if let Err(err) = writeln!(file, "{}", line) {
+      debug!("Failed to write permission trace: {}", err);
+    }
+  } else {
+    tracer.used.entry(name).or_default().insert(descriptor);
+  }
+}
+
+/// Stops tracing and prints the minimal permission flags for the recorded
+/// checks, unless they were written to a file.
+pub fn finish() {
+  ENABLED.store(false, Ordering::SeqCst);
+  let tracer = match TRACER.lock().unwrap().take() {
+    Some(tracer) => tracer,
+    None => return,
+  };
+  if tracer.file.is_some() {
+    return;
+  }
+  let flags = minimal_flags(&tracer.used);
+  if flags.is_empty() {
+    eprintln!("{} No permissions were used", colors::green_bold("Trace"));
+  } else {
+    eprintln!(
+      "{} Permissions used: {}",
+      colors::green_bold("Trace"),
+      flags.join(" ")
+    );
+  }
+}
+
+/// Returns the `--allow-*` flags which grant exactly the used permissions.
+fn minimal_flags(
+  used: &BTreeMap<&'static str, BTreeSet<Option<String>>>,
+) -> Vec<String> {
+  used
+    .iter()
+    .map(|(name, descriptors)| {
+      // Plugins and hrtime can not be scoped.
+      if descriptors.contains(&None) || *name == "plugin" || *name == "hrtime" {
+        return format!("--allow-{}", name);
+      }
+      let mut list: Vec<&str> =
+        descriptors.iter().flatten().map(String::as_str).collect();
+      if *name == "read" || *name == "write" {
+        // Paths below another used path are covered by it.
+        list = list
+          .iter()
+          .filter(|path| {
+            !list
+              .iter()
+              .any(|other| other != *path && Path::new(path).starts_with(other))
+          })
+          .cloned()
+          .collect();
+      }
+      format!("--allow-{}={}", name, list.join(","))
+    })
+    .collect()
+}
+
+/// Sets the JS stack of the op call which is about to be dispatched.
+pub fn set_pending_stack(stack: String) {
+  PENDING_STACK.with(|s| *s.borrow_mut() = Some(stack));
+}
+
+/// Runs a sync op with the JS stack of the call which dispatched it.
+pub fn with_op_stack<R>(op: impl FnOnce() -> R) -> R {
+  if !is_enabled() {
+    return op();
+  }
+  let stack = PENDING_STACK.with(|s| s.borrow_mut().take());
+  CURRENT_STACK.with(|s| *s.borrow_mut() = stack);
+  let result = op();
+  CURRENT_STACK.with(|s| *s.borrow_mut() = None);
+  result
+}
+
+/// As `with_op_stack()`, but for async ops. Their permission checks usually
+/// run once the future is polled, so the stack is restored on every poll.
+pub fn with_op_stack_async<F: Future>(
+  fut: F,
+) -> impl Future<Output = F::Output> {
+  if !is_enabled() {
+    return Either::Left(fut);
+  }
+  let stack = PENDING_STACK.with(|s| s.borrow_mut().take());
+  let mut fut = Box::pin(fut);
+  Either::Right(poll_fn(move |cx| {
+    CURRENT_STACK.with(|s| *s.borrow_mut() = stack.clone());
+    let poll = fut.as_mut().poll(cx);
+    CURRENT_STACK.with(|s| *s.borrow_mut() = None);
+    poll
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_minimal_flags() {
+    let mut used = BTreeMap::new();
+    let mut read = BTreeSet::new();
+    read.insert(Some("/a/b".to_string()));
+    read.insert(Some("/a".to_string()));
+    read.insert(Some("/c/d".to_string()));
+    used.insert("read", read);
+    let mut net = BTreeSet::new();
+    net.insert(Some("deno.land:443".to_string()));
+    used.insert("net", net);
+    let mut env = BTreeSet::new();
+    env.insert(Some("HOME".to_string()));
+    env.insert(None);
+    used.insert("env", env);
+    let mut hrtime = BTreeSet::new();
+    hrtime.insert(None);
+    used.insert("hrtime", hrtime);
+
+    assert_eq!(
+      minimal_flags(&used),
+      vec![
+        "--allow-env",
+        "--allow-hrtime",
+        "--allow-net=deno.land:443",
+        "--allow-read=/a,/c/d",
+      ]
+    );
+  }
+}
@@ -2,7 +2,10 @@
 
 use crate::colors;
 use crate::flags::Flags;
+use crate::flags_allow_net::NetPattern;
 use crate::fs::resolve_from_cwd;
+use crate::permission_policy::PermissionPolicy;
+use crate::permission_trace;
 use deno_core::error::custom_error;
 use deno_core::error::uri_error;
 use deno_core::error::AnyError;
@@ -11,6 +14,7 @@ use deno_core::ModuleSpecifier;
 use serde::Deserialize;
 use std::collections::HashSet;
 use std::env::current_dir;
+use std::ffi::OsStr;
 use std::fmt;
 use std::hash::Hash;
 #[cfg(not(test))]
@@ -61,11 +65,57 @@ impl Default for PermissionState {
   }
 }
 
-#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct UnaryPermission<T: Eq + Hash> {
   pub global_state: PermissionState,
   pub granted_list: HashSet<T>,
   pub denied_list: HashSet<T>,
+  /// If unset, whatever isn't granted is denied instead of prompted for.
+  #[serde(default = "default_prompt")]
+  pub prompt: bool,
+}
+
+fn default_prompt() -> bool {
+  true
+}
+
+impl<T: Eq + Hash> Default for UnaryPermission<T> {
+  fn default() -> Self {
+    UnaryPermission {
+      global_state: Default::default(),
+      granted_list: Default::default(),
+      denied_list: Default::default(),
+      prompt: default_prompt(),
+    }
+  }
+}
+
+impl<T: Eq + Hash> UnaryPermission<T> {
+  /// A permission which denies everything.
+  fn denied_all(prompt: bool) -> Self {
+    UnaryPermission {
+      global_state: PermissionState::Denied,
+      granted_list: HashSet::new(),
+      denied_list: HashSet::new(),
+      prompt,
+    }
+  }
+
+  /// A denied global state only denies everything if nothing is listed.
+  /// Otherwise it records that prompts for the listed entries were declined,
+  /// and entries which aren't listed can still be prompted for.
+  fn is_denied_all(&self) -> bool {
+    self.global_state == PermissionState::Denied && self.denied_list.is_empty()
+  }
+
+  /// The state of descriptors which are neither granted nor denied.
+  fn ungranted_state(&self) -> PermissionState {
+    if self.prompt {
+      PermissionState::Prompt
+    } else {
+      PermissionState::Denied
+    }
+  }
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
@@ -73,48 +123,226 @@ pub struct Permissions {
   pub read: UnaryPermission<PathBuf>,
   pub write: UnaryPermission<PathBuf>,
   pub net: UnaryPermission<String>,
-  pub env: PermissionState,
-  pub run: PermissionState,
+  pub env: UnaryPermission<String>,
+  pub run: UnaryPermission<String>,
   pub plugin: PermissionState,
   pub hrtime: PermissionState,
 }
 
-fn resolve_fs_allowlist(allowlist: &[PathBuf]) -> HashSet<PathBuf> {
-  allowlist
+/// A permission given to a worker through `new Worker()`: either the whole
+/// permission, or only the listed descriptors.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum WorkerPermissionArg {
+  Bool(bool),
+  List(Vec<String>),
+}
+
+/// The permissions given to a worker through `new Worker()`. Permissions
+/// which are not set are inherited from the parent.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WorkerPermissionsArg {
+  pub read: Option<WorkerPermissionArg>,
+  pub write: Option<WorkerPermissionArg>,
+  pub net: Option<WorkerPermissionArg>,
+  pub env: Option<WorkerPermissionArg>,
+  pub run: Option<WorkerPermissionArg>,
+  pub plugin: Option<bool>,
+  pub hrtime: Option<bool>,
+}
+
+fn escalation_error(access: &str) -> AnyError {
+  custom_error(
+    "PermissionDenied",
+    format!("Can't escalate parent thread permissions: {}", access),
+  )
+}
+
+/// Narrows a parent's permission down to the one given to a worker.
+/// `resolve` turns a listed descriptor into its stored form, along with the
+/// parent's state for it.
+fn narrow_unary<T: Eq + Hash + Clone>(
+  parent: &UnaryPermission<T>,
+  name: &str,
+  arg: &Option<WorkerPermissionArg>,
+  resolve: impl Fn(&str) -> Result<(T, PermissionState), AnyError>,
+) -> Result<UnaryPermission<T>, AnyError> {
+  match arg {
+    None => Ok(parent.clone()),
+    Some(WorkerPermissionArg::Bool(false)) => {
+      Ok(UnaryPermission::denied_all(parent.prompt))
+    }
+    Some(WorkerPermissionArg::Bool(true)) => {
+      // The worker gets exactly the parent's permission, including its
+      // denials.
+      if parent.global_state != PermissionState::Granted {
+        return Err(escalation_error(&format!("{} access", name)));
+      }
+      Ok(parent.clone())
+    }
+    Some(WorkerPermissionArg::List(list)) => {
+      let mut granted_list = HashSet::new();
+      for descriptor in list {
+        let (resolved, state) = resolve(descriptor)?;
+        if state != PermissionState::Granted {
+          return Err(escalation_error(&format!(
+            "{} access to \"{}\"",
+            name, descriptor
+          )));
+        }
+        granted_list.insert(resolved);
+      }
+      // Descriptors outside of the list are treated as the parent treats
+      // descriptors it wasn't granted.
+      let global_state = if parent.global_state == PermissionState::Denied {
+        PermissionState::Denied
+      } else {
+        PermissionState::Prompt
+      };
+      Ok(UnaryPermission {
+        global_state,
+        granted_list,
+        denied_list: parent.denied_list.clone(),
+        prompt: parent.prompt,
+      })
+    }
+  }
+}
+
+fn narrow_state(
+  parent: PermissionState,
+  name: &str,
+  arg: Option<bool>,
+) -> Result<PermissionState, AnyError> {
+  match arg {
+    None => Ok(parent),
+    Some(false) => Ok(PermissionState::Denied),
+    Some(true) if parent == PermissionState::Granted => Ok(parent),
+    Some(true) => Err(escalation_error(&format!("{} access", name))),
+  }
+}
+
+fn resolve_fs_list(list: &[PathBuf]) -> HashSet<PathBuf> {
+  list
     .iter()
     .map(|raw_path| resolve_from_cwd(Path::new(&raw_path)).unwrap())
     .collect()
 }
 
 impl Permissions {
-  pub fn from_flags(flags: &Flags) -> Self {
-    fn state_from_flag_bool(flag: bool) -> PermissionState {
-      if flag {
+  /// Creates the permissions given by the `--allow-*` and `--deny-*` flags,
+  /// together with the policy file given by `--permissions`, if any.
+  pub fn from_flags(flags: &Flags) -> Result<Self, AnyError> {
+    let maybe_policy = match &flags.permissions_policy {
+      Some(path) => Some(PermissionPolicy::load(path)?),
+      None => None,
+    };
+    Self::from_flags_and_policy(flags, maybe_policy.as_ref())
+  }
+
+  /// Same as `Permissions::from_flags()`, but with an already loaded policy
+  /// instead of the one given by `--permissions`.
+  pub fn from_flags_and_policy(
+    flags: &Flags,
+    maybe_policy: Option<&PermissionPolicy>,
+  ) -> Result<Self, AnyError> {
+    let policy = match maybe_policy {
+      Some(policy) => policy,
+      None => return Ok(Self::from_permission_flags(flags)),
+    };
+    let mut flags = flags.clone();
+    policy.merge_into(&mut flags)?;
+    let mut permissions = Self::from_permission_flags(&flags);
+    policy.apply_prompts(&mut permissions);
+    Ok(permissions)
+  }
+
+  fn from_permission_flags(flags: &Flags) -> Self {
+    fn state_from_flags(allow: bool, deny: bool) -> PermissionState {
+      if deny {
+        PermissionState::Denied
+      } else if allow {
         PermissionState::Granted
       } else {
         PermissionState::Prompt
       }
     }
+    // A `--deny-*` flag without a list denies the whole permission, so none
+    // of the listed entries apply.
+    fn listed_unless_denied<T: Eq + Hash>(
+      deny: bool,
+      list: HashSet<T>,
+    ) -> HashSet<T> {
+      if deny {
+        HashSet::new()
+      } else {
+        list
+      }
+    }
     Self {
       read: UnaryPermission::<PathBuf> {
-        global_state: state_from_flag_bool(flags.allow_read),
-        granted_list: resolve_fs_allowlist(&flags.read_allowlist),
+        global_state: state_from_flags(flags.allow_read, flags.deny_read),
+        granted_list: listed_unless_denied(
+          flags.deny_read,
+          resolve_fs_list(&flags.read_allowlist),
+        ),
+        denied_list: listed_unless_denied(
+          flags.deny_read,
+          resolve_fs_list(&flags.read_denylist),
+        ),
         ..Default::default()
       },
       write: UnaryPermission::<PathBuf> {
-        global_state: state_from_flag_bool(flags.allow_write),
-        granted_list: resolve_fs_allowlist(&flags.write_allowlist),
+        global_state: state_from_flags(flags.allow_write, flags.deny_write),
+        granted_list: listed_unless_denied(
+          flags.deny_write,
+          resolve_fs_list(&flags.write_allowlist),
+        ),
+        denied_list: listed_unless_denied(
+          flags.deny_write,
+          resolve_fs_list(&flags.write_denylist),
+        ),
         ..Default::default()
       },
       net: UnaryPermission::<String> {
-        global_state: state_from_flag_bool(flags.allow_net),
-        granted_list: flags.net_allowlist.iter().cloned().collect(),
+        global_state: state_from_flags(flags.allow_net, flags.deny_net),
+        granted_list: listed_unless_denied(
+          flags.deny_net,
+          flags.net_allowlist.iter().cloned().collect(),
+        ),
+        denied_list: listed_unless_denied(
+          flags.deny_net,
+          flags.net_denylist.iter().cloned().collect(),
+        ),
         ..Default::default()
       },
-      env: state_from_flag_bool(flags.allow_env),
-      run: state_from_flag_bool(flags.allow_run),
-      plugin: state_from_flag_bool(flags.allow_plugin),
-      hrtime: state_from_flag_bool(flags.allow_hrtime),
+      env: UnaryPermission::<String> {
+        global_state: state_from_flags(flags.allow_env, flags.deny_env),
+        granted_list: listed_unless_denied(
+          flags.deny_env,
+          flags.env_allowlist.iter().map(|k| env_key(k)).collect(),
+        ),
+        denied_list: listed_unless_denied(
+          flags.deny_env,
+          flags.env_denylist.iter().map(|k| env_key(k)).collect(),
+        ),
+        ..Default::default()
+      },
+      run: UnaryPermission::<String> {
+        global_state: state_from_flags(flags.allow_run, flags.deny_run),
+        granted_list: listed_unless_denied(
+          flags.deny_run,
+          flags.run_allowlist.iter().cloned().collect(),
+        ),
+        denied_list: listed_unless_denied(
+          flags.deny_run,
+          flags.run_denylist.iter().cloned().collect(),
+        ),
+        ..Default::default()
+      },
+      plugin: state_from_flags(flags.allow_plugin, flags.deny_plugin),
+      hrtime: state_from_flags(flags.allow_hrtime, flags.deny_hrtime),
     }
   }
 
@@ -150,21 +378,66 @@ impl Permissions {
         global_state: PermissionState::Granted,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
       plugin: PermissionState::Granted,
       hrtime: PermissionState::Granted,
     }
   }
 
+  /// Returns the permissions of a worker which is given `arg`. Fails if they
+  /// would grant anything these permissions don't grant.
+  pub fn for_worker(
+    &self,
+    arg: &WorkerPermissionsArg,
+  ) -> Result<Self, AnyError> {
+    Ok(Self {
+      read: narrow_unary(&self.read, "read", &arg.read, |path| {
+        let path = resolve_from_cwd(Path::new(path))?;
+        let state = self.query_read(&Some(&path));
+        Ok((path, state))
+      })?,
+      write: narrow_unary(&self.write, "write", &arg.write, |path| {
+        let path = resolve_from_cwd(Path::new(path))?;
+        let state = self.query_write(&Some(&path));
+        Ok((path, state))
+      })?,
+      net: narrow_unary(&self.net, "net", &arg.net, |entry| {
+        let pattern = parse_net_descriptor(entry)?;
+        Ok((pattern.to_string(), self.query_net_pattern(&pattern)))
+      })?,
+      env: narrow_unary(&self.env, "env", &arg.env, |key| {
+        Ok((env_key(key), self.query_env(&Some(key))))
+      })?,
+      run: narrow_unary(&self.run, "run", &arg.run, |cmd| {
+        Ok((cmd.to_string(), self.query_run(&Some(cmd))))
+      })?,
+      plugin: narrow_state(self.plugin, "plugin", arg.plugin)?,
+      hrtime: narrow_state(self.hrtime, "hrtime", arg.hrtime)?,
+    })
+  }
+
   pub fn query_read(&self, path: &Option<&Path>) -> PermissionState {
     let path = path.map(|p| resolve_from_cwd(p).unwrap());
-    if self.read.global_state == PermissionState::Denied
-      && match path.as_ref() {
-        None => true,
-        Some(path) => check_path_blocklist(path, &self.read.denied_list),
+    if match path.as_ref() {
+      // Denials always win, so nothing is granted as a whole while some of it
+      // is denied.
+      None => {
+        self.read.global_state == PermissionState::Denied
+          || !self.read.denied_list.is_empty()
       }
-    {
+      Some(path) => {
+        check_path_allowlist(path, &self.read.denied_list)
+          || (self.read.global_state == PermissionState::Denied
+            && check_path_blocklist(path, &self.read.denied_list))
+      }
+    } {
       return PermissionState::Denied;
     }
     if self.read.global_state == PermissionState::Granted
@@ -175,17 +448,25 @@ impl Permissions {
     {
       return PermissionState::Granted;
     }
-    PermissionState::Prompt
+    if self.read.is_denied_all() {
+      return PermissionState::Denied;
+    }
+    self.read.ungranted_state()
   }
 
   pub fn query_write(&self, path: &Option<&Path>) -> PermissionState {
     let path = path.map(|p| resolve_from_cwd(p).unwrap());
-    if self.write.global_state == PermissionState::Denied
-      && match path.as_ref() {
-        None => true,
-        Some(path) => check_path_blocklist(path, &self.write.denied_list),
+    if match path.as_ref() {
+      None => {
+        self.write.global_state == PermissionState::Denied
+          || !self.write.denied_list.is_empty()
       }
-    {
+      Some(path) => {
+        check_path_allowlist(path, &self.write.denied_list)
+          || (self.write.global_state == PermissionState::Denied
+            && check_path_blocklist(path, &self.write.denied_list))
+      }
+    } {
       return PermissionState::Denied;
     }
     if self.write.global_state == PermissionState::Granted
@@ -196,53 +477,112 @@ impl Permissions {
     {
       return PermissionState::Granted;
     }
-    PermissionState::Prompt
+    if self.write.is_denied_all() {
+      return PermissionState::Denied;
+    }
+    self.write.ungranted_state()
   }
 
   pub fn query_net(&self, host: &str, port: Option<u16>) -> PermissionState {
+    self.query_net_pattern(&NetPattern::from_host_and_port(host, port))
+  }
+
+  /// A pattern is only granted if all of its hosts and ports are, and none of
+  /// them is denied.
+  fn query_net_pattern(&self, pattern: &NetPattern) -> PermissionState {
     if self.net.global_state == PermissionState::Denied
-      || check_host_and_port_list(host, port, &self.net.denied_list)
+      || net_list(&self.net.denied_list).any(|denied| denied.covers(pattern))
     {
       return PermissionState::Denied;
     }
-    if self.net.global_state == PermissionState::Granted
-      || check_host_and_port_list(host, port, &self.net.granted_list)
+    if (self.net.global_state == PermissionState::Granted
+      || net_list(&self.net.granted_list)
+        .any(|granted| granted.covers(pattern)))
+      && !net_list(&self.net.denied_list).any(|denied| denied.overlaps(pattern))
     {
       return PermissionState::Granted;
     }
-    PermissionState::Prompt
+    self.net.ungranted_state()
   }
 
   pub fn query_net_url(
     &self,
     url: &Option<&str>,
   ) -> Result<PermissionState, AnyError> {
-    if url.is_none() {
-      return Ok(self.net.global_state);
+    match url {
+      None if !self.net.denied_list.is_empty() => Ok(PermissionState::Denied),
+      None if self.net.global_state == PermissionState::Prompt => {
+        Ok(self.net.ungranted_state())
+      }
+      None => Ok(self.net.global_state),
+      Some(url) => Ok(self.query_net_pattern(&parse_net_descriptor(url)?)),
     }
-    let url: &str = url.unwrap();
-    // If url is invalid, then throw a TypeError.
-    let parsed = url::Url::parse(url)?;
-    // The url may be parsed correctly but still lack a host, i.e. "localhost:235" or "mailto:someone@somewhere.com" or "file:/1.txt"
-    // Note that host:port combos are parsed as scheme:path
-    if parsed.host().is_none() {
-      return Err(custom_error(
-        "URIError",
-        "invalid urlormat: <scheme>://<host>[:port][/subpath]",
-      ));
+  }
+
+  pub fn query_env(&self, key: &Option<&str>) -> PermissionState {
+    let key = key.map(env_key);
+    if match key.as_ref() {
+      None => {
+        self.env.global_state == PermissionState::Denied
+          || !self.env.denied_list.is_empty()
+      }
+      Some(key) => self.env.denied_list.contains(key),
+    } {
+      return PermissionState::Denied;
     }
-    Ok(self.query_net(
-      &format!("{}", parsed.host().unwrap()),
-      parsed.port_or_known_default(),
-    ))
+    if self.env.global_state == PermissionState::Granted
+      || match key.as_ref() {
+        None => false,
+        Some(key) => self.env.granted_list.contains(key),
+      }
+    {
+      return PermissionState::Granted;
+    }
+    if self.env.is_denied_all() {
+      return PermissionState::Denied;
+    }
+    self.env.ungranted_state()
   }
 
-  pub fn query_env(&self) -> PermissionState {
-    self.env
+  pub fn query_run(&self, cmd: &Option<&str>) -> PermissionState {
+    let resolved = cmd.and_then(|cmd| {
+      resolve_cmd(cmd, std::env::var_os("PATH").as_deref(), None)
+    });
+    self.query_resolved_run(cmd, resolved.as_deref())
   }
 
-  pub fn query_run(&self) -> PermissionState {
-    self.run
+  /// Queries the permission to run `cmd`, which resolves to the binary
+  /// `resolved`.
+  fn query_resolved_run(
+    &self,
+    cmd: &Option<&str>,
+    resolved: Option<&Path>,
+  ) -> PermissionState {
+    if match cmd {
+      None => {
+        self.run.global_state == PermissionState::Denied
+          || !self.run.denied_list.is_empty()
+      }
+      // A denied name is denied whichever binary it resolves to.
+      Some(cmd) => {
+        self.run.denied_list.contains(*cmd)
+          || check_cmd_list(cmd, resolved, &self.run.denied_list)
+      }
+    } {
+      return PermissionState::Denied;
+    }
+    if self.run.global_state == PermissionState::Granted
+      || match cmd {
+        None => false,
+        Some(cmd) => check_cmd_list(cmd, resolved, &self.run.granted_list),
+      }
+    {
+      return PermissionState::Granted;
+    }
+    if self.run.is_denied_all() {
+      return PermissionState::Denied;
+    }
+    self.run.ungranted_state()
   }
 
   pub fn query_plugin(&self) -> PermissionState {
@@ -348,10 +688,12 @@ impl Permissions {
           "Deno requests network access to \"{}\"",
           url
         )) {
-          self.net.granted_list.insert(url.to_string());
+          let pattern = parse_net_descriptor(url)?;
+          self.net.granted_list.insert(pattern.to_string());
           return Ok(PermissionState::Granted);
         } else {
-          self.net.denied_list.insert(url.to_string());
+          let pattern = parse_net_descriptor(url)?;
+          self.net.denied_list.insert(pattern.to_string());
           self.net.global_state = PermissionState::Denied;
           return Ok(PermissionState::Denied);
         }
@@ -373,26 +715,68 @@ impl Permissions {
     }
   }
 
-  pub fn request_env(&mut self) -> PermissionState {
-    if self.env == PermissionState::Prompt {
-      if permission_prompt("Deno requests access to environment variables") {
-        self.env = PermissionState::Granted;
-      } else {
-        self.env = PermissionState::Denied;
+  pub fn request_env(&mut self, key: &Option<&str>) -> PermissionState {
+    if let Some(key) = key {
+      let key = env_key(key);
+      let state = self.query_env(&Some(key.as_str()));
+      if state == PermissionState::Prompt {
+        if permission_prompt(&format!(
+          "Deno requests access to environment variable \"{}\"",
+          key
+        )) {
+          self.env.granted_list.insert(key);
+          return PermissionState::Granted;
+        } else {
+          self.env.denied_list.insert(key);
+          self.env.global_state = PermissionState::Denied;
+          return PermissionState::Denied;
+        }
+      }
+      state
+    } else {
+      let state = self.query_env(&None);
+      if state == PermissionState::Prompt {
+        if permission_prompt("Deno requests access to environment variables") {
+          self.env.granted_list.clear();
+          self.env.global_state = PermissionState::Granted;
+          return PermissionState::Granted;
+        } else {
+          self.env.global_state = PermissionState::Denied;
+          return PermissionState::Denied;
+        }
       }
+      state
     }
-    self.env
   }
 
-  pub fn request_run(&mut self) -> PermissionState {
-    if self.run == PermissionState::Prompt {
-      if permission_prompt("Deno requests to access to run a subprocess") {
-        self.run = PermissionState::Granted;
-      } else {
-        self.run = PermissionState::Denied;
+  pub fn request_run(&mut self, cmd: &Option<&str>) -> PermissionState {
+    if let Some(cmd) = cmd {
+      let state = self.query_run(&Some(*cmd));
+      if state == PermissionState::Prompt {
+        if permission_prompt(&format!("Deno requests to run \"{}\"", cmd)) {
+          self.run.granted_list.insert(cmd.to_string());
+          return PermissionState::Granted;
+        } else {
+          self.run.denied_list.insert(cmd.to_string());
+          self.run.global_state = PermissionState::Denied;
+          return PermissionState::Denied;
+        }
+      }
+      state
+    } else {
+      let state = self.query_run(&None);
+      if state == PermissionState::Prompt {
+        if permission_prompt("Deno requests to access to run a subprocess") {
+          self.run.granted_list.clear();
+          self.run.global_state = PermissionState::Granted;
+          return PermissionState::Granted;
+        } else {
+          self.run.global_state = PermissionState::Denied;
+          return PermissionState::Denied;
+        }
       }
+      state
     }
-    self.run
   }
 
   pub fn request_plugin(&mut self) -> PermissionState {
@@ -454,7 +838,12 @@ impl Permissions {
     url: &Option<&str>,
   ) -> Result<PermissionState, AnyError> {
     if let Some(url) = url {
-      self.net.granted_list.remove(*url);
+      let pattern = parse_net_descriptor(url)?;
+      self.net.granted_list.retain(|entry| {
+        !entry
+          .parse::<NetPattern>()
+          .map_or(false, |granted| pattern.covers(&granted))
+      });
     } else {
       self.net.granted_list.clear();
       if self.net.global_state == PermissionState::Granted {
@@ -464,18 +853,28 @@ impl Permissions {
     self.query_net_url(url)
   }
 
-  pub fn revoke_env(&mut self) -> PermissionState {
-    if self.env == PermissionState::Granted {
-      self.env = PermissionState::Prompt;
+  pub fn revoke_env(&mut self, key: &Option<&str>) -> PermissionState {
+    if let Some(key) = key {
+      self.env.granted_list.remove(&env_key(key));
+    } else {
+      self.env.granted_list.clear();
+      if self.env.global_state == PermissionState::Granted {
+        self.env.global_state = PermissionState::Prompt;
+      }
     }
-    self.env
+    self.query_env(key)
   }
 
-  pub fn revoke_run(&mut self) -> PermissionState {
-    if self.run == PermissionState::Granted {
-      self.run = PermissionState::Prompt;
+  pub fn revoke_run(&mut self, cmd: &Option<&str>) -> PermissionState {
+    if let Some(cmd) = cmd {
+      self.run.granted_list.remove(*cmd);
+    } else {
+      self.run.granted_list.clear();
+      if self.run.global_state == PermissionState::Granted {
+        self.run.global_state = PermissionState::Prompt;
+      }
     }
-    self.run
+    self.query_run(cmd)
   }
 
   pub fn revoke_plugin(&mut self) -> PermissionState {
@@ -494,7 +893,9 @@ impl Permissions {
 
   pub fn check_read(&self, path: &Path) -> Result<(), AnyError> {
     let (resolved_path, display_path) = self.resolved_and_display_path(path);
-    self.query_read(&Some(&resolved_path)).check(
+    let state = self.query_read(&Some(&resolved_path));
+    permission_trace::record("read", Some(&resolved_path.display()), state);
+    state.check(
       &format!("read access to \"{}\"", display_path.display()),
       "--allow-read",
     )
@@ -508,21 +909,29 @@ impl Permissions {
     display: &str,
   ) -> Result<(), AnyError> {
     let resolved_path = resolve_from_cwd(path).unwrap();
-    self
-      .query_read(&Some(&resolved_path))
-      .check(&format!("read access to <{}>", display), "--allow-read")
+    let state = self.query_read(&Some(&resolved_path));
+    permission_trace::record("read", Some(&resolved_path.display()), state);
+    state.check(&format!("read access to <{}>", display), "--allow-read")
   }
 
   pub fn check_write(&self, path: &Path) -> Result<(), AnyError> {
     let (resolved_path, display_path) = self.resolved_and_display_path(path);
-    self.query_write(&Some(&resolved_path)).check(
+    let state = self.query_write(&Some(&resolved_path));
+    permission_trace::record("write", Some(&resolved_path.display()), state);
+    state.check(
       &format!("write access to \"{}\"", display_path.display()),
       "--allow-write",
     )
   }
 
   pub fn check_net(&self, hostname: &str, port: u16) -> Result<(), AnyError> {
-    self.query_net(hostname, Some(port)).check(
+    let state = self.query_net(hostname, Some(port));
+    permission_trace::record(
+      "net",
+      Some(&format_args!("{}:{}", hostname, port)),
+      state,
+    );
+    state.check(
       &format!("network access to \"{}:{}\"", hostname, port),
       "--allow-net",
     )
@@ -530,38 +939,82 @@ impl Permissions {
 
   pub fn check_net_url(&self, url: &url::Url) -> Result<(), AnyError> {
     let host = url.host_str().ok_or_else(|| uri_error("missing host"))?;
-    self
-      .query_net(host, url.port_or_known_default())
-      .check(&format!("network access to \"{}\"", url), "--allow-net")
+    let port = url.port_or_known_default();
+    let state = self.query_net(host, port);
+    match port {
+      Some(port) => permission_trace::record(
+        "net",
+        Some(&format_args!("{}:{}", host, port)),
+        state,
+      ),
+      None => permission_trace::record("net", Some(&host), state),
+    }
+    state.check(&format!("network access to \"{}\"", url), "--allow-net")
   }
 
   /// A helper function that determines if the module specifier is a local or
-  /// remote, and performs a read or net check for the specifier.
+  /// remote, and performs a read or net check for the specifier. Data and blob
+  /// URLs carry their source, so they don't need any permission.
   pub fn check_specifier(
     &self,
     specifier: &ModuleSpecifier,
   ) -> Result<(), AnyError> {
     let url = specifier.as_url();
-    if url.scheme() == "file" {
-      let path = url.to_file_path().unwrap();
-      self.check_read(&path)
-    } else {
-      self.check_net_url(url)
+    match url.scheme() {
+      "file" => {
+        let path = url.to_file_path().unwrap();
+        self.check_read(&path)
+      }
+      "data" | "blob" => Ok(()),
+      _ => self.check_net_url(url),
     }
   }
 
-  pub fn check_env(&self) -> Result<(), AnyError> {
-    self
-      .env
-      .check("access to environment variables", "--allow-env")
+  pub fn check_env(&self, key: &str) -> Result<(), AnyError> {
+    let state = self.query_env(&Some(key));
+    permission_trace::record("env", Some(&key), state);
+    state.check(
+      &format!("access to environment variable \"{}\"", key),
+      "--allow-env",
+    )
+  }
+
+  /// Checks access to all environment variables, for operations which are
+  /// not scoped to a single variable.
+  pub fn check_env_all(&self) -> Result<(), AnyError> {
+    let state = self.query_env(&None);
+    permission_trace::record("env", None, state);
+    state.check("access to environment variables", "--allow-env")
+  }
+
+  /// Checks access to run `cmd`, which resolves to the binary `resolved`
+  /// with the `PATH` and working directory of the subprocess, see
+  /// `resolve_cmd()`. The subprocess has to spawn exactly that binary.
+  pub fn check_run(
+    &self,
+    cmd: &str,
+    resolved: Option<&Path>,
+  ) -> Result<(), AnyError> {
+    let state = self.query_resolved_run(&Some(cmd), resolved);
+    permission_trace::record("run", Some(&cmd), state);
+    state.check(&format!("access to run \"{}\"", cmd), "--allow-run")
   }
 
-  pub fn check_run(&self) -> Result<(), AnyError> {
-    self.run.check("access to run a subprocess", "--allow-run")
+  /// Checks access to run any subprocess, for operations which are not scoped
+  /// to a single command.
+  pub fn check_run_all(&self) -> Result<(), AnyError> {
+    let state = self.query_run(&None);
+    permission_trace::record("run", None, state);
+    state.check("access to run a subprocess", "--allow-run")
   }
 
   pub fn check_plugin(&self, path: &Path) -> Result<(), AnyError> {
-    let (_, display_path) = self.resolved_and_display_path(path);
+    let (resolved_path, display_path) = self.resolved_and_display_path(path);
+    permission_trace::record(
+      "plugin",
+      Some(&resolved_path.display()),
+      self.plugin,
+    );
     self.plugin.check(
       &format!("access to open a plugin: {}", display_path.display()),
       "--allow-plugin",
@@ -569,6 +1022,7 @@ impl Permissions {
   }
 
   pub fn check_hrtime(&self) -> Result<(), AnyError> {
+    permission_trace::record("hrtime", None, self.hrtime);
     self
       .hrtime
       .check("access to high precision time", "--allow-hrtime")
@@ -665,26 +1119,111 @@ fn check_path_blocklist(path: &Path, blocklist: &HashSet<PathBuf>) -> bool {
   false
 }
 
-fn check_host_and_port_list(
-  host: &str,
-  port: Option<u16>,
-  allowlist: &HashSet<String>,
+/// Parses the valid entries of a `--allow-net` style list.
+fn net_list(list: &HashSet<String>) -> impl Iterator<Item = NetPattern> + '_ {
+  list
+    .iter()
+    .filter_map(|entry| entry.parse::<NetPattern>().ok())
+}
+
+/// Parses the `url` of a net permission descriptor, which is either a URL or
+/// an entry as accepted by `--allow-net`.
+fn parse_net_descriptor(url: &str) -> Result<NetPattern, AnyError> {
+  if !url.contains("://") {
+    return url.parse::<NetPattern>().map_err(uri_error);
+  }
+  // If url is invalid, then throw a TypeError.
+  let parsed = url::Url::parse(url)?;
+  // The url may be parsed correctly but still lack a host, i.e. "file:///1.txt"
+  match parsed.host_str() {
+    Some(host) => Ok(NetPattern::from_host_and_port(
+      host,
+      parsed.port_or_known_default(),
+    )),
+    None => Err(uri_error(
+      "invalid urlormat: <scheme>://<host>[:port][/subpath]",
+    )),
+  }
+}
+
+/// Environment variable names are case insensitive on Windows.
+fn env_key(key: &str) -> String {
+  if cfg!(windows) {
+    key.to_uppercase()
+  } else {
+    key.to_string()
+  }
+}
+
+/// Resolves a command the way the OS would when spawning it: names without a
+/// path separator are looked up in `path_env`, other paths are resolved from
+/// `cwd`, or from the CWD of this process if it isn't given.
+pub fn resolve_cmd(
+  cmd: &str,
+  path_env: Option<&OsStr>,
+  cwd: Option<&Path>,
+) -> Option<PathBuf> {
+  let path = Path::new(cmd);
+  if path.components().count() > 1 {
+    return match cwd {
+      Some(cwd) => resolve_from_cwd(&cwd.join(path)).ok(),
+      None => resolve_from_cwd(path).ok(),
+    };
+  }
+  for dir in std::env::split_paths(path_env?) {
+    let candidate = dir.join(cmd);
+    if candidate.is_file() {
+      return resolve_from_cwd(&candidate).ok();
+    }
+    if cfg!(windows) {
+      let candidate = candidate.with_extension("exe");
+      if candidate.is_file() {
+        return resolve_from_cwd(&candidate).ok();
+      }
+    }
+  }
+  None
+}
+
+/// Checks whether a command, which resolves to the binary `resolved`, is in a
+/// run list. An entry matches if it resolves to the same binary with the
+/// `PATH` of this process. A bare `git` entry therefore matches
+/// `/usr/bin/git`, but not a `git` binary elsewhere, even if the subprocess
+/// is given another `PATH`. Commands which can't be resolved are matched by
+/// name, as spawning them fails anyway.
+fn check_cmd_list(
+  cmd: &str,
+  resolved: Option<&Path>,
+  list: &HashSet<String>,
 ) -> bool {
-  allowlist.contains(host)
-    || (port.is_some()
-      && allowlist.contains(&format!("{}:{}", host, port.unwrap())))
+  let resolved = match resolved {
+    Some(resolved) => resolved,
+    None => return list.contains(cmd),
+  };
+  let path_env = std::env::var_os("PATH");
+  list.iter().any(|entry| {
+    resolve_cmd(entry, path_env.as_deref(), None).as_deref() == Some(resolved)
+  })
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
   use deno_core::serde_json;
+  use deno_core::serde_json::json;
 
   // Creates vector of strings, Vec<String>
   macro_rules! svec {
       ($($x:expr),*) => (vec![$($x.to_string()),*]);
   }
 
+  /// Checks a command the way `Deno.run()` does without a `PATH` of its own.
+  fn check_run(perms: &Permissions, cmd: &str) -> Result<(), AnyError> {
+    let path_env = std::env::var_os("PATH");
+    let resolved = resolve_cmd(cmd, path_env.as_deref(), None);
+    perms.check_run(cmd, resolved.as_deref())
+  }
+
   #[test]
   fn check_paths() {
     let allowlist = vec![
@@ -697,7 +1236,8 @@ mod tests {
       read_allowlist: allowlist.clone(),
       write_allowlist: allowlist,
       ..Default::default()
-    });
+    })
+    .unwrap();
 
     // Inside of /a/specific and /a/specific/dir/name
     assert!(perms.check_read(Path::new("/a/specific/dir/name")).is_ok());
@@ -758,7 +1298,8 @@ mod tests {
         "www.github.com:443"
       ],
       ..Default::default()
-    });
+    })
+    .unwrap();
 
     let domain_tests = vec![
       ("localhost", 1234, true),
@@ -832,6 +1373,217 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_net_patterns() {
+    let mut perms = Permissions::from_flags(&Flags {
+      net_allowlist: svec![
+        "10.0.0.0/8",
+        "*.internal.example.com",
+        "[::1]:8000-8100"
+      ],
+      net_denylist: svec!["10.0.0.1"],
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert!(perms.check_net("10.20.30.40", 443).is_ok());
+    assert!(perms.check_net("10.0.0.1", 443).is_err());
+    assert!(perms.check_net("11.0.0.1", 443).is_err());
+    assert!(perms.check_net("api.internal.example.com", 80).is_ok());
+    assert!(perms.check_net("internal.example.com", 80).is_err());
+    assert!(perms.check_net("::1", 8080).is_ok());
+    assert!(perms.check_net("::1", 8101).is_err());
+    let u = url::Url::parse("http://[::1]:8100/").unwrap();
+    assert!(perms.check_net_url(&u).is_ok());
+
+    #[rustfmt::skip]
+    {
+      assert_eq!(perms.query_net_url(&Some("10.1.0.0/16")).unwrap(), PermissionState::Granted);
+      // Part of the block is denied.
+      assert_eq!(perms.query_net_url(&Some("10.0.0.0/24")).unwrap(), PermissionState::Prompt);
+      assert_eq!(perms.query_net_url(&Some("*.example.com")).unwrap(), PermissionState::Prompt);
+      assert_eq!(perms.query_net_url(&Some("https://db.internal.example.com")).unwrap(), PermissionState::Granted);
+      assert!(perms.query_net_url(&Some("10.0.0.0/33")).is_err());
+      assert_eq!(perms.revoke_net(&Some("10.0.0.0/8")).unwrap(), PermissionState::Prompt);
+      assert!(perms.check_net("10.20.30.40", 443).is_err());
+      let _guard = PERMISSION_PROMPT_GUARD.lock().unwrap();
+      set_prompt_result(true);
+      assert_eq!(perms.request_net(&Some("192.168.0.0/16:5432")).unwrap(), PermissionState::Granted);
+      assert!(perms.check_net("192.168.1.2", 5432).is_ok());
+    };
+  }
+
+  #[test]
+  fn test_check_env_and_run() {
+    let perms = Permissions::from_flags(&Flags {
+      env_allowlist: svec!["HOME"],
+      run_allowlist: svec!["git"],
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert!(perms.check_env("HOME").is_ok());
+    assert!(perms.check_env("PATH").is_err());
+    assert!(perms.check_env_all().is_err());
+    assert!(check_run(&perms, "git").is_ok());
+    assert!(check_run(&perms, "curl").is_err());
+    assert!(check_run(&perms, "./git").is_err());
+    assert!(perms.check_run_all().is_err());
+  }
+
+  #[test]
+  fn test_check_run_with_subprocess_path() {
+    let perms = Permissions::from_flags(&Flags {
+      run_allowlist: svec!["git"],
+      ..Default::default()
+    })
+    .unwrap();
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let name = if cfg!(windows) { "git.exe" } else { "git" };
+    let fake_git = temp_dir.path().join(name);
+    std::fs::write(&fake_git, "").unwrap();
+
+    // The `PATH` of the subprocess leads to another `git`.
+    let resolved = resolve_cmd("git", Some(temp_dir.path().as_os_str()), None);
+    assert_eq!(resolved.as_deref(), Some(fake_git.as_path()));
+    assert!(perms.check_run("git", resolved.as_deref()).is_err());
+
+    // Paths are resolved from the working directory of the subprocess.
+    let cmd = format!("./{}", name);
+    let resolved = resolve_cmd(&cmd, None, Some(temp_dir.path()));
+    assert_eq!(resolved.as_deref(), Some(fake_git.as_path()));
+  }
+
+  #[test]
+  fn test_deny_flags() {
+    let mut perms = Permissions::from_flags(&Flags {
+      allow_read: true,
+      read_denylist: vec![PathBuf::from("/a/secret")],
+      allow_net: true,
+      net_denylist: svec!["evil.com", "deno.land:80"],
+      env_allowlist: svec!["HOME"],
+      deny_env: true,
+      allow_run: true,
+      run_denylist: svec!["curl"],
+      allow_hrtime: true,
+      deny_hrtime: true,
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert!(perms.check_read(Path::new("/a/public")).is_ok());
+    assert!(perms.check_read(Path::new("/a/secret")).is_err());
+    assert!(perms.check_read(Path::new("/a/secret/key")).is_err());
+    assert!(perms.check_net("deno.land", 443).is_ok());
+    assert!(perms.check_net("deno.land", 80).is_err());
+    assert!(perms.check_net("evil.com", 443).is_err());
+    assert!(perms.check_env("HOME").is_err());
+    assert!(perms.check_env_all().is_err());
+    assert!(check_run(&perms, "git").is_ok());
+    assert!(check_run(&perms, "curl").is_err());
+    assert!(perms.check_run_all().is_err());
+    assert!(perms.check_hrtime().is_err());
+    // Nothing is granted as a whole while a part of it is denied.
+    assert_eq!(perms.query_read(&None), PermissionState::Denied);
+    assert_eq!(perms.query_run(&None), PermissionState::Denied);
+    assert_eq!(perms.query_net_url(&None).unwrap(), PermissionState::Denied);
+
+    // Denials are never turned into prompts.
+    let _guard = PERMISSION_PROMPT_GUARD.lock().unwrap();
+    set_prompt_result(true);
+    assert_eq!(
+      perms.request_read(&Some(Path::new("/a/secret/key"))),
+      PermissionState::Denied
+    );
+    assert_eq!(perms.request_env(&Some("PATH")), PermissionState::Denied);
+    assert_eq!(perms.request_env(&None), PermissionState::Denied);
+    assert_eq!(perms.request_hrtime(), PermissionState::Denied);
+  }
+
+  #[test]
+  fn test_deny_list_with_allow_all() {
+    let perms = Permissions::from_flags(&Flags {
+      allow_env: true,
+      env_denylist: svec!["SECRET"],
+      ..Default::default()
+    })
+    .unwrap();
+    assert!(perms.check_env("HOME").is_ok());
+    assert!(perms.check_env("SECRET").is_err());
+    // `Deno.env.toObject()` would leak the denied variable.
+    assert!(perms.check_env_all().is_err());
+    assert_eq!(perms.query_env(&None), PermissionState::Denied);
+  }
+
+  #[test]
+  fn test_for_worker() {
+    let parent = Permissions::from_flags(&Flags {
+      allow_read: true,
+      read_denylist: vec![PathBuf::from("/a/secret")],
+      allow_write: true,
+      net_allowlist: svec!["deno.land", "localhost:4545"],
+      env_allowlist: svec!["HOME"],
+      allow_hrtime: true,
+      ..Default::default()
+    })
+    .unwrap();
+
+    let arg: WorkerPermissionsArg = serde_json::from_value(json!({
+      "read": ["/a"],
+      "write": false,
+      "net": ["localhost:4545"],
+      "hrtime": false,
+    }))
+    .unwrap();
+    let perms = parent.for_worker(&arg).unwrap();
+    assert!(perms.check_read(Path::new("/a/public")).is_ok());
+    assert!(perms.check_read(Path::new("/a/secret")).is_err());
+    assert!(perms.check_read(Path::new("/b")).is_err());
+    assert!(perms.check_write(Path::new("/a/public")).is_err());
+    assert!(perms.check_net("localhost", 4545).is_ok());
+    assert!(perms.check_net("deno.land", 443).is_err());
+    assert!(perms.check_env("HOME").is_ok());
+    assert!(perms.check_hrtime().is_err());
+    assert_eq!(perms.write.global_state, PermissionState::Denied);
+
+    let arg = WorkerPermissionsArg::default();
+    assert_eq!(parent.for_worker(&arg).unwrap(), parent);
+
+    let escalations = vec![
+      json!({ "net": true }),
+      json!({ "net": ["localhost:8000"] }),
+      json!({ "read": ["/a/secret/key"] }),
+      json!({ "env": ["PATH"] }),
+      json!({ "run": true }),
+      json!({ "plugin": true }),
+    ];
+    for arg in escalations {
+      let arg: WorkerPermissionsArg = serde_json::from_value(arg).unwrap();
+      assert!(parent.for_worker(&arg).is_err());
+    }
+
+    // A worker without read access has nothing to prompt for, even though the
+    // parent only denies a part of it.
+    let arg: WorkerPermissionsArg =
+      serde_json::from_value(json!({ "read": false })).unwrap();
+    let mut perms = parent.for_worker(&arg).unwrap();
+    assert_eq!(
+      perms.query_read(&Some(Path::new("/b"))),
+      PermissionState::Denied
+    );
+    let _guard = PERMISSION_PROMPT_GUARD.lock().unwrap();
+    set_prompt_result(true);
+    assert_eq!(
+      perms.request_read(&Some(Path::new("/b"))),
+      PermissionState::Denied
+    );
+    assert_eq!(perms.request_read(&None), PermissionState::Denied);
+
+    let unknown: Result<WorkerPermissionsArg, _> =
+      serde_json::from_value(json!({ "network": true }));
+    assert!(unknown.is_err());
+  }
+
   #[test]
   fn check_specifiers() {
     let read_allowlist = if cfg!(target_os = "windows") {
@@ -843,7 +1595,8 @@ mod tests {
       read_allowlist,
       net_allowlist: svec!["localhost"],
       ..Default::default()
-    });
+    })
+    .unwrap();
 
     let mut fixtures = vec![
       (
@@ -878,6 +1631,12 @@ mod tests {
       ));
     }
 
+    fixtures.push((
+      ModuleSpecifier::resolve_url_or_path("data:text/javascript,export {}")
+        .unwrap(),
+      true,
+    ));
+
     for (specifier, expected) in fixtures {
       assert_eq!(perms.check_specifier(&specifier).is_ok(), expected);
     }
@@ -902,8 +1661,16 @@ mod tests {
         "granted_list": [],
         "denied_list": []
       },
-      "env": "Granted",
-      "run": "Granted",
+      "env": {
+        "global_state": "Granted",
+        "granted_list": [],
+        "denied_list": []
+      },
+      "run": {
+        "global_state": "Granted",
+        "granted_list": [],
+        "denied_list": []
+      },
       "plugin": "Granted",
       "hrtime": "Granted"
     }
@@ -921,8 +1688,14 @@ mod tests {
         global_state: PermissionState::Granted,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
       hrtime: PermissionState::Granted,
       plugin: PermissionState::Granted,
     };
@@ -946,20 +1719,26 @@ mod tests {
         global_state: PermissionState::Granted,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
       plugin: PermissionState::Granted,
       hrtime: PermissionState::Granted,
     };
     let perms2 = Permissions {
       read: UnaryPermission {
         global_state: PermissionState::Prompt,
-        granted_list: resolve_fs_allowlist(&[PathBuf::from("/foo")]),
+        granted_list: resolve_fs_list(&[PathBuf::from("/foo")]),
         ..Default::default()
       },
       write: UnaryPermission {
         global_state: PermissionState::Prompt,
-        granted_list: resolve_fs_allowlist(&[PathBuf::from("/foo")]),
+        granted_list: resolve_fs_list(&[PathBuf::from("/foo")]),
         ..Default::default()
       },
       net: UnaryPermission {
@@ -967,8 +1746,16 @@ mod tests {
         granted_list: ["127.0.0.1:8000".to_string()].iter().cloned().collect(),
         ..Default::default()
       },
-      env: PermissionState::Prompt,
-      run: PermissionState::Prompt,
+      env: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        granted_list: ["HOME".to_string()].iter().cloned().collect(),
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        granted_list: ["deno".to_string()].iter().cloned().collect(),
+        ..Default::default()
+      },
       plugin: PermissionState::Prompt,
       hrtime: PermissionState::Prompt,
     };
@@ -988,10 +1775,16 @@ mod tests {
       assert_eq!(perms1.query_net_url(&Some("http://127.0.0.1:8000")).unwrap(), PermissionState::Granted);
       assert_eq!(perms2.query_net_url(&None).unwrap(), PermissionState::Prompt);
       assert_eq!(perms2.query_net_url(&Some("http://127.0.0.1:8000")).unwrap(), PermissionState::Granted);
-      assert_eq!(perms1.query_env(), PermissionState::Granted);
-      assert_eq!(perms2.query_env(), PermissionState::Prompt);
-      assert_eq!(perms1.query_run(), PermissionState::Granted);
-      assert_eq!(perms2.query_run(), PermissionState::Prompt);
+      assert_eq!(perms1.query_env(&None), PermissionState::Granted);
+      assert_eq!(perms1.query_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms2.query_env(&None), PermissionState::Prompt);
+      assert_eq!(perms2.query_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms2.query_env(&Some("PATH")), PermissionState::Prompt);
+      assert_eq!(perms1.query_run(&None), PermissionState::Granted);
+      assert_eq!(perms1.query_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms2.query_run(&None), PermissionState::Prompt);
+      assert_eq!(perms2.query_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms2.query_run(&Some("git")), PermissionState::Prompt);
       assert_eq!(perms1.query_plugin(), PermissionState::Granted);
       assert_eq!(perms2.query_plugin(), PermissionState::Prompt);
       assert_eq!(perms1.query_hrtime(), PermissionState::Granted);
@@ -1014,8 +1807,14 @@ mod tests {
         global_state: PermissionState::Prompt,
         ..Default::default()
       },
-      env: PermissionState::Prompt,
-      run: PermissionState::Prompt,
+      env: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        ..Default::default()
+      },
       plugin: PermissionState::Prompt,
       hrtime: PermissionState::Prompt,
     };
@@ -1029,7 +1828,8 @@ mod tests {
       assert_eq!(perms.request_read(&Some(&Path::new("/foo/bar"))), PermissionState::Granted);
       set_prompt_result(false);
       assert_eq!(perms.request_write(&Some(&Path::new("/foo"))), PermissionState::Denied);
-      assert_eq!(perms.query_write(&Some(&Path::new("/foo/bar"))), PermissionState::Prompt);
+      assert_eq!(perms.query_write(&Some(&Path::new("/foo/bar"))), PermissionState::Denied);
+      assert_eq!(perms.query_write(&Some(&Path::new("/baz"))), PermissionState::Prompt);
       set_prompt_result(true);
       assert_eq!(perms.request_write(&None), PermissionState::Denied);
       set_prompt_result(true);
@@ -1037,13 +1837,17 @@ mod tests {
       set_prompt_result(false);
       assert_eq!(perms.request_net(&Some("http://127.0.0.1:8000")).unwrap(), PermissionState::Granted);
       set_prompt_result(true);
-      assert_eq!(perms.request_env(), PermissionState::Granted);
+      assert_eq!(perms.request_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms.query_env(&None), PermissionState::Prompt);
       set_prompt_result(false);
-      assert_eq!(perms.request_env(), PermissionState::Granted);
+      assert_eq!(perms.request_env(&Some("HOME")), PermissionState::Granted);
+      set_prompt_result(true);
+      assert_eq!(perms.request_env(&None), PermissionState::Granted);
       set_prompt_result(false);
-      assert_eq!(perms.request_run(), PermissionState::Denied);
+      assert_eq!(perms.request_run(&Some("deno")), PermissionState::Denied);
+      assert_eq!(perms.query_run(&Some("git")), PermissionState::Prompt);
       set_prompt_result(true);
-      assert_eq!(perms.request_run(), PermissionState::Denied);
+      assert_eq!(perms.request_run(&None), PermissionState::Denied);
       set_prompt_result(true);
       assert_eq!(perms.request_plugin(), PermissionState::Granted);
       set_prompt_result(false);
@@ -1060,20 +1864,27 @@ mod tests {
     let mut perms = Permissions {
       read: UnaryPermission {
         global_state: PermissionState::Prompt,
-        granted_list: resolve_fs_allowlist(&[PathBuf::from("/foo")]),
+        granted_list: resolve_fs_list(&[PathBuf::from("/foo")]),
         ..Default::default()
       },
       write: UnaryPermission {
         global_state: PermissionState::Prompt,
-        granted_list: resolve_fs_allowlist(&[PathBuf::from("/foo")]),
+        granted_list: resolve_fs_list(&[PathBuf::from("/foo")]),
         ..Default::default()
       },
       net: UnaryPermission {
         global_state: PermissionState::Denied,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        granted_list: ["HOME".to_string()].iter().cloned().collect(),
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
       plugin: PermissionState::Prompt,
       hrtime: PermissionState::Denied,
     };
@@ -1086,8 +1897,9 @@ mod tests {
       assert_eq!(perms.revoke_write(&None), PermissionState::Prompt);
       assert_eq!(perms.query_write(&Some(&Path::new("/foo/bar"))), PermissionState::Prompt);
       assert_eq!(perms.revoke_net(&None).unwrap(), PermissionState::Denied);
-      assert_eq!(perms.revoke_env(), PermissionState::Prompt);
-      assert_eq!(perms.revoke_run(), PermissionState::Prompt);
+      assert_eq!(perms.revoke_env(&Some("HOME")), PermissionState::Prompt);
+      assert_eq!(perms.revoke_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms.revoke_run(&None), PermissionState::Prompt);
       assert_eq!(perms.revoke_plugin(), PermissionState::Prompt);
       assert_eq!(perms.revoke_hrtime(), PermissionState::Denied);
     };
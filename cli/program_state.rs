@@ -3,6 +3,7 @@
 use crate::deno_dir;
 use crate::file_fetcher::SourceFileFetcher;
 use crate::flags;
+use crate::fs;
 use crate::http_cache;
 use crate::import_map::ImportMap;
 use crate::inspector::InspectorServer;
@@ -14,14 +15,20 @@ use crate::module_graph::TranspileOptions;
 use crate::module_graph::TypeLib;
 use crate::permissions::Permissions;
 use crate::source_maps::SourceMapGetter;
+use crate::specifier_handler::BlobEmitCache;
+use crate::specifier_handler::Emit;
 use crate::specifier_handler::FetchHandler;
 
 use deno_core::error::generic_error;
 use deno_core::error::AnyError;
 use deno_core::url::Url;
 use deno_core::ModuleSpecifier;
+use deno_fetch::BlobUrlStore;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::env;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -51,6 +58,14 @@ pub struct ProgramState {
   pub lockfile: Option<Arc<Mutex<Lockfile>>>,
   pub maybe_import_map: Option<ImportMap>,
   pub maybe_inspector_server: Option<Arc<InspectorServer>>,
+  /// Blobs registered with `URL.createObjectURL()` by any worker.
+  pub blob_url_store: BlobUrlStore,
+  /// Emits of the `blob:` modules loaded by any worker.
+  pub blob_emit_cache: BlobEmitCache,
+  /// Modules which were prepared for loading or loaded by any worker,
+  /// including dynamic imports. Used by `--watch` to find out which files to
+  /// watch.
+  pub loaded_modules: Mutex<HashSet<ModuleSpecifier>>,
 }
 
 impl ProgramState {
@@ -58,8 +73,10 @@ impl ProgramState {
     let custom_root = env::var("DENO_DIR").map(String::into).ok();
     let dir = deno_dir::DenoDir::new(custom_root)?;
     let deps_cache_location = dir.root.join("deps");
-    let http_cache = http_cache::HttpCache::new(&deps_cache_location);
+    let mut http_cache = http_cache::HttpCache::new(&deps_cache_location);
+    http_cache.cache_policy = flags.cache_policy;
     let ca_file = flags.ca_file.clone().or_else(|| env::var("DENO_CERT").ok());
+    let blob_url_store = BlobUrlStore::default();
 
     let file_fetcher = SourceFileFetcher::new(
       http_cache,
@@ -68,6 +85,7 @@ impl ProgramState {
       flags.no_remote,
       flags.cached_only,
       ca_file.as_deref(),
+      blob_url_store.clone(),
     )?;
 
     let lockfile = if let Some(filename) = &flags.lock {
@@ -101,10 +119,43 @@ impl ProgramState {
       lockfile,
       maybe_import_map,
       maybe_inspector_server,
+      blob_url_store,
+      blob_emit_cache: BlobEmitCache::default(),
+      loaded_modules: Mutex::new(HashSet::new()),
     };
     Ok(Arc::new(program_state))
   }
 
+  /// Invalidates the state which depends on the given changed local files,
+  /// so that a restart in `--watch` mode picks up their new contents. The
+  /// import map is loaded once, so a change to it results in a new
+  /// `ProgramState`.
+  pub fn invalidate_files(
+    self: &Arc<Self>,
+    paths: &[PathBuf],
+  ) -> Result<Arc<Self>, AnyError> {
+    if let Some(import_map_path) = &self.flags.import_map_path {
+      let import_map_path = fs::resolve_from_cwd(Path::new(import_map_path))?;
+      if paths.contains(&import_map_path) {
+        return ProgramState::new(self.flags.clone());
+      }
+    }
+    self.file_fetcher.invalidate_local_files(paths);
+    Ok(self.clone())
+  }
+
+  /// Returns the paths of the local files of the loaded modules.
+  pub fn get_loaded_files(&self) -> Vec<PathBuf> {
+    self
+      .loaded_modules
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|specifier| specifier.as_url().scheme() == "file")
+      .filter_map(|specifier| specifier.as_url().to_file_path().ok())
+      .collect()
+  }
+
   /// This function is called when new module load is
   /// initialized by the JsRuntime. Its resposibility is to collect
   /// all dependencies and if it is required then also perform TS typecheck
@@ -130,6 +181,11 @@ impl ProgramState {
       GraphBuilder::new(handler, maybe_import_map, self.lockfile.clone());
     builder.add(&specifier, is_dynamic).await?;
     let mut graph = builder.get_graph();
+    self
+      .loaded_modules
+      .lock()
+      .unwrap()
+      .extend(graph.get_modules());
     let debug = self.flags.log_level == Some(log::Level::Debug);
     let maybe_config_path = self.flags.config_path.clone();
 
@@ -181,6 +237,11 @@ impl ProgramState {
       .expect("Cached source file doesn't exist");
 
     let url = out.url.clone();
+    self
+      .loaded_modules
+      .lock()
+      .unwrap()
+      .insert(ModuleSpecifier::from(url.clone()));
     let compiled_module = if let Some((code, _)) = self.get_emit(&url) {
       CompiledModule {
         code: String::from_utf8(code).unwrap(),
@@ -220,7 +281,16 @@ impl ProgramState {
     match url.scheme() {
       // we should only be looking for emits for schemes that denote external
       // modules, which the disk_cache supports
-      "wasm" | "file" | "http" | "https" => (),
+      "wasm" | "file" | "http" | "https" | "data" => (),
+      // `blob:` modules are only emitted into memory.
+      "blob" => {
+        let specifier = ModuleSpecifier::from(url.clone());
+        return match self.blob_emit_cache.get(&specifier)? {
+          Emit::Cli((code, maybe_map)) => {
+            Some((code.into_bytes(), maybe_map.map(String::into_bytes)))
+          }
+        };
+      }
       _ => {
         return None;
       }
@@ -3,13 +3,14 @@
 ((window) => {
   const core = window.Deno.core;
   const { Window } = window.__bootstrap.globalInterfaces;
-  const { log } = window.__bootstrap.util;
+  const { log, pathFromURL } = window.__bootstrap.util;
 
   function createWorker(
     specifier,
     hasSourceCode,
     sourceCode,
     useDenoNamespace,
+    permissions,
     name,
   ) {
     return core.jsonOpSync("op_create_worker", {
@@ -18,9 +19,33 @@
       sourceCode,
       name,
       useDenoNamespace,
+      permissions,
     });
   }
 
+  // Turns the `deno.permissions` option of `new Worker()` into the form
+  // expected by `op_create_worker`, where inherited permissions are left out.
+  function parsePermissions(permissions) {
+    if (permissions === undefined || permissions === "inherit") {
+      return undefined;
+    }
+    if (typeof permissions !== "object" || permissions === null) {
+      throw new TypeError('Worker permissions must be "inherit" or an object');
+    }
+    const parsed = {};
+    for (const [name, value] of Object.entries(permissions)) {
+      if (value === "inherit") {
+        continue;
+      }
+      if (Array.isArray(value) && (name === "read" || name === "write")) {
+        parsed[name] = value.map(pathFromURL);
+      } else {
+        parsed[name] = value;
+      }
+    }
+    return parsed;
+  }
+
   function hostTerminateWorker(id) {
     core.jsonOpSync("op_host_terminate_worker", { id });
   }
@@ -65,13 +90,21 @@
       const hasSourceCode = false;
       const sourceCode = decoder.decode(new Uint8Array());
 
-      const useDenoNamespace = options ? !!options.deno : false;
+      let useDenoNamespace = false;
+      let permissions;
+      if (typeof options?.deno === "object" && options.deno !== null) {
+        useDenoNamespace = !!options.deno.namespace;
+        permissions = parsePermissions(options.deno.permissions);
+      } else {
+        useDenoNamespace = !!options?.deno;
+      }
 
       const { id } = createWorker(
         specifier,
         hasSourceCode,
         sourceCode,
         useDenoNamespace,
+        permissions,
         options?.name,
       );
       this.#id = id;
@@ -8,6 +8,10 @@
   const CLOSING = 2;
   const CLOSED = 3;
 
+  // Used instead of the URL to construct a WebSocket from an accepted
+  // connection, see `acceptWebSocket()`.
+  const _accepted = Symbol("[[accepted]]");
+
   class WebSocket extends EventTarget {
     #readyState = CONNECTING;
 
@@ -15,6 +19,17 @@
       super();
       requiredArguments("WebSocket", arguments.length, 1);
 
+      if (url === _accepted) {
+        const accepted = protocols;
+        this.#rid = accepted.rid;
+        this.#url = accepted.url;
+        this.#protocol = accepted.protocol;
+        this.#extensions = accepted.extensions;
+        this.#readyState = OPEN;
+        this.#eventLoop();
+        return;
+      }
+
       const wsURL = new URL(url);
 
       if (wsURL.protocol !== "ws:" && wsURL.protocol !== "wss:") {
@@ -277,6 +292,11 @@
 
           this.#eventLoop();
         } else if (message.type === "close") {
+          if (this.#readyState === OPEN) {
+            // The peer started the closing handshake. Polling once more sends
+            // the reply, after which the resource is closed.
+            await core.jsonOpAsync("op_ws_next_event", { rid: this.#rid });
+          }
           this.#readyState = CLOSED;
           const event = new CloseEvent("close", {
             wasClean: true,
@@ -319,7 +339,16 @@
     },
   });
 
+  async function acceptWebSocket(conn, { protocols = [] } = {}) {
+    const accepted = await core.jsonOpAsync("op_ws_accept", {
+      rid: conn.rid,
+      protocols,
+    });
+    return new WebSocket(_accepted, accepted);
+  }
+
   window.__bootstrap.webSocket = {
     WebSocket,
+    acceptWebSocket,
   };
 })(this);
@@ -0,0 +1,164 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+((window) => {
+  const core = window.Deno.core;
+  const { Request } = window.__bootstrap.fetch;
+  const { ReadableStream } = window.__bootstrap.streams;
+  const { errors } = window.__bootstrap.errors;
+
+  const READ_CHUNK_SIZE = 64 * 1024;
+
+  function opHttpStart(rid) {
+    return core.jsonOpSync("op_http_start", { rid });
+  }
+
+  function opHttpRequestNext(rid) {
+    return core.jsonOpAsync("op_http_request_next", { rid });
+  }
+
+  function opHttpRequestRead(rid, buf) {
+    return core.jsonOpAsync("op_http_request_read", { rid }, buf);
+  }
+
+  function opHttpRespond(args, ...zeroCopy) {
+    return core.jsonOpSync("op_http_respond", args, ...zeroCopy);
+  }
+
+  function opHttpResponseWrite(rid, buf) {
+    return core.jsonOpAsync("op_http_response_write", { rid }, buf);
+  }
+
+  function createRequestBodyStream(rid) {
+    return new ReadableStream({
+      type: "bytes",
+      async pull(controller) {
+        try {
+          const buf = new Uint8Array(READ_CHUNK_SIZE);
+          const { nread } = await opHttpRequestRead(rid, buf);
+          if (nread === 0) {
+            controller.close();
+            core.close(rid);
+          } else {
+            controller.enqueue(buf.subarray(0, nread));
+          }
+        } catch (e) {
+          controller.error(e);
+          core.close(rid);
+        }
+      },
+      cancel() {
+        core.close(rid);
+      },
+    });
+  }
+
+  async function respond(responseRid, response) {
+    const headers = [...response.headers];
+    const body = response._bodySource;
+    if (body instanceof ReadableStream) {
+      const { bodyRid } = opHttpRespond({
+        rid: responseRid,
+        status: response.status,
+        headers,
+        streamBody: true,
+      });
+      try {
+        for await (const chunk of body) {
+          const buf = typeof chunk === "string"
+            ? new TextEncoder().encode(chunk)
+            : chunk;
+          await opHttpResponseWrite(bodyRid, buf);
+        }
+      } finally {
+        core.close(bodyRid);
+      }
+    } else {
+      const zeroCopy = body == null
+        ? []
+        : [new Uint8Array(await response.arrayBuffer())];
+      opHttpRespond({
+        rid: responseRid,
+        status: response.status,
+        headers,
+        streamBody: false,
+      }, ...zeroCopy);
+    }
+  }
+
+  class HttpConn {
+    #rid = 0;
+    #closed = false;
+
+    constructor(rid) {
+      this.#rid = rid;
+    }
+
+    get rid() {
+      return this.#rid;
+    }
+
+    /** Resolves to the next request event, or `null` once the connection
+     * was closed. */
+    async nextRequest() {
+      let nextRequest;
+      try {
+        nextRequest = await opHttpRequestNext(this.#rid);
+      } catch (error) {
+        if (error instanceof errors.BadResource) {
+          return null;
+        }
+        throw error;
+      }
+      if (nextRequest === null) {
+        // The connection was closed by the client.
+        this.close();
+        return null;
+      }
+
+      const { bodyRid, responseRid, method, url, headers } = nextRequest;
+      const body = bodyRid === null ? null : createRequestBodyStream(bodyRid);
+      const request = new Request(url, { method, headers, body });
+
+      let responded = false;
+      const respondWith = async (resp) => {
+        if (responded) {
+          throw new TypeError("The request has already been responded to");
+        }
+        responded = true;
+        await respond(responseRid, await resp);
+      };
+
+      return { request, respondWith };
+    }
+
+    close() {
+      if (!this.#closed) {
+        this.#closed = true;
+        core.close(this.#rid);
+      }
+    }
+
+    [Symbol.asyncIterator]() {
+      const httpConn = this;
+      return {
+        async next() {
+          const requestEvent = await httpConn.nextRequest();
+          if (requestEvent === null) {
+            return { value: undefined, done: true };
+          }
+          return { value: requestEvent, done: false };
+        },
+      };
+    }
+  }
+
+  function serveHttp(conn) {
+    const rid = opHttpStart(conn.rid);
+    return new HttpConn(rid);
+  }
+
+  window.__bootstrap.http = {
+    serveHttp,
+    HttpConn,
+  };
+})(this);
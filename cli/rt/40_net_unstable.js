@@ -1,6 +1,7 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 ((window) => {
+  const core = window.Deno.core;
   const net = window.__bootstrap.net;
 
   function listen(options) {
@@ -40,9 +41,18 @@
     }
   }
 
+  function resolveDns(query, recordType, options) {
+    return core.jsonOpAsync("op_dns_resolve", {
+      query,
+      recordType,
+      options,
+    });
+  }
+
   window.__bootstrap.netUnstable = {
     connect,
     listenDatagram,
     listen,
+    resolveDns,
   };
 })(this);
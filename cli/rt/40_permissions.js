@@ -57,9 +57,40 @@
 
   const permissions = new Permissions(illegalConstructorKey);
 
+  // Sends the JS stack of every op call to the privileged side right before
+  // the op is dispatched, so that `--trace-permissions` can attribute the
+  // permission checks made by the op to it.
+  function enableTrace() {
+    const { jsonOpSync, jsonOpAsync } = core;
+    // Formatting a stack trace dispatches ops itself.
+    let tracing = false;
+    function setTraceStack() {
+      if (tracing) {
+        return;
+      }
+      tracing = true;
+      try {
+        // Skips the "Error" line and the frames of the wrappers.
+        const stack = new Error().stack.split("\n").slice(3).join("\n");
+        jsonOpSync("op_set_permission_trace_stack", { stack });
+      } finally {
+        tracing = false;
+      }
+    }
+    core.jsonOpSync = (opName, ...args) => {
+      setTraceStack();
+      return jsonOpSync(opName, ...args);
+    };
+    core.jsonOpAsync = (opName, ...args) => {
+      setTraceStack();
+      return jsonOpAsync(opName, ...args);
+    };
+  }
+
   window.__bootstrap.permissions = {
     permissions,
     Permissions,
     PermissionStatus,
+    enableTrace,
   };
 })(this);
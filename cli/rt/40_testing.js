@@ -199,6 +199,62 @@ finishing test case.`;
 
   exposeForTest("reportToConsole", reportToConsole);
 
+  // Messages posted to the host only contain serializable data: the tests are
+  // identified by their names and errors are sent with their formatted stack.
+  function reportToHost(message) {
+    let hostMessage;
+    if (message.start != null) {
+      hostMessage = {
+        plan: {
+          tests: message.start.tests.length,
+          filtered: message.start.filtered,
+          usedOnly: message.start.usedOnly,
+        },
+      };
+    } else if (message.testStart != null) {
+      hostMessage = { testStart: { name: message.testStart.name } };
+    } else if (message.testEnd != null) {
+      const { name, status, duration, error } = message.testEnd;
+      hostMessage = {
+        testEnd: {
+          name,
+          status,
+          duration,
+          error: error != null
+            ? {
+              message: error instanceof Error ? error.message : String(error),
+              stack: inspectArgs([error]),
+            }
+            : null,
+        },
+      };
+    } else if (message.end != null) {
+      hostMessage = { end: { duration: message.end.duration } };
+    }
+    core.jsonOpSync("op_post_test_message", hostMessage);
+  }
+
+  // Mulberry32, a small pseudo random number generator. Its sequence only
+  // depends on the seed, so that a shuffled order can be reproduced.
+  function createRandom(seed) {
+    let state = seed >>> 0;
+    return () => {
+      state = (state + 0x6d2b79f5) >>> 0;
+      let t = state;
+      t = Math.imul(t ^ (t >>> 15), t | 1);
+      t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+      return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+    };
+  }
+
+  function shuffle(array, seed) {
+    const random = createRandom(seed);
+    for (let i = array.length - 1; i > 0; i--) {
+      const j = Math.floor(random() * (i + 1));
+      [array[i], array[j]] = [array[j], array[i]];
+    }
+  }
+
   // TODO: already implements AsyncGenerator<RunTestsMessage>, but add as "implements to class"
   // TODO: implements PromiseLike<RunTestsEndResult>
   class TestRunner {
@@ -208,6 +264,7 @@ finishing test case.`;
       tests,
       filterFn,
       failFast,
+      shuffleSeed,
     ) {
       this.stats = {
         filtered: 0,
@@ -222,11 +279,20 @@ finishing test case.`;
       this.#usedOnly = onlyTests.length > 0;
       const unfilteredTests = this.#usedOnly ? onlyTests : tests;
       this.testsToRun = unfilteredTests.filter(filterFn);
+      if (shuffleSeed != null) {
+        shuffle(this.testsToRun, shuffleSeed);
+      }
       this.stats.filtered = unfilteredTests.length - this.testsToRun.length;
     }
 
     async *[Symbol.asyncIterator]() {
-      yield { start: { tests: this.testsToRun } };
+      yield {
+        start: {
+          tests: this.testsToRun,
+          filtered: this.stats.filtered,
+          usedOnly: this.#usedOnly,
+        },
+      };
 
       const results = [];
       const suiteStart = +new Date();
@@ -306,10 +372,17 @@ finishing test case.`;
     skip = undefined,
     disableLog = false,
     reportToConsole: reportToConsole_ = true,
+    reportToHost: reportToHost_ = false,
+    shuffle: shuffleSeed = undefined,
     onMessage = undefined,
   } = {}) {
     const filterFn = createFilterFn(filter, skip);
-    const testRunner = new TestRunner(TEST_REGISTRY, filterFn, failFast);
+    const testRunner = new TestRunner(
+      TEST_REGISTRY,
+      filterFn,
+      failFast,
+      shuffleSeed,
+    );
 
     const originalConsole = globalThis.console;
 
@@ -326,6 +399,9 @@ finishing test case.`;
       if (reportToConsole_) {
         reportToConsole(message);
       }
+      if (reportToHost_) {
+        reportToHost(message);
+      }
       if (message.end != null) {
         endMsg = message.end;
       }
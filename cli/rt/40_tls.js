@@ -27,20 +27,50 @@
     hostname = "127.0.0.1",
     transport = "tcp",
     certFile = undefined,
+    caCerts = [],
+    certChain = undefined,
+    certChainFile = undefined,
+    privateKey = undefined,
+    privateKeyFile = undefined,
+    alpnProtocols = [],
   }) {
     const res = await opConnectTls({
       port,
       hostname,
       transport,
       certFile,
+      caCerts,
+      certChain,
+      certChainFile,
+      privateKey,
+      privateKeyFile,
+      alpnProtocols,
     });
     return new Conn(res.rid, res.remoteAddr, res.localAddr);
   }
 
+  class TLSConn extends Conn {
+    #alpnProtocol = null;
+
+    constructor(rid, remoteAddr, localAddr, alpnProtocol) {
+      super(rid, remoteAddr, localAddr);
+      this.#alpnProtocol = alpnProtocol ?? null;
+    }
+
+    get alpnProtocol() {
+      return this.#alpnProtocol;
+    }
+  }
+
   class TLSListener extends Listener {
     async accept() {
       const res = await opAcceptTLS(this.rid);
-      return new Conn(res.rid, res.remoteAddr, res.localAddr);
+      return new TLSConn(
+        res.rid,
+        res.remoteAddr,
+        res.localAddr,
+        res.alpnProtocol,
+      );
     }
   }
 
@@ -48,6 +78,12 @@
     port,
     certFile,
     keyFile,
+    certChain = undefined,
+    privateKey = undefined,
+    clientCaFile = undefined,
+    clientCaCerts = [],
+    sniCerts = {},
+    alpnProtocols = [],
     hostname = "0.0.0.0",
     transport = "tcp",
   }) {
@@ -55,6 +91,12 @@
       port,
       certFile,
       keyFile,
+      certChain,
+      privateKey,
+      clientCaFile,
+      clientCaCerts,
+      sniCerts,
+      alpnProtocols,
       hostname,
       transport,
     });
@@ -63,12 +105,27 @@
 
   async function startTls(
     conn,
-    { hostname = "127.0.0.1", certFile } = {},
+    {
+      hostname = "127.0.0.1",
+      certFile,
+      caCerts = [],
+      certChain,
+      certChainFile,
+      privateKey,
+      privateKeyFile,
+      alpnProtocols = [],
+    } = {},
   ) {
     const res = await opStartTls({
       rid: conn.rid,
       hostname,
       certFile,
+      caCerts,
+      certChain,
+      certChainFile,
+      privateKey,
+      privateKeyFile,
+      alpnProtocols,
     });
     return new Conn(res.rid, res.remoteAddr, res.localAddr);
   }
@@ -77,6 +134,7 @@
     startTls,
     listenTls,
     connectTls,
+    TLSConn,
     TLSListener,
   };
 })(this);
@@ -117,7 +117,10 @@
     listen: __bootstrap.netUnstable.listen,
     connect: __bootstrap.netUnstable.connect,
     listenDatagram: __bootstrap.netUnstable.listenDatagram,
+    resolveDns: __bootstrap.netUnstable.resolveDns,
     startTls: __bootstrap.tls.startTls,
+    serveHttp: __bootstrap.http.serveHttp,
+    acceptWebSocket: __bootstrap.webSocket.acceptWebSocket,
     fstatSync: __bootstrap.fs.fstatSync,
     fstat: __bootstrap.fs.fstat,
     ftruncateSync: __bootstrap.fs.ftruncateSync,
@@ -28,6 +28,7 @@ delete Object.prototype.__proto__;
   const webSocket = window.__bootstrap.webSocket;
   const fetch = window.__bootstrap.fetch;
   const prompt = window.__bootstrap.prompt;
+  const permissions = window.__bootstrap.permissions;
   const denoNs = window.__bootstrap.denoNs;
   const denoNsUnstable = window.__bootstrap.denoNsUnstable;
   const errors = window.__bootstrap.errors.errors;
@@ -161,6 +162,9 @@ delete Object.prototype.__proto__;
     build.setBuildInfo(s.target);
     util.setLogDebug(s.debugFlag, source);
     errorStack.setPrepareStackTrace(Error);
+    if (s.tracePermissions && "op_set_permission_trace_stack" in core.ops()) {
+      permissions.enableTrace();
+    }
     return s;
   }
 
@@ -23,6 +23,7 @@ use std::fmt;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 pub type DependencyMap = HashMap<String, Dependency>;
 pub type FetchFuture =
@@ -94,6 +95,22 @@ pub enum Emit {
   Cli((String, Option<String>)),
 }
 
+/// Emits of `blob:` modules. Their URLs are random and only valid within the
+/// process which created them, so their emits are kept in memory instead of
+/// being written to the gen cache, where they would never be used again.
+#[derive(Debug, Clone, Default)]
+pub struct BlobEmitCache(Arc<Mutex<HashMap<ModuleSpecifier, Emit>>>);
+
+impl BlobEmitCache {
+  pub fn get(&self, specifier: &ModuleSpecifier) -> Option<Emit> {
+    self.0.lock().unwrap().get(specifier).cloned()
+  }
+
+  pub fn set(&self, specifier: &ModuleSpecifier, emit: Emit) {
+    self.0.lock().unwrap().insert(specifier.clone(), emit);
+  }
+}
+
 impl Default for Emit {
   fn default() -> Self {
     Emit::Cli(("".to_string(), None))
@@ -221,6 +238,8 @@ pub struct FetchHandler {
   runtime_permissions: Permissions,
   /// A clone of the `program_state` file fetcher.
   file_fetcher: SourceFileFetcher,
+  /// A clone of the `program_state` emits of `blob:` modules.
+  blob_emit_cache: BlobEmitCache,
 }
 
 impl FetchHandler {
@@ -232,11 +251,13 @@ impl FetchHandler {
     let deno_dir = DenoDir::new(custom_root)?;
     let disk_cache = deno_dir.gen_cache;
     let file_fetcher = program_state.file_fetcher.clone();
+    let blob_emit_cache = program_state.blob_emit_cache.clone();
 
     Ok(FetchHandler {
       disk_cache,
       runtime_permissions,
       file_fetcher,
+      blob_emit_cache,
     })
   }
 }
@@ -351,6 +372,9 @@ impl SpecifierHandler for FetchHandler {
     specifier: &ModuleSpecifier,
     tsbuildinfo: String,
   ) -> Result<(), AnyError> {
+    if specifier.as_url().scheme() == "blob" {
+      return Ok(());
+    }
     let filename = self
       .disk_cache
       .get_cache_filename_with_extension(specifier.as_url(), "buildinfo");
@@ -366,6 +390,10 @@ impl SpecifierHandler for FetchHandler {
     specifier: &ModuleSpecifier,
     emit: &Emit,
   ) -> Result<(), AnyError> {
+    if specifier.as_url().scheme() == "blob" {
+      self.blob_emit_cache.set(specifier, emit.clone());
+      return Ok(());
+    }
     match emit {
       Emit::Cli((code, maybe_map)) => {
         let url = specifier.as_url();
@@ -408,6 +436,10 @@ impl SpecifierHandler for FetchHandler {
     specifier: &ModuleSpecifier,
     version_hash: String,
   ) -> Result<(), AnyError> {
+    // Without a version, `blob:` modules are emitted again by every graph.
+    if specifier.as_url().scheme() == "blob" {
+      return Ok(());
+    }
     let compiled_file_metadata = CompiledFileMetadata { version_hash };
     let filename = self
       .disk_cache
@@ -522,6 +554,7 @@ impl SpecifierHandler for MemoryHandler {
 pub mod tests {
   use super::*;
   use crate::http_cache::HttpCache;
+  use deno_fetch::BlobUrlStore;
   use tempfile::TempDir;
 
   macro_rules! map (
@@ -548,6 +581,7 @@ pub mod tests {
       false,
       false,
       None,
+      BlobUrlStore::default(),
     )
     .expect("could not setup");
     let disk_cache = deno_dir.gen_cache;
@@ -556,6 +590,7 @@ pub mod tests {
       disk_cache,
       runtime_permissions: Permissions::default(),
       file_fetcher,
+      blob_emit_cache: BlobEmitCache::default(),
     };
 
     (temp_dir, fetch_handler)
@@ -610,6 +645,31 @@ pub mod tests {
     );
   }
 
+  #[test]
+  fn test_fetch_handler_set_cache_blob() {
+    let (_, mut file_fetcher) = setup();
+    let specifier = ModuleSpecifier::resolve_url(
+      "blob:null/6ae8a6b5-5b5b-4a47-9c1b-95a3c1c1b2f6",
+    )
+    .unwrap();
+    let emit = Emit::Cli(("some code".to_string(), None));
+    file_fetcher
+      .set_cache(&specifier, &emit)
+      .expect("could not set cache");
+    file_fetcher
+      .set_version(&specifier, "1".to_string())
+      .expect("could not set version");
+
+    // The emit is kept in memory instead of the gen cache.
+    assert_eq!(file_fetcher.blob_emit_cache.get(&specifier), Some(emit));
+    for extension in &["js", "meta"] {
+      let filename = file_fetcher
+        .disk_cache
+        .get_cache_filename_with_extension(specifier.as_url(), extension);
+      assert!(file_fetcher.disk_cache.get(&filename).is_err());
+    }
+  }
+
   #[tokio::test]
   async fn test_fetch_handler_is_remote() {
     let _http_server_guard = test_util::http_server();
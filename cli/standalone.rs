@@ -0,0 +1,264 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use crate::flags;
+use crate::flags::Flags;
+use crate::fs as deno_fs;
+use crate::permission_policy::PermissionPolicy;
+use crate::permissions::Permissions;
+use crate::program_state::ProgramState;
+use crate::worker::MainWorker;
+use deno_core::error::generic_error;
+use deno_core::error::type_error;
+use deno_core::error::AnyError;
+use deno_core::futures::FutureExt;
+use deno_core::serde_json;
+use deno_core::ModuleLoader;
+use deno_core::ModuleSpecifier;
+use deno_core::OpState;
+use serde::Deserialize;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::rc::Rc;
+
+const MAGIC_TRAILER: &[u8; 8] = b"d3n0l4nd";
+/// Magic trailer followed by the positions of the bundle and the metadata,
+/// each encoded as a big endian `u64`.
+const TRAILER_LEN: usize = 24;
+const SPECIFIER: &str = "file://$deno$/bundle.js";
+
+/// Runtime configuration embedded into a standalone binary next to the
+/// bundled source code.
+#[derive(Deserialize, Serialize)]
+pub struct Metadata {
+  /// Permission flags as returned by `Flags::to_permission_args`, without
+  /// `--permissions`.
+  pub permissions: Vec<String>,
+  /// Absolute path and contents of the policy file given by `--permissions`.
+  /// The contents are embedded, as the file is usually not around where the
+  /// binary runs.
+  pub permissions_policy: Option<(PathBuf, String)>,
+  pub unstable: bool,
+}
+
+impl Metadata {
+  /// Reconstruct the `Flags` of a standalone binary. `args` are the
+  /// arguments the binary was invoked with, including the binary name.
+  fn into_flags(self, args: Vec<String>) -> Result<Flags, AnyError> {
+    let mut run_args = vec!["deno".to_string(), "run".to_string()];
+    run_args.extend(self.permissions);
+    run_args.push(SPECIFIER.to_string());
+    let mut flags = flags::flags_from_vec_safe(run_args)
+      .map_err(|e| generic_error(e.to_string()))?;
+    flags.argv = args.into_iter().skip(1).collect();
+    flags.unstable = self.unstable;
+    Ok(flags)
+  }
+}
+
+/// Reads the trailer of a binary, returning the positions of the bundle and
+/// the metadata, or `None` if the file has no standalone payload.
+fn read_trailer(file: &mut File) -> Result<Option<(u64, u64, u64)>, AnyError> {
+  let trailer_pos = match file.seek(SeekFrom::End(-(TRAILER_LEN as i64))) {
+    Ok(pos) => pos,
+    // The file is shorter than the trailer.
+    Err(_) => return Ok(None),
+  };
+  let mut trailer = [0; TRAILER_LEN];
+  file.read_exact(&mut trailer)?;
+  let (magic_trailer, rest) = trailer.split_at(8);
+  if magic_trailer != MAGIC_TRAILER {
+    return Ok(None);
+  }
+  let (bundle_pos, metadata_pos) = rest.split_at(8);
+  let bundle_pos = u64::from_be_bytes(bundle_pos.try_into()?);
+  let metadata_pos = u64::from_be_bytes(metadata_pos.try_into()?);
+  Ok(Some((bundle_pos, metadata_pos, trailer_pos)))
+}
+
+fn read_string_slice(
+  file: &mut File,
+  pos: u64,
+  len: u64,
+) -> Result<String, AnyError> {
+  let mut string = String::new();
+  file.seek(SeekFrom::Start(pos))?;
+  file.take(len).read_to_string(&mut string)?;
+  Ok(string)
+}
+
+/// This function will try to extract the bundle and the metadata appended to
+/// the currently running executable by `deno compile`. Returns `None` when
+/// the executable is a regular `deno` binary.
+pub fn extract_standalone() -> Result<Option<(String, Metadata)>, AnyError> {
+  let current_exe_path = std::env::current_exe()?;
+  let mut current_exe = File::open(current_exe_path)?;
+  let (bundle_pos, metadata_pos, trailer_pos) =
+    match read_trailer(&mut current_exe)? {
+      Some(positions) => positions,
+      None => return Ok(None),
+    };
+
+  let bundle =
+    read_string_slice(&mut current_exe, bundle_pos, metadata_pos - bundle_pos)?;
+  let metadata = read_string_slice(
+    &mut current_exe,
+    metadata_pos,
+    trailer_pos - metadata_pos,
+  )?;
+  let metadata: Metadata = serde_json::from_str(&metadata)?;
+  Ok(Some((bundle, metadata)))
+}
+
+/// Module loader of standalone binaries. It serves the embedded bundle and
+/// refuses to load anything else.
+struct EmbeddedModuleLoader(String);
+
+impl ModuleLoader for EmbeddedModuleLoader {
+  fn resolve(
+    &self,
+    _op_state: Rc<RefCell<OpState>>,
+    specifier: &str,
+    _referrer: &str,
+    _is_main: bool,
+  ) -> Result<ModuleSpecifier, AnyError> {
+    if specifier != SPECIFIER {
+      return Err(type_error(
+        "Self-contained binaries don't support module loading",
+      ));
+    }
+    Ok(ModuleSpecifier::resolve_url(specifier)?)
+  }
+
+  fn load(
+    &self,
+    _op_state: Rc<RefCell<OpState>>,
+    module_specifier: &ModuleSpecifier,
+    _maybe_referrer: Option<ModuleSpecifier>,
+    _is_dynamic: bool,
+  ) -> Pin<Box<deno_core::ModuleSourceFuture>> {
+    let module_specifier = module_specifier.clone();
+    let code = self.0.to_string();
+    async move {
+      if module_specifier.to_string() != SPECIFIER {
+        return Err(type_error(
+          "Self-contained binaries don't support module loading",
+        ));
+      }
+      Ok(deno_core::ModuleSource {
+        code,
+        module_url_specified: module_specifier.to_string(),
+        module_url_found: module_specifier.to_string(),
+      })
+    }
+    .boxed_local()
+  }
+}
+
+/// Runs the bundle embedded in a standalone binary. `args` are the arguments
+/// the binary was invoked with.
+pub async fn run(
+  source_code: String,
+  metadata: Metadata,
+  args: Vec<String>,
+) -> Result<(), AnyError> {
+  let maybe_policy = match &metadata.permissions_policy {
+    Some((path, source)) => Some(PermissionPolicy::parse(path, source)?),
+    None => None,
+  };
+  let flags = metadata.into_flags(args)?;
+  let main_module = ModuleSpecifier::resolve_url(SPECIFIER)?;
+  let permissions =
+    Permissions::from_flags_and_policy(&flags, maybe_policy.as_ref())?;
+  let program_state = ProgramState::new(flags)?;
+  let module_loader = Rc::new(EmbeddedModuleLoader(source_code));
+  let mut worker = MainWorker::from_module_loader(
+    &program_state,
+    main_module.clone(),
+    permissions,
+    module_loader,
+  );
+  worker.execute_module(&main_module).await?;
+  worker.execute("window.dispatchEvent(new Event('load'))")?;
+  worker.run_event_loop().await?;
+  worker.execute("window.dispatchEvent(new Event('unload'))")?;
+  Ok(())
+}
+
+/// This functions creates a standalone deno binary by appending a bundle
+/// and the runtime configuration derived from `flags` to the currently
+/// running executable.
+pub fn create_standalone_binary(
+  source_code: String,
+  flags: &Flags,
+  output: PathBuf,
+) -> Result<PathBuf, AnyError> {
+  let permissions_policy = match &flags.permissions_policy {
+    Some(path) => {
+      let (path, source) = PermissionPolicy::read(path)?;
+      // Fail now rather than when the binary runs.
+      PermissionPolicy::parse(&path, &source)?;
+      Some((path, source))
+    }
+    None => None,
+  };
+  let mut permission_flags = flags.clone();
+  permission_flags.permissions_policy = None;
+  let metadata = Metadata {
+    permissions: permission_flags.to_permission_args(),
+    permissions_policy,
+    unstable: flags.unstable,
+  };
+  let mut source_code = source_code.into_bytes();
+  let mut metadata = serde_json::to_string(&metadata)?.into_bytes();
+  let original_binary_path = std::env::current_exe()?;
+  let mut original_bin = std::fs::read(original_binary_path)?;
+
+  let bundle_pos = original_bin.len() as u64;
+  let metadata_pos = bundle_pos + source_code.len() as u64;
+  let mut trailer = MAGIC_TRAILER.to_vec();
+  trailer.extend_from_slice(&bundle_pos.to_be_bytes());
+  trailer.extend_from_slice(&metadata_pos.to_be_bytes());
+
+  let mut final_bin = Vec::with_capacity(
+    original_bin.len() + source_code.len() + metadata.len() + trailer.len(),
+  );
+  final_bin.append(&mut original_bin);
+  final_bin.append(&mut source_code);
+  final_bin.append(&mut metadata);
+  final_bin.append(&mut trailer);
+
+  let output =
+    if cfg!(windows) && output.extension().unwrap_or_default() != "exe" {
+      PathBuf::from(output.display().to_string() + ".exe")
+    } else {
+      output
+    };
+
+  if output.exists() {
+    if output.is_dir() {
+      return Err(generic_error(format!(
+        "Could not compile: {:?} is a directory.",
+        &output
+      )));
+    }
+    // Make sure we don't overwrite any file that was not created by
+    // `deno compile`.
+    let mut output_file = File::open(&output)?;
+    if read_trailer(&mut output_file)?.is_none() {
+      return Err(generic_error(format!(
+        "Could not compile: cannot overwrite {:?}.",
+        &output
+      )));
+    }
+  }
+  deno_fs::write_file(&output, final_bin, 0o777)?;
+
+  Ok(output)
+}
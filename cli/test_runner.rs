@@ -1,12 +1,43 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::colors;
+use crate::coverage;
+use crate::file_fetcher::SourceFile;
 use crate::fs as deno_fs;
 use crate::installer::is_remote_url;
+use crate::media_type::MediaType;
+use crate::permissions::Permissions;
+use crate::program_state::ProgramState;
+use crate::tokio_util::create_basic_runtime;
+use crate::worker::MainWorker;
+use deno_core::error::generic_error;
 use deno_core::error::AnyError;
+use deno_core::futures::channel::mpsc;
+use deno_core::futures::stream::StreamExt;
+use deno_core::serde_json;
 use deno_core::serde_json::json;
 use deno_core::url::Url;
+use deno_core::ModuleSpecifier;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
 
 fn is_supported(p: &Path) -> bool {
   use std::path::Component;
@@ -64,34 +95,811 @@ pub fn prepare_test_modules_urls(
   Ok(prepared)
 }
 
-pub fn render_test_file(
-  modules: Vec<Url>,
-  fail_fast: bool,
-  quiet: bool,
-  filter: Option<String>,
-) -> String {
+/// Options of a test run, as given to `deno test`.
+#[derive(Clone)]
+pub struct TestOptions {
+  pub fail_fast: bool,
+  pub quiet: bool,
+  pub filter: Option<String>,
+  /// Run every test module in its own isolate, with up to this many modules
+  /// running in parallel.
+  pub jobs: Option<usize>,
+  pub reporter: String,
+  pub reporter_output: Option<PathBuf>,
+  /// Shuffle the order of tests with this seed.
+  pub shuffle: Option<u64>,
+}
+
+/// Splits the test modules into `count` shards of about the same size and
+/// returns the one at `index`, starting at 1. The modules are sorted first, so
+/// that all machines which run shards of the same test suite agree on them.
+pub fn shard_test_modules(
+  mut modules: Vec<Url>,
+  index: usize,
+  count: usize,
+) -> Vec<Url> {
+  modules.sort();
+  modules
+    .into_iter()
+    .enumerate()
+    .filter(|(i, _)| i % count == index - 1)
+    .map(|(_, module)| module)
+    .collect()
+}
+
+/// Shuffles the test modules into an order that only depends on `seed` and
+/// not on the order in which they were discovered.
+pub fn shuffle_test_modules(modules: &mut [Url], seed: u64) {
+  modules.sort();
+  let mut rng = StdRng::seed_from_u64(seed);
+  modules.shuffle(&mut rng);
+}
+
+/// Renders the entry point which imports the test modules and runs their
+/// tests. The messages of the test runner are posted to the host, which
+/// reports on them.
+pub fn render_test_file(modules: Vec<Url>, options: &TestOptions) -> String {
   let mut test_file = "".to_string();
 
   for module in modules {
     test_file.push_str(&format!("import \"{}\";\n", module.to_string()));
   }
 
-  let options = if let Some(filter) = filter {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet, "filter": filter })
-  } else {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet })
-  };
+  let mut js_options = json!({
+    "failFast": options.fail_fast,
+    "exitOnFail": false,
+    "reportToConsole": false,
+    "reportToHost": true,
+    "disableLog": options.quiet,
+  });
+  if let Some(filter) = &options.filter {
+    js_options["filter"] = json!(filter);
+  }
+  if let Some(seed) = options.shuffle {
+    js_options["shuffle"] = json!(seed);
+  }
 
   test_file.push_str("// @ts-ignore\n");
 
   test_file.push_str(&format!(
     "await Deno[Deno.internal].runTests({});\n",
-    options
+    js_options
   ));
 
   test_file
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+  Passed,
+  Failed,
+  Ignored,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TestError {
+  pub message: String,
+  /// The error as formatted by the console, including its stack.
+  pub stack: String,
+}
+
+/// A message posted by the test runner of `40_testing.js` with the
+/// `reportToHost` option. Durations are in milliseconds.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TestMessage {
+  #[serde(rename_all = "camelCase")]
+  Plan {
+    tests: usize,
+    filtered: usize,
+    used_only: bool,
+  },
+  TestStart {
+    name: String,
+  },
+  TestEnd {
+    name: String,
+    status: TestStatus,
+    duration: u64,
+    error: Option<TestError>,
+  },
+  End {
+    duration: u64,
+  },
+}
+
+pub enum TestEvent {
+  /// A message of the test runner of a test module, or of all test modules
+  /// when they run in the same isolate.
+  Message(Option<Url>, TestMessage),
+  /// The test module failed to load or its isolate threw an uncaught error.
+  Error(Url, AnyError),
+}
+
+/// Handles the messages of the test runner of an isolate. It is put into the
+/// `OpState` of the isolate running the tests.
+pub struct TestEventSender {
+  module: Option<Url>,
+  handler: Box<dyn Fn(TestEvent) -> Result<(), AnyError>>,
+}
+
+impl TestEventSender {
+  pub fn send(&self, message: TestMessage) -> Result<(), AnyError> {
+    (self.handler)(TestEvent::Message(self.module.clone(), message))
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct TestSummary {
+  pub passed: usize,
+  pub failed: usize,
+  pub ignored: usize,
+  pub filtered: usize,
+  pub used_only: bool,
+  /// Duration of the whole test run in milliseconds.
+  pub duration: u64,
+}
+
+impl TestSummary {
+  fn visit_event(&mut self, event: &TestEvent) {
+    match event {
+      TestEvent::Message(
+        _,
+        TestMessage::Plan {
+          filtered,
+          used_only,
+          ..
+        },
+      ) => {
+        self.filtered += filtered;
+        self.used_only |= used_only;
+      }
+      TestEvent::Message(_, TestMessage::TestEnd { status, .. }) => {
+        match status {
+          TestStatus::Passed => self.passed += 1,
+          TestStatus::Failed => self.failed += 1,
+          TestStatus::Ignored => self.ignored += 1,
+        }
+      }
+      TestEvent::Message(_, _) => {}
+      TestEvent::Error(_, _) => self.failed += 1,
+    }
+  }
+
+  /// A test run fails if any test failed or if the `only` option was used.
+  pub fn has_failed(&self) -> bool {
+    self.failed > 0 || self.used_only
+  }
+}
+
+pub trait TestReporter {
+  fn visit_event(&mut self, event: &TestEvent) -> Result<(), AnyError>;
+  fn done(&mut self, summary: &TestSummary) -> Result<(), AnyError>;
+}
+
+/// Create the reporter for the value of `--reporter`. Reports are written to
+/// `maybe_output` if given, or to stdout. With `concurrent`, the events of
+/// several test modules are interleaved.
+pub fn create_reporter(
+  kind: &str,
+  quiet: bool,
+  concurrent: bool,
+  maybe_output: Option<PathBuf>,
+) -> Result<Box<dyn TestReporter>, AnyError> {
+  let writer: Box<dyn Write> = match maybe_output {
+    Some(output) => {
+      if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      Box::new(BufWriter::new(File::create(output)?))
+    }
+    None => Box::new(io::stdout()),
+  };
+  let reporter: Box<dyn TestReporter> = match kind {
+    "junit" => Box::new(JUnitTestReporter::new(writer)),
+    "tap" => Box::new(TapTestReporter::new(writer)),
+    _ => Box::new(PrettyTestReporter::new(writer, quiet, concurrent)),
+  };
+  Ok(reporter)
+}
+
+fn format_duration(duration: u64) -> String {
+  colors::gray(&format!("({}ms)", duration)).to_string()
+}
+
+fn format_status(status: TestStatus) -> String {
+  match status {
+    TestStatus::Passed => colors::green("ok").to_string(),
+    TestStatus::Failed => colors::red("FAILED").to_string(),
+    TestStatus::Ignored => colors::yellow("ignored").to_string(),
+  }
+}
+
+/// The human readable report. When test modules run concurrently, results
+/// are printed once a test is complete, as the output of several modules is
+/// interleaved.
+pub struct PrettyTestReporter {
+  writer: Box<dyn Write>,
+  quiet: bool,
+  concurrent: bool,
+  failures: Vec<(String, String)>,
+}
+
+impl PrettyTestReporter {
+  pub fn new(writer: Box<dyn Write>, quiet: bool, concurrent: bool) -> Self {
+    Self {
+      writer,
+      quiet,
+      concurrent,
+      failures: Vec::new(),
+    }
+  }
+}
+
+impl TestReporter for PrettyTestReporter {
+  fn visit_event(&mut self, event: &TestEvent) -> Result<(), AnyError> {
+    if self.quiet {
+      return Ok(());
+    }
+
+    match event {
+      TestEvent::Message(Some(module), TestMessage::Plan { tests, .. }) => {
+        writeln!(self.writer, "running {} tests from {}", tests, module)?;
+      }
+      TestEvent::Message(None, TestMessage::Plan { tests, .. }) => {
+        writeln!(self.writer, "running {} tests", tests)?;
+      }
+      TestEvent::Message(_, TestMessage::TestStart { name }) => {
+        if !self.concurrent {
+          // The output of the test follows on the same line.
+          write!(self.writer, "test {} ... ", name)?;
+          self.writer.flush()?;
+        }
+      }
+      TestEvent::Message(
+        _,
+        TestMessage::TestEnd {
+          name,
+          status,
+          duration,
+          error,
+        },
+      ) => {
+        if self.concurrent {
+          write!(self.writer, "test {} ... ", name)?;
+        }
+        writeln!(
+          self.writer,
+          "{} {}",
+          format_status(*status),
+          format_duration(*duration)
+        )?;
+        if let Some(error) = error {
+          self.failures.push((name.clone(), error.stack.clone()));
+        }
+      }
+      TestEvent::Message(_, TestMessage::End { .. }) => {}
+      TestEvent::Error(module, err) => {
+        writeln!(
+          self.writer,
+          "test module {} ... {}",
+          module,
+          format_status(TestStatus::Failed)
+        )?;
+        self.failures.push((module.to_string(), err.to_string()));
+      }
+    }
+    Ok(())
+  }
+
+  fn done(&mut self, summary: &TestSummary) -> Result<(), AnyError> {
+    if self.quiet {
+      return Ok(());
+    }
+
+    if !self.failures.is_empty() {
+      writeln!(self.writer, "\nfailures:\n")?;
+      for (name, error) in &self.failures {
+        writeln!(self.writer, "{}", name)?;
+        writeln!(self.writer, "{}", error)?;
+        writeln!(self.writer)?;
+      }
+      writeln!(self.writer, "failures:\n")?;
+      for (name, _) in &self.failures {
+        writeln!(self.writer, "\t{}", name)?;
+      }
+    }
+
+    let status = if summary.failed > 0 {
+      TestStatus::Failed
+    } else {
+      TestStatus::Passed
+    };
+    writeln!(
+      self.writer,
+      "\ntest result: {}. {} passed; {} failed; {} ignored; 0 measured; {} filtered out {}\n",
+      format_status(status),
+      summary.passed,
+      summary.failed,
+      summary.ignored,
+      summary.filtered,
+      format_duration(summary.duration),
+    )?;
+    if summary.used_only && summary.failed == 0 {
+      writeln!(
+        self.writer,
+        "{} because the \"only\" option was used\n",
+        format_status(TestStatus::Failed)
+      )?;
+    }
+    self.writer.flush()?;
+    Ok(())
+  }
+}
+
+/// Escapes text for XML attributes and content. Colors of formatted errors
+/// are stripped.
+fn escape_xml(text: &str) -> String {
+  colors::strip_ansi_codes(text)
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&apos;")
+}
+
+fn format_seconds(duration: u64) -> String {
+  format!("{:.3}", duration as f64 / 1000.0)
+}
+
+struct JUnitTestCase {
+  name: String,
+  status: TestStatus,
+  duration: u64,
+  error: Option<TestError>,
+}
+
+#[derive(Default)]
+struct JUnitTestSuite {
+  test_cases: Vec<JUnitTestCase>,
+  duration: u64,
+  /// The error which aborted the test module.
+  error: Option<String>,
+}
+
+/// Writes a JUnit XML report with a test suite per test module, once the test
+/// run is done.
+pub struct JUnitTestReporter {
+  writer: Box<dyn Write>,
+  suites: BTreeMap<String, JUnitTestSuite>,
+}
+
+impl JUnitTestReporter {
+  pub fn new(writer: Box<dyn Write>) -> Self {
+    Self {
+      writer,
+      suites: BTreeMap::new(),
+    }
+  }
+
+  fn suite(&mut self, module: &Option<Url>) -> &mut JUnitTestSuite {
+    let name = match module {
+      Some(module) => module.to_string(),
+      None => "deno test".to_string(),
+    };
+    self.suites.entry(name).or_default()
+  }
+}
+
+impl TestReporter for JUnitTestReporter {
+  fn visit_event(&mut self, event: &TestEvent) -> Result<(), AnyError> {
+    match event {
+      TestEvent::Message(
+        module,
+        TestMessage::TestEnd {
+          name,
+          status,
+          duration,
+          error,
+        },
+      ) => {
+        let test_case = JUnitTestCase {
+          name: name.clone(),
+          status: *status,
+          duration: *duration,
+          error: error.clone(),
+        };
+        self.suite(module).test_cases.push(test_case);
+      }
+      TestEvent::Message(module, TestMessage::End { duration }) => {
+        self.suite(module).duration = *duration;
+      }
+      TestEvent::Message(_, _) => {}
+      TestEvent::Error(module, err) => {
+        self.suite(&Some(module.clone())).error = Some(err.to_string());
+      }
+    }
+    Ok(())
+  }
+
+  fn done(&mut self, summary: &TestSummary) -> Result<(), AnyError> {
+    let writer = &mut self.writer;
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+      writer,
+      "<testsuites name=\"deno test\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+      summary.passed + summary.failed + summary.ignored,
+      summary.failed,
+      summary.ignored,
+      format_seconds(summary.duration)
+    )?;
+    for (name, suite) in &self.suites {
+      let count = |status: TestStatus| {
+        suite
+          .test_cases
+          .iter()
+          .filter(|test_case| test_case.status == status)
+          .count()
+      };
+      writeln!(
+        writer,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">",
+        escape_xml(name),
+        suite.test_cases.len(),
+        count(TestStatus::Failed),
+        if suite.error.is_some() { 1 } else { 0 },
+        count(TestStatus::Ignored),
+        format_seconds(suite.duration)
+      )?;
+      for test_case in &suite.test_cases {
+        write!(
+          writer,
+          "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+          escape_xml(&test_case.name),
+          escape_xml(name),
+          format_seconds(test_case.duration)
+        )?;
+        match (&test_case.status, &test_case.error) {
+          (TestStatus::Failed, Some(error)) => {
+            writeln!(writer, ">")?;
+            writeln!(
+              writer,
+              "      <failure message=\"{}\">{}</failure>",
+              escape_xml(&error.message),
+              escape_xml(&error.stack)
+            )?;
+            writeln!(writer, "    </testcase>")?;
+          }
+          (TestStatus::Ignored, _) => {
+            writeln!(writer, ">")?;
+            writeln!(writer, "      <skipped/>")?;
+            writeln!(writer, "    </testcase>")?;
+          }
+          _ => writeln!(writer, "/>")?,
+        }
+      }
+      if let Some(error) = &suite.error {
+        writeln!(writer, "    <system-err>{}</system-err>", escape_xml(error))?;
+      }
+      writeln!(writer, "  </testsuite>")?;
+    }
+    writeln!(writer, "</testsuites>")?;
+    writer.flush()?;
+    Ok(())
+  }
+}
+
+/// Writes a report in the Test Anything Protocol, version 13. Results are
+/// written as they arrive and the plan follows at the end, as the number of
+/// tests is only known once all modules have run.
+pub struct TapTestReporter {
+  writer: Box<dyn Write>,
+  count: usize,
+  header_written: bool,
+}
+
+impl TapTestReporter {
+  pub fn new(writer: Box<dyn Write>) -> Self {
+    Self {
+      writer,
+      count: 0,
+      header_written: false,
+    }
+  }
+
+  fn write_header(&mut self) -> Result<(), AnyError> {
+    if !self.header_written {
+      writeln!(self.writer, "TAP version 13")?;
+      self.header_written = true;
+    }
+    Ok(())
+  }
+
+  fn write_diagnostics(
+    &mut self,
+    duration: Option<u64>,
+    message: &str,
+    stack: &str,
+  ) -> Result<(), AnyError> {
+    writeln!(self.writer, "  ---")?;
+    if let Some(duration) = duration {
+      writeln!(self.writer, "  duration_ms: {}", duration)?;
+    }
+    // A JSON string is a valid YAML scalar.
+    writeln!(
+      self.writer,
+      "  message: {}",
+      serde_json::to_string(&*colors::strip_ansi_codes(message))?
+    )?;
+    writeln!(self.writer, "  stack: |-")?;
+    for line in colors::strip_ansi_codes(stack).lines() {
+      writeln!(self.writer, "    {}", line)?;
+    }
+    writeln!(self.writer, "  ...")?;
+    Ok(())
+  }
+}
+
+impl TestReporter for TapTestReporter {
+  fn visit_event(&mut self, event: &TestEvent) -> Result<(), AnyError> {
+    self.write_header()?;
+    match event {
+      TestEvent::Message(Some(module), TestMessage::Plan { .. }) => {
+        writeln!(self.writer, "# {}", module)?;
+      }
+      TestEvent::Message(
+        _,
+        TestMessage::TestEnd {
+          name,
+          status,
+          duration,
+          error,
+        },
+      ) => {
+        self.count += 1;
+        match status {
+          TestStatus::Passed => {
+            writeln!(self.writer, "ok {} - {}", self.count, name)?
+          }
+          TestStatus::Ignored => {
+            writeln!(self.writer, "ok {} - {} # SKIP", self.count, name)?
+          }
+          TestStatus::Failed => {
+            writeln!(self.writer, "not ok {} - {}", self.count, name)?;
+            if let Some(error) = error {
+              self.write_diagnostics(
+                Some(*duration),
+                &error.message,
+                &error.stack,
+              )?;
+            }
+          }
+        }
+      }
+      TestEvent::Message(_, _) => {}
+      TestEvent::Error(module, err) => {
+        self.count += 1;
+        writeln!(self.writer, "not ok {} - {}", self.count, module)?;
+        let message = err.to_string();
+        let first_line = message.lines().next().unwrap_or_default();
+        self.write_diagnostics(None, first_line, &message)?;
+      }
+    }
+    self.writer.flush()?;
+    Ok(())
+  }
+
+  fn done(&mut self, _summary: &TestSummary) -> Result<(), AnyError> {
+    self.write_header()?;
+    writeln!(self.writer, "1..{}", self.count)?;
+    self.writer.flush()?;
+    Ok(())
+  }
+}
+
+/// The summary and the reporter of a test run, which are fed with the events
+/// of all test modules.
+struct TestReport {
+  summary: TestSummary,
+  reporter: Box<dyn TestReporter>,
+}
+
+impl TestReport {
+  fn visit_event(&mut self, event: TestEvent) -> Result<(), AnyError> {
+    self.summary.visit_event(&event);
+    self.reporter.visit_event(&event)
+  }
+}
+
+/// Saves the entry point which runs the tests of `modules` into the cache of
+/// the file fetcher, under `file_name` in the current directory.
+fn save_test_file(
+  program_state: &Arc<ProgramState>,
+  file_name: &str,
+  modules: Vec<Url>,
+  options: &TestOptions,
+) -> Result<ModuleSpecifier, AnyError> {
+  let test_file_path = std::env::current_dir()?.join(file_name);
+  let test_file_url =
+    Url::from_file_path(&test_file_path).expect("Should be valid file url");
+  let main_module = ModuleSpecifier::resolve_url(test_file_url.as_str())?;
+  // Create a dummy source file.
+  let source_file = SourceFile {
+    filename: test_file_path,
+    url: test_file_url,
+    types_header: None,
+    media_type: MediaType::TypeScript,
+    source_code: render_test_file(modules, options),
+  };
+  // Save our fake file into file fetcher cache
+  // to allow module access by TS compiler
+  program_state
+    .file_fetcher
+    .save_source_file_in_cache(&main_module, source_file);
+  Ok(main_module)
+}
+
+/// Runs the tests of a test file in a new `MainWorker` on the current thread.
+async fn run_test_file(
+  program_state: &Arc<ProgramState>,
+  permissions: Permissions,
+  main_module: ModuleSpecifier,
+  sender: TestEventSender,
+) -> Result<(), AnyError> {
+  let mut worker =
+    MainWorker::new(program_state, main_module.clone(), permissions);
+  worker.js_runtime.op_state().borrow_mut().put(sender);
+
+  let mut maybe_coverage_collector =
+    coverage::start_coverage_collector(program_state, &mut worker).await?;
+
+  worker.execute_module(&main_module).await?;
+  worker.execute("window.dispatchEvent(new Event('load'))")?;
+  worker.run_event_loop().await?;
+  worker.execute("window.dispatchEvent(new Event('unload'))")?;
+  worker.run_event_loop().await?;
+
+  if let Some(coverage_collector) = maybe_coverage_collector.as_mut() {
+    coverage_collector.stop_collecting().await?;
+  }
+  Ok(())
+}
+
+/// Runs every test module in its own isolate, with up to `jobs` modules
+/// running in parallel on separate threads. With `fail_fast`, no further
+/// modules are started once a test failed.
+async fn run_isolated_test_modules(
+  program_state: &Arc<ProgramState>,
+  permissions: Permissions,
+  test_modules: Vec<Url>,
+  jobs: usize,
+  options: &TestOptions,
+  report: &RefCell<TestReport>,
+) -> Result<(), AnyError> {
+  let mut queue = VecDeque::new();
+  for (index, test_module) in test_modules.into_iter().enumerate() {
+    let main_module = save_test_file(
+      program_state,
+      &format!("$deno$test_{}.ts", index),
+      vec![test_module.clone()],
+      options,
+    )?;
+    queue.push_back((test_module, main_module));
+  }
+
+  let jobs = jobs.min(queue.len());
+  let queue = Arc::new(Mutex::new(queue));
+  let cancelled = Arc::new(AtomicBool::new(false));
+  let (sender, mut receiver) = mpsc::unbounded::<TestEvent>();
+
+  let mut join_handles = Vec::new();
+  for job in 0..jobs {
+    let program_state = program_state.clone();
+    let permissions = permissions.clone();
+    let queue = queue.clone();
+    let cancelled = cancelled.clone();
+    let sender = sender.clone();
+    let builder =
+      std::thread::Builder::new().name(format!("deno-test-{}", job));
+    join_handles.push(builder.spawn(move || {
+      let mut rt = create_basic_runtime();
+      while !cancelled.load(Ordering::SeqCst) {
+        let (test_module, main_module) = match queue.lock().unwrap().pop_front()
+        {
+          Some(next) => next,
+          None => break,
+        };
+        let module_sender = sender.clone();
+        let event_sender = TestEventSender {
+          module: Some(test_module.clone()),
+          handler: Box::new(move |event| {
+            module_sender
+              .unbounded_send(event)
+              .map_err(|_| generic_error("The test report is already done"))
+          }),
+        };
+        let result = rt.block_on(run_test_file(
+          &program_state,
+          permissions.clone(),
+          main_module,
+          event_sender,
+        ));
+        if let Err(err) = result {
+          // The receiver is only dropped after all senders are gone.
+          sender
+            .unbounded_send(TestEvent::Error(test_module, err))
+            .unwrap();
+        }
+      }
+    })?);
+  }
+  drop(sender);
+
+  while let Some(event) = receiver.next().await {
+    let mut report = report.borrow_mut();
+    report.visit_event(event)?;
+    if options.fail_fast && report.summary.failed > 0 {
+      cancelled.store(true, Ordering::SeqCst);
+    }
+  }
+  for join_handle in join_handles {
+    join_handle.join().unwrap();
+  }
+  Ok(())
+}
+
+/// Runs the tests of the given modules and reports on them. Returns `false` if
+/// the test run failed.
+pub async fn run_tests(
+  program_state: &Arc<ProgramState>,
+  permissions: Permissions,
+  test_modules: Vec<Url>,
+  options: TestOptions,
+) -> Result<bool, AnyError> {
+  let reporter = create_reporter(
+    &options.reporter,
+    options.quiet,
+    options.jobs.is_some(),
+    options.reporter_output.clone(),
+  )?;
+  let report = Rc::new(RefCell::new(TestReport {
+    summary: TestSummary::default(),
+    reporter,
+  }));
+  let start_time = Instant::now();
+
+  if let Some(jobs) = options.jobs {
+    run_isolated_test_modules(
+      program_state,
+      permissions,
+      test_modules,
+      jobs,
+      &options,
+      &report,
+    )
+    .await?;
+  } else {
+    let main_module =
+      save_test_file(program_state, "$deno$test.ts", test_modules, &options)?;
+    // The messages are reported right away, so that the output of a test
+    // follows its name.
+    let report_ = report.clone();
+    let event_sender = TestEventSender {
+      module: None,
+      handler: Box::new(move |event| report_.borrow_mut().visit_event(event)),
+    };
+    run_test_file(program_state, permissions, main_module, event_sender)
+      .await?;
+  }
+
+  let mut report = report.borrow_mut();
+  report.summary.duration = start_time.elapsed().as_millis() as u64;
+  let TestReport { summary, reporter } = &mut *report;
+  reporter.done(summary)?;
+  Ok(!summary.has_failed())
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -127,6 +935,197 @@ mod tests {
     assert_eq!(matched_urls, expected);
   }
 
+  #[test]
+  fn test_render_test_file() {
+    let modules = vec![
+      Url::parse("file:///a/foo_test.ts").unwrap(),
+      Url::parse("file:///a/bar_test.ts").unwrap(),
+    ];
+    let options = TestOptions {
+      fail_fast: true,
+      quiet: false,
+      filter: Some("bar".to_string()),
+      jobs: None,
+      reporter: "pretty".to_string(),
+      reporter_output: None,
+      shuffle: Some(42),
+    };
+    let test_file = render_test_file(modules, &options);
+    assert!(test_file.starts_with(
+      "import \"file:///a/foo_test.ts\";\nimport \"file:///a/bar_test.ts\";\n"
+    ));
+    assert!(test_file.contains("\"reportToHost\":true"));
+    assert!(test_file.contains("\"exitOnFail\":false"));
+    assert!(test_file.contains("\"failFast\":true"));
+    assert!(test_file.contains("\"filter\":\"bar\""));
+    assert!(test_file.contains("\"shuffle\":42"));
+  }
+
+  fn test_module_urls(names: &[&str]) -> Vec<Url> {
+    names
+      .iter()
+      .map(|name| Url::parse(&format!("file:///a/{}", name)).unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_shard_test_modules() {
+    let modules = test_module_urls(&["e.ts", "b.ts", "d.ts", "a.ts", "c.ts"]);
+    assert_eq!(
+      shard_test_modules(modules.clone(), 1, 2),
+      test_module_urls(&["a.ts", "c.ts", "e.ts"])
+    );
+    assert_eq!(
+      shard_test_modules(modules.clone(), 2, 2),
+      test_module_urls(&["b.ts", "d.ts"])
+    );
+    assert!(shard_test_modules(modules, 7, 8).is_empty());
+  }
+
+  #[test]
+  fn test_shuffle_test_modules() {
+    let modules = test_module_urls(&["a.ts", "b.ts", "c.ts", "d.ts", "e.ts"]);
+    let mut shuffled1 = modules.clone();
+    shuffle_test_modules(&mut shuffled1, 1);
+    let mut shuffled2 = modules.clone();
+    shuffled2.reverse();
+    shuffle_test_modules(&mut shuffled2, 1);
+    assert_eq!(shuffled1, shuffled2);
+    shuffled1.sort();
+    assert_eq!(shuffled1, modules);
+  }
+
+  #[test]
+  fn test_deserialize_test_message() {
+    let message: TestMessage = serde_json::from_value(json!({
+      "testEnd": {
+        "name": "foo",
+        "status": "failed",
+        "duration": 3,
+        "error": {
+          "message": "bar",
+          "stack": "Error: bar\n    at file:///a/foo_test.ts:1:7",
+        },
+      }
+    }))
+    .unwrap();
+    assert_eq!(
+      message,
+      TestMessage::TestEnd {
+        name: "foo".to_string(),
+        status: TestStatus::Failed,
+        duration: 3,
+        error: Some(TestError {
+          message: "bar".to_string(),
+          stack: "Error: bar\n    at file:///a/foo_test.ts:1:7".to_string(),
+        }),
+      }
+    );
+  }
+
+  /// A writer whose content can be read after it was given to a reporter.
+  #[derive(Clone, Default)]
+  struct SharedWriter(Rc<RefCell<Vec<u8>>>);
+
+  impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  impl SharedWriter {
+    fn contents(&self) -> String {
+      colors::strip_ansi_codes(&String::from_utf8_lossy(&self.0.borrow()))
+        .to_string()
+    }
+  }
+
+  fn report_events(reporter: &mut dyn TestReporter) {
+    let module = Some(Url::parse("file:///a/foo_test.ts").unwrap());
+    let events = vec![
+      TestMessage::Plan {
+        tests: 3,
+        filtered: 0,
+        used_only: false,
+      },
+      TestMessage::TestEnd {
+        name: "passes".to_string(),
+        status: TestStatus::Passed,
+        duration: 1,
+        error: None,
+      },
+      TestMessage::TestEnd {
+        name: "fails <1>".to_string(),
+        status: TestStatus::Failed,
+        duration: 2,
+        error: Some(TestError {
+          message: "bar".to_string(),
+          stack: "Error: bar\n    at file:///a/foo_test.ts:5:9".to_string(),
+        }),
+      },
+      TestMessage::TestEnd {
+        name: "ignored".to_string(),
+        status: TestStatus::Ignored,
+        duration: 0,
+        error: None,
+      },
+      TestMessage::End { duration: 3 },
+    ];
+    let mut summary = TestSummary::default();
+    for message in events {
+      let event = TestEvent::Message(module.clone(), message);
+      summary.visit_event(&event);
+      reporter.visit_event(&event).unwrap();
+    }
+    reporter.done(&summary).unwrap();
+  }
+
+  #[test]
+  fn test_junit_reporter() {
+    let writer = SharedWriter::default();
+    let mut reporter = JUnitTestReporter::new(Box::new(writer.clone()));
+    report_events(&mut reporter);
+    let xml = writer.contents();
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(xml.contains(
+      "<testsuites name=\"deno test\" tests=\"3\" failures=\"1\" skipped=\"1\""
+    ));
+    assert!(xml.contains("<testsuite name=\"file:///a/foo_test.ts\" tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\" time=\"0.003\">"));
+    assert!(xml.contains("<testcase name=\"passes\" classname=\"file:///a/foo_test.ts\" time=\"0.001\"/>"));
+    assert!(xml.contains("<testcase name=\"fails &lt;1&gt;\""));
+    assert!(xml.contains("<failure message=\"bar\">Error: bar\n    at file:///a/foo_test.ts:5:9</failure>"));
+    assert!(xml.contains("<skipped/>"));
+    assert!(xml.ends_with("</testsuites>\n"));
+  }
+
+  #[test]
+  fn test_tap_reporter() {
+    let writer = SharedWriter::default();
+    let mut reporter = TapTestReporter::new(Box::new(writer.clone()));
+    report_events(&mut reporter);
+    assert_eq!(
+      writer.contents(),
+      r#"TAP version 13
+# file:///a/foo_test.ts
+ok 1 - passes
+not ok 2 - fails <1>
+  ---
+  duration_ms: 2
+  message: "bar"
+  stack: |-
+    Error: bar
+        at file:///a/foo_test.ts:5:9
+  ...
+ok 3 - ignored # SKIP
+1..3
+"#
+    );
+  }
+
   #[test]
   fn test_is_supported() {
     assert!(is_supported(Path::new("tests/subdir/foo_test.ts")));
@@ -0,0 +1,17 @@
+console.log(await Deno.permissions.query({ name: "env", variable: "FOO" }));
+console.log(await Deno.permissions.query({ name: "env", variable: "BAR" }));
+console.log(await Deno.permissions.query({ name: "run", command: "curl" }));
+console.log(await Deno.permissions.query({ name: "run" }));
+
+Deno.env.set("FOO", "foo");
+console.log(Deno.env.get("FOO"));
+try {
+  Deno.env.get("BAR");
+} catch (e) {
+  console.log(e.message);
+}
+try {
+  Deno.run({ cmd: ["git", "--version"] });
+} catch (e) {
+  console.log(e.message);
+}
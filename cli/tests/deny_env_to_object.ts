@@ -0,0 +1,10 @@
+const accesses = [() => Deno.env.toObject(), () => Deno.env.get("SECRET")];
+for (const access of accesses) {
+  try {
+    access();
+    console.log("granted");
+  } catch (e) {
+    console.log(e.name);
+  }
+}
+console.log(typeof Deno.env.get("PATH"));
@@ -0,0 +1,26 @@
+import { typed } from "data:application/typescript;base64,ZXhwb3J0IGNvbnN0IHR5cGVkOiBzdHJpbmcgPSAiZnJvbSBhIFR5cGVTY3JpcHQgZGF0YSBVUkwiOwo=";
+import { plain } from "data:text/javascript,export const plain = %22from a percent-encoded data URL%22;";
+
+console.log(typed);
+console.log(plain);
+
+const { dynamic } = await import(
+  "data:application/javascript,export const dynamic = 'from a dynamic import';"
+);
+console.log(dynamic);
+
+const blob = new Blob(
+  ["export const fromBlob = 'from a blob URL';"],
+  { type: "application/javascript" },
+);
+const url = URL.createObjectURL(blob);
+const { fromBlob } = await import(url);
+console.log(fromBlob);
+
+const revoked = URL.createObjectURL(blob);
+URL.revokeObjectURL(revoked);
+try {
+  await import(revoked);
+} catch {
+  console.log("revoked blob URL not found");
+}
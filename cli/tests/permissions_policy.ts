@@ -0,0 +1,10 @@
+const descriptors: Deno.PermissionDescriptor[] = [
+  { name: "read", path: "subdir/mod1.ts" },
+  { name: "read", path: "permissions_policy.ts" },
+  { name: "env", variable: "FOO" },
+  { name: "env", variable: "BAR" },
+  { name: "env", variable: "BAZ" },
+];
+for (const descriptor of descriptors) {
+  console.log(await Deno.permissions.query(descriptor));
+}
@@ -0,0 +1,11 @@
+const res = await fetch("http://localhost:4545/cli/tests/fixture.json");
+console.log(`fetch: ${res.status}`);
+await res.arrayBuffer();
+
+const ws = new WebSocket("ws://localhost:4242");
+ws.onopen = (): void => ws.send("hello");
+ws.onmessage = (e): void => {
+  console.log(`websocket: ${e.data}`);
+  ws.close();
+};
+ws.onerror = (): void => console.log("websocket: error");
@@ -0,0 +1,5 @@
+console.log("start");
+// The specifier is computed at runtime so that the bundler leaves the dynamic
+// import in place.
+const specifier = ["./001_hello", "js"].join(".");
+await import(specifier);
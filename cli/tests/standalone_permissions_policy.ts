@@ -0,0 +1,9 @@
+Promise.all(
+  ["FOO", "BAR", "BAZ"].map((variable) =>
+    Deno.permissions.query({ name: "env", variable })
+  ),
+).then((statuses) => {
+  for (const status of statuses) {
+    console.log(status.state);
+  }
+});
@@ -0,0 +1,13 @@
+onmessage = async function (): Promise<void> {
+  const descriptors: Deno.PermissionDescriptor[] = [
+    { name: "read", path: "subdir" },
+    { name: "read", path: "workers_test.ts" },
+    { name: "net", url: "http://localhost:4545" },
+  ];
+  const states = [];
+  for (const descriptor of descriptors) {
+    const { state } = await Deno.permissions.query(descriptor);
+    states.push(state);
+  }
+  postMessage(states);
+};
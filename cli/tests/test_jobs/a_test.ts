@@ -0,0 +1,9 @@
+// This global must not be visible to the other test modules.
+// deno-lint-ignore no-explicit-any
+(globalThis as any).leaked = true;
+
+Deno.test("a1", function () {});
+
+Deno.test("a2 fails", function () {
+  throw new Error("a2 failed");
+});
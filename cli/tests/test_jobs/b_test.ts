@@ -0,0 +1,7 @@
+Deno.test("b1 sees no leaked global", function () {
+  if ("leaked" in globalThis) {
+    throw new Error("global leaked from a_test.ts");
+  }
+});
+
+Deno.test("b2", function () {});
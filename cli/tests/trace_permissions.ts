@@ -0,0 +1,3 @@
+Deno.env.get("FOO");
+Deno.env.get("BAR");
+Deno.env.get("FOO");
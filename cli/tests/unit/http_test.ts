@@ -0,0 +1,125 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { assert, assertEquals, unitTest } from "./test_util.ts";
+
+unitTest(
+  { perms: { net: true } },
+  async function httpServerBasic(): Promise<void> {
+    const promise = (async () => {
+      const listener = Deno.listen({ port: 4601 });
+      const conn = await listener.accept();
+      listener.close();
+      const httpConn = Deno.serveHttp(conn);
+      const requestEvent = await httpConn.nextRequest();
+      assert(requestEvent);
+      const { request, respondWith } = requestEvent;
+      assertEquals(request.method, "GET");
+      assertEquals(request.url, "http://127.0.0.1:4601/foo?bar=baz");
+      assertEquals(request.headers.get("x-foo"), "bar");
+      assertEquals(await request.text(), "");
+      await respondWith(
+        new Response("Hello World", { headers: { "foo": "bar" } }),
+      );
+      httpConn.close();
+    })();
+
+    const resp = await fetch("http://127.0.0.1:4601/foo?bar=baz", {
+      headers: { "x-foo": "bar" },
+    });
+    assertEquals(resp.status, 200);
+    assertEquals(resp.headers.get("foo"), "bar");
+    assertEquals(await resp.text(), "Hello World");
+    await promise;
+  },
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function httpServerRequestBody(): Promise<void> {
+    const promise = (async () => {
+      const listener = Deno.listen({ port: 4602 });
+      const conn = await listener.accept();
+      listener.close();
+      const httpConn = Deno.serveHttp(conn);
+      const requestEvent = await httpConn.nextRequest();
+      assert(requestEvent);
+      const { request, respondWith } = requestEvent;
+      assertEquals(request.method, "POST");
+      const body = await request.text();
+      await respondWith(new Response(body.toUpperCase(), { status: 201 }));
+      httpConn.close();
+    })();
+
+    const resp = await fetch("http://127.0.0.1:4602/", {
+      method: "POST",
+      body: "hello world",
+    });
+    assertEquals(resp.status, 201);
+    assertEquals(await resp.text(), "HELLO WORLD");
+    await promise;
+  },
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function httpServerStreamResponse(): Promise<void> {
+    const encoder = new TextEncoder();
+    const promise = (async () => {
+      const listener = Deno.listen({ port: 4603 });
+      const conn = await listener.accept();
+      listener.close();
+      const httpConn = Deno.serveHttp(conn);
+      const requestEvent = await httpConn.nextRequest();
+      assert(requestEvent);
+      let i = 0;
+      const body = new ReadableStream<Uint8Array>({
+        pull(controller) {
+          if (i < 3) {
+            controller.enqueue(encoder.encode(`chunk${i++} `));
+          } else {
+            controller.close();
+          }
+        },
+      });
+      await requestEvent.respondWith(new Response(body));
+      httpConn.close();
+    })();
+
+    const resp = await fetch("http://127.0.0.1:4603/");
+    assertEquals(await resp.text(), "chunk0 chunk1 chunk2 ");
+    await promise;
+  },
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function httpServerKeepAlive(): Promise<void> {
+    const encoder = new TextEncoder();
+    const decoder = new TextDecoder();
+    const promise = (async () => {
+      const listener = Deno.listen({ port: 4604 });
+      const conn = await listener.accept();
+      listener.close();
+      const paths: string[] = [];
+      for await (const { request, respondWith } of Deno.serveHttp(conn)) {
+        paths.push(new URL(request.url).pathname);
+        await respondWith(new Response("ok"));
+      }
+      assertEquals(paths, ["/a", "/b"]);
+    })();
+
+    // Both requests are pipelined on a single connection.
+    const conn = await Deno.connect({ port: 4604 });
+    const requests = "GET /a HTTP/1.1\r\nHost: localhost\r\n\r\n" +
+      "GET /b HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    await conn.write(encoder.encode(requests));
+    let response = "";
+    const buf = new Uint8Array(1024);
+    let nread;
+    while ((nread = await conn.read(buf)) !== null) {
+      response += decoder.decode(buf.subarray(0, nread));
+    }
+    conn.close();
+    assertEquals(response.match(/HTTP\/1.1 200 OK/g)?.length, 2);
+    await promise;
+  },
+);
@@ -0,0 +1,109 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { assertEquals, assertThrowsAsync, unitTest } from "./test_util.ts";
+
+const encoder = new TextEncoder();
+
+function u16(n: number): number[] {
+  return [n >> 8, n & 0xff];
+}
+
+function u32(n: number): number[] {
+  return [...u16(n >>> 16), ...u16(n & 0xffff)];
+}
+
+function encodeName(name: string): number[] {
+  const bytes: number[] = [];
+  for (const label of name.split(".")) {
+    bytes.push(label.length, ...encoder.encode(label));
+  }
+  bytes.push(0);
+  return bytes;
+}
+
+// The RDATA served by the stand-in name server, by record type.
+const records: Record<number, number[][]> = {
+  // A
+  1: [[1, 2, 3, 4]],
+  // MX
+  15: [[...u16(10), ...encodeName("mail.example.com")]],
+  // TXT
+  16: [[3, ...encoder.encode("foo"), 3, ...encoder.encode("bar")]],
+  // SRV
+  33: [[...u16(0), ...u16(5), ...u16(8080), ...encodeName("www.example.com")]],
+};
+
+/** Answers a DNS query with the records of the queried type, ignoring the
+ * queried name. */
+function respond(query: Uint8Array): Uint8Array {
+  // The question follows the 12 bytes header.
+  let end = 12;
+  while (query[end] !== 0) {
+    end += query[end] + 1;
+  }
+  // The root label, QTYPE and QCLASS.
+  end += 5;
+  const recordType = (query[end - 4] << 8) | query[end - 3];
+  const answers = records[recordType] ?? [];
+  const response = [
+    query[0],
+    query[1],
+    // A response to a recursive query.
+    0x81,
+    0x80,
+    ...u16(1),
+    ...u16(answers.length),
+    ...u16(0),
+    ...u16(0),
+    ...query.subarray(12, end),
+  ];
+  for (const rdata of answers) {
+    // The name is a pointer to the name of the question.
+    response.push(0xc0, 12, ...u16(recordType), ...u16(1), ...u32(300));
+    response.push(...u16(rdata.length), ...rdata);
+  }
+  return new Uint8Array(response);
+}
+
+unitTest(async function resolveDnsNoPerm(): Promise<void> {
+  await assertThrowsAsync(async () => {
+    await Deno.resolveDns("www.example.com", "A", {
+      nameServer: { ipAddr: "127.0.0.1", port: 4553 },
+    });
+  }, Deno.errors.PermissionDenied);
+});
+
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { net: true } },
+  async function resolveDnsWithNameServer(): Promise<void> {
+    const nameServer = { ipAddr: "127.0.0.1", port: 4553 };
+    const server = Deno.listenDatagram({ port: 4553, transport: "udp" });
+    const serverPromise = (async (): Promise<void> => {
+      for (let i = 0; i < 5; i++) {
+        const [query, addr] = await server.receive();
+        await server.send(respond(query), addr);
+      }
+      server.close();
+    })();
+
+    assertEquals(
+      await Deno.resolveDns("www.example.com", "A", { nameServer }),
+      ["1.2.3.4"],
+    );
+    assertEquals(
+      await Deno.resolveDns("example.com", "MX", { nameServer }),
+      [{ preference: 10, exchange: "mail.example.com." }],
+    );
+    assertEquals(
+      await Deno.resolveDns("www.example.com", "TXT", { nameServer }),
+      [["foo", "bar"]],
+    );
+    assertEquals(
+      await Deno.resolveDns("_http._tcp.example.com", "SRV", { nameServer }),
+      [{ priority: 0, weight: 5, port: 8080, target: "www.example.com." }],
+    );
+    await assertThrowsAsync(async () => {
+      await Deno.resolveDns("www.example.com", "AAAA", { nameServer });
+    }, Deno.errors.NotFound);
+    await serverPromise;
+  },
+);
@@ -232,3 +232,131 @@ unitTest(
     conn.close();
   },
 );
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function dialAndListenTLSInMemory(): Promise<void> {
+    const hostname = "localhost";
+    const port = 3504;
+
+    const listener = Deno.listenTls({
+      hostname,
+      port,
+      certChain: Deno.readTextFileSync("cli/tests/tls/localhost.crt"),
+      privateKey: Deno.readTextFileSync("cli/tests/tls/localhost.key"),
+    });
+    const serverPromise = listener.accept().then(
+      async (conn): Promise<void> => {
+        await conn.write(encoder.encode("hello"));
+        conn.close();
+      },
+    );
+
+    const conn = await Deno.connectTls({
+      hostname,
+      port,
+      caCerts: [Deno.readTextFileSync("cli/tests/tls/RootCA.pem")],
+    });
+    const buf = new Uint8Array(5);
+    await new BufReader(conn).readFull(buf);
+    assertEquals(decoder.decode(buf), "hello");
+    conn.close();
+    listener.close();
+    await serverPromise;
+  },
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function mutualTLS(): Promise<void> {
+    const hostname = "localhost";
+    const port = 3505;
+
+    const listener = Deno.listenTls({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+      clientCaFile: "cli/tests/tls/RootCA.pem",
+    });
+
+    // A client without a certificate is rejected.
+    const rejectedPromise = assertThrowsAsync(() => listener.accept());
+    try {
+      const rejectedConn = await Deno.connectTls({
+        hostname,
+        port,
+        certFile: "cli/tests/tls/RootCA.pem",
+      });
+      try {
+        await rejectedConn.read(new Uint8Array(1));
+      } finally {
+        rejectedConn.close();
+      }
+    } catch {
+      // The server aborts the handshake.
+    }
+    await rejectedPromise;
+
+    const serverPromise = listener.accept().then(
+      async (conn): Promise<void> => {
+        await conn.write(encoder.encode("hello"));
+        conn.close();
+      },
+    );
+    const conn = await Deno.connectTls({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+      certChainFile: "cli/tests/tls/localhost.crt",
+      privateKey: Deno.readTextFileSync("cli/tests/tls/localhost.key"),
+    });
+    const buf = new Uint8Array(5);
+    await new BufReader(conn).readFull(buf);
+    assertEquals(decoder.decode(buf), "hello");
+    conn.close();
+    listener.close();
+    await serverPromise;
+  },
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSAlpnAndSni(): Promise<void> {
+    const port = 3506;
+
+    // There is no default certificate, so only clients which send the
+    // "localhost" SNI hostname are accepted.
+    const listener = Deno.listenTls({
+      hostname: "localhost",
+      port,
+      sniCerts: {
+        localhost: {
+          certFile: "cli/tests/tls/localhost.crt",
+          keyFile: "cli/tests/tls/localhost.key",
+        },
+      },
+      alpnProtocols: ["h2", "http/1.1"],
+    });
+    const serverPromise = listener.accept().then(
+      async (conn): Promise<void> => {
+        assertEquals((conn as Deno.TlsConn).alpnProtocol, "http/1.1");
+        await conn.write(encoder.encode("hello"));
+        conn.close();
+      },
+    );
+
+    const conn = await Deno.connectTls({
+      hostname: "localhost",
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+      alpnProtocols: ["http/1.1"],
+    });
+    const buf = new Uint8Array(5);
+    await new BufReader(conn).readFull(buf);
+    assertEquals(decoder.decode(buf), "hello");
+    conn.close();
+    listener.close();
+    await serverPromise;
+  },
+);
@@ -32,6 +32,7 @@ import "./fs_events_test.ts";
 import "./get_random_values_test.ts";
 import "./globals_test.ts";
 import "./headers_test.ts";
+import "./http_test.ts";
 import "./internals_test.ts";
 import "./io_test.ts";
 import "./link_test.ts";
@@ -53,6 +54,7 @@ import "./read_link_test.ts";
 import "./remove_test.ts";
 import "./rename_test.ts";
 import "./request_test.ts";
+import "./resolve_dns_test.ts";
 import "./resources_test.ts";
 import "./response_test.ts";
 import "./signal_test.ts";
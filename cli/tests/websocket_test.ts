@@ -273,3 +273,33 @@ Deno.test("echo arraybuffer with binaryType arraybuffer", async () => {
   };
   await promise;
 });
+
+Deno.test("accept websocket", async () => {
+  const listener = Deno.listen({ port: 4250 });
+  const serverClosed = createResolvable();
+  const server = (async (): Promise<void> => {
+    const conn = await listener.accept();
+    listener.close();
+    const socket = await Deno.acceptWebSocket(conn, { protocols: ["chat"] });
+    assertEquals(socket.url, "ws://localhost:4250/foo?bar");
+    assertEquals(socket.protocol, "chat");
+    socket.onerror = (): void => fail();
+    socket.onmessage = (e): void => socket.send(e.data);
+    socket.onclose = (): void => serverClosed.resolve();
+  })();
+
+  const clientClosed = createResolvable();
+  const ws = new WebSocket("ws://localhost:4250/foo?bar", ["foo", "chat"]);
+  ws.onerror = (): void => fail();
+  ws.onopen = (): void => {
+    assertEquals(ws.protocol, "chat");
+    ws.send("foo");
+  };
+  ws.onmessage = (e): void => {
+    assertEquals(e.data, "foo");
+    ws.close();
+  };
+  ws.onclose = (): void => clientClosed.resolve();
+  await server;
+  await Promise.all([serverClosed, clientClosed]);
+});
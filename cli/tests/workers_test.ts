@@ -7,7 +7,11 @@
 // yet implemented. Once it gets implemented this file should be
 // again moved to `cli/js/` as an unit test file.
 
-import { assert, assertEquals } from "../../std/testing/asserts.ts";
+import {
+  assert,
+  assertEquals,
+  assertThrows,
+} from "../../std/testing/asserts.ts";
 
 export interface ResolvableMethods<T> {
   resolve: (value?: T | PromiseLike<T>) => void;
@@ -334,3 +338,46 @@ Deno.test({
     w.terminate();
   },
 });
+
+Deno.test({
+  name: "worker with narrowed permissions",
+  fn: async function (): Promise<void> {
+    const promise = createResolvable();
+    const w = new Worker(
+      new URL("subdir/permissions_worker.ts", import.meta.url).href,
+      {
+        type: "module",
+        deno: {
+          namespace: true,
+          permissions: { read: ["subdir"], net: false },
+        },
+      },
+    );
+    w.onmessage = (e): void => {
+      assertEquals(e.data, ["granted", "prompt", "denied"]);
+      promise.resolve();
+    };
+    w.postMessage(null);
+    await promise;
+    w.terminate();
+  },
+});
+
+Deno.test({
+  name: "worker can't escalate permissions",
+  fn: function (): void {
+    assertThrows(
+      () => {
+        new Worker(
+          new URL("subdir/permissions_worker.ts", import.meta.url).href,
+          {
+            type: "module",
+            deno: { namespace: true, permissions: { write: true } },
+          },
+        );
+      },
+      Deno.errors.PermissionDenied,
+      "Can't escalate parent thread permissions: write access",
+    );
+  },
+});
@@ -20,6 +20,7 @@ use deno_core::OpFn;
 use deno_core::RuntimeOptions;
 use deno_core::Snapshot;
 use serde::Deserialize;
+use serde::Serialize;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -104,6 +105,9 @@ struct State {
   graph: Rc<RefCell<Graph>>,
   maybe_tsbuildinfo: Option<String>,
   maybe_response: Option<RespondArgs>,
+  maybe_server_response: Option<Value>,
+  /// The specifiers which tsc knows by another name, see `remap_root_names()`
+  /// and `remap_specifier()`.
   root_map: HashMap<String, ModuleSpecifier>,
 }
 
@@ -120,6 +124,7 @@ impl State {
       graph,
       maybe_tsbuildinfo,
       maybe_response: None,
+      maybe_server_response: None,
       root_map,
     }
   }
@@ -286,10 +291,15 @@ fn resolve(state: &mut State, args: Value) -> Result<Value, AnyError> {
               resolved_specifier
             )
           };
-          resolved.push((
-            resolved_specifier.to_string(),
-            media_type.as_ts_extension(),
-          ));
+          let resolved_name = if let Some(name) =
+            remap_specifier(&resolved_specifier, &media_type)
+          {
+            state.root_map.insert(name.clone(), resolved_specifier);
+            name
+          } else {
+            resolved_specifier.to_string()
+          };
+          resolved.push((resolved_name, media_type.as_ts_extension()));
         }
         // in certain situations, like certain dynamic imports, we won't have
         // the source file in the graph, so we will return a fake module to
@@ -317,26 +327,53 @@ fn respond(state: &mut State, args: Value) -> Result<Value, AnyError> {
   Ok(json!(true))
 }
 
-/// Execute a request on the supplied snapshot, returning a response which
-/// contains information, like any emitted files, diagnostics, statistics and
-/// optionally an updated TypeScript build info.
-pub fn exec(
-  snapshot: Snapshot,
-  request: Request,
-) -> Result<Response, AnyError> {
-  let mut runtime = JsRuntime::new(RuntimeOptions {
-    startup_snapshot: Some(snapshot),
-    ..Default::default()
-  });
-  // tsc cannot handle root specifiers that don't have one of the "acceptable"
-  // extensions.  Therefore, we have to check the root modules against their
-  // extensions and remap any that are unacceptable to tsc and add them to the
-  // op state so when requested, we can remap to the original specifier.
+#[derive(Debug, Deserialize)]
+struct ServerRespondArgs {
+  data: Value,
+}
+
+fn server_respond(state: &mut State, args: Value) -> Result<Value, AnyError> {
+  let v: ServerRespondArgs = serde_json::from_value(args)
+    .context("Error converting the result for \"op_server_respond\".")?;
+  state.maybe_server_response = Some(v.data);
+  Ok(json!(true))
+}
+
+/// tsc treats specifiers without an authority, like data and blob URLs, as
+/// relative paths. Those are given a name based on a hash of the URL instead,
+/// which is returned if the specifier needs it.
+fn remap_specifier(
+  specifier: &ModuleSpecifier,
+  media_type: &MediaType,
+) -> Option<String> {
+  let url = specifier.as_url();
+  match url.scheme() {
+    "data" | "blob" => Some(format!(
+      "{}:///{}{}",
+      url.scheme(),
+      crate::checksum::gen(&[url.as_str().as_bytes()]),
+      media_type.as_ts_extension()
+    )),
+    _ => None,
+  }
+}
+
+/// tsc cannot handle root specifiers that don't have one of the "acceptable"
+/// extensions.  Therefore, we have to check the root modules against their
+/// extensions and remap any that are unacceptable to tsc, returning a map of
+/// the remapped root names so when requested, we can remap to the original
+/// specifier.
+fn remap_root_names(
+  root_names: &[(ModuleSpecifier, MediaType)],
+) -> (Vec<String>, HashMap<String, ModuleSpecifier>) {
   let mut root_map = HashMap::new();
-  let root_names: Vec<String> = request
-    .root_names
+  let root_names = root_names
     .iter()
     .map(|(s, mt)| {
+      if let Some(new_specifier) = remap_specifier(s, mt) {
+        root_map.insert(new_specifier.clone(), s.clone());
+        return new_specifier;
+      }
       let ext_media_type = MediaType::from(&s.as_str().to_owned());
       if mt != &ext_media_type {
         let new_specifier = format!("{}{}", s, mt.as_ts_extension());
@@ -347,6 +384,30 @@ pub fn exec(
       }
     })
     .collect();
+  (root_names, root_map)
+}
+
+fn register_ops(runtime: &mut JsRuntime) {
+  runtime.register_op("op_create_hash", op(create_hash));
+  runtime.register_op("op_emit", op(emit));
+  runtime.register_op("op_load", op(load));
+  runtime.register_op("op_resolve", op(resolve));
+  runtime.register_op("op_respond", op(respond));
+  runtime.register_op("op_server_respond", op(server_respond));
+}
+
+/// Execute a request on the supplied snapshot, returning a response which
+/// contains information, like any emitted files, diagnostics, statistics and
+/// optionally an updated TypeScript build info.
+pub fn exec(
+  snapshot: Snapshot,
+  request: Request,
+) -> Result<Response, AnyError> {
+  let mut runtime = JsRuntime::new(RuntimeOptions {
+    startup_snapshot: Some(snapshot),
+    ..Default::default()
+  });
+  let (root_names, root_map) = remap_root_names(&request.root_names);
 
   {
     let op_state = runtime.op_state();
@@ -359,11 +420,7 @@ pub fn exec(
     ));
   }
 
-  runtime.register_op("op_create_hash", op(create_hash));
-  runtime.register_op("op_emit", op(emit));
-  runtime.register_op("op_load", op(load));
-  runtime.register_op("op_resolve", op(resolve));
-  runtime.register_op("op_respond", op(respond));
+  register_ops(&mut runtime);
 
   let startup_source = "globalThis.startup({ legacyFlag: false })";
   let request_value = json!({
@@ -400,6 +457,115 @@ pub fn exec(
   }
 }
 
+/// A request to the TypeScript language service.  Positions are zero based
+/// lines and UTF-16 characters, like they are used by the Language Server
+/// Protocol.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "method", rename_all = "camelCase")]
+pub enum LanguageServiceRequest {
+  #[serde(rename_all = "camelCase")]
+  GetQuickInfo {
+    specifier: String,
+    line: u64,
+    character: u64,
+  },
+  #[serde(rename_all = "camelCase")]
+  GetCompletions {
+    specifier: String,
+    line: u64,
+    character: u64,
+  },
+  #[serde(rename_all = "camelCase")]
+  GetDefinition {
+    specifier: String,
+    line: u64,
+    character: u64,
+  },
+}
+
+impl LanguageServiceRequest {
+  fn specifier_mut(&mut self) -> &mut String {
+    match self {
+      LanguageServiceRequest::GetQuickInfo { specifier, .. } => specifier,
+      LanguageServiceRequest::GetCompletions { specifier, .. } => specifier,
+      LanguageServiceRequest::GetDefinition { specifier, .. } => specifier,
+    }
+  }
+}
+
+/// A TypeScript language service which is kept alive between requests, so
+/// that unchanged modules don't need to be parsed and checked again.  It is
+/// used by the language server to provide hover information, completions
+/// and definitions.
+pub struct LanguageService(JsRuntime);
+
+impl LanguageService {
+  pub fn new(snapshot: Snapshot) -> Result<Self, AnyError> {
+    let mut runtime = JsRuntime::new(RuntimeOptions {
+      startup_snapshot: Some(snapshot),
+      ..Default::default()
+    });
+    register_ops(&mut runtime);
+    runtime
+      .execute("[native code]", "globalThis.startup({ legacyFlag: false })")
+      .context("Could not properly start the compiler runtime.")?;
+    Ok(LanguageService(runtime))
+  }
+
+  /// Execute a language service request for the program represented by the
+  /// supplied graph, returning the raw response of the language service.
+  pub fn request(
+    &mut self,
+    config: TsConfig,
+    debug: bool,
+    graph: Graph,
+    mut request: LanguageServiceRequest,
+  ) -> Result<Value, AnyError> {
+    let (root_names, root_map) = remap_root_names(&graph.get_root_names());
+    // The root module of the request might have been remapped, in which case
+    // the language service only knows it by its remapped name.
+    let specifier = request.specifier_mut();
+    if let Some((remapped, _)) = root_map
+      .iter()
+      .find(|(_, s)| s.as_str() == specifier.as_str())
+    {
+      *specifier = remapped.clone();
+    }
+    let hash_data = vec![
+      config.as_bytes(),
+      crate::version::DENO.as_bytes().to_owned(),
+    ];
+
+    {
+      let op_state = self.0.op_state();
+      let mut op_state = op_state.borrow_mut();
+      op_state.put(State::new(
+        Rc::new(RefCell::new(graph)),
+        hash_data,
+        None,
+        root_map,
+      ));
+    }
+
+    let request_value = json!({
+      "config": config,
+      "debug": debug,
+      "rootNames": root_names,
+      "request": request,
+    });
+    let request_source = format!("globalThis.serverRequest({})", request_value);
+    self.0.execute("[native_code]", &request_source)?;
+
+    let op_state = self.0.op_state();
+    let mut op_state = op_state.borrow_mut();
+    let state = op_state.take::<State>();
+
+    state.maybe_server_response.ok_or_else(|| {
+      anyhow!("The response for the language service request was not set.")
+    })
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -672,6 +838,26 @@ mod tests {
     assert_eq!(actual, json!([["deno:///none.d.ts", ".d.ts"]]));
   }
 
+  #[test]
+  fn test_remap_root_names() {
+    let data_url = ModuleSpecifier::resolve_url_or_path(
+      "data:application/typescript,export const a: string = \"a\";",
+    )
+    .unwrap();
+    let file_url =
+      ModuleSpecifier::resolve_url_or_path("file:///a/main").unwrap();
+    let (root_names, root_map) = remap_root_names(&[
+      (data_url.clone(), MediaType::TypeScript),
+      (file_url.clone(), MediaType::TypeScript),
+    ]);
+    assert_eq!(root_names.len(), 2);
+    assert!(root_names[0].starts_with("data:///"));
+    assert!(root_names[0].ends_with(".ts"));
+    assert_eq!(root_names[1], "file:///a/main.ts");
+    assert_eq!(root_map.get(&root_names[0]), Some(&data_url));
+    assert_eq!(root_map.get(&root_names[1]), Some(&file_url));
+  }
+
   #[tokio::test]
   async fn test_respond() {
     let mut state = setup(None, None, None).await;
@@ -335,6 +335,164 @@ delete Object.prototype.__proto__;
     debug("<<< exec stop");
   }
 
+  /** The root names and compiler options of the most recent language service
+   * request. The language service host reads them when the language service
+   * asks for the "project" it should work on. */
+  /** @type {string[]} */
+  let serverRootNames = [];
+  /** @type {ts.CompilerOptions} */
+  let serverCompilerOptions = {};
+
+  /** The language service host, which provides the same bindings to the Deno
+   * environment as the compiler host, but for the language service that is
+   * used by `deno lsp`. Sources are versioned by their hash, which allows the
+   * language service to only re-parse the modules that changed between
+   * requests.
+   *
+   * @type {ts.LanguageServiceHost} */
+  const languageServiceHost = {
+    getCompilationSettings() {
+      debug("languageServiceHost.getCompilationSettings()");
+      return serverCompilerOptions;
+    },
+    getScriptFileNames() {
+      debug("languageServiceHost.getScriptFileNames()");
+      return serverRootNames;
+    },
+    getScriptVersion(specifier) {
+      debug(`languageServiceHost.getScriptVersion("${specifier}")`);
+      const { hash } = core.jsonOpSync("op_load", { specifier });
+      return hash ?? "1";
+    },
+    getScriptKind(specifier) {
+      debug(`languageServiceHost.getScriptKind("${specifier}")`);
+      return core.jsonOpSync("op_load", { specifier }).scriptKind;
+    },
+    getScriptSnapshot(specifier) {
+      debug(`languageServiceHost.getScriptSnapshot("${specifier}")`);
+      const { data } = core.jsonOpSync("op_load", { specifier });
+      return data != null ? ts.ScriptSnapshot.fromString(data) : undefined;
+    },
+    getCurrentDirectory: host.getCurrentDirectory,
+    getDefaultLibFileName: host.getDefaultLibFileName,
+    fileExists: host.fileExists,
+    readFile: host.readFile,
+    resolveModuleNames: host.resolveModuleNames,
+    useCaseSensitiveFileNames: host.useCaseSensitiveFileNames,
+  };
+
+  /** @type {ts.LanguageService | undefined} */
+  let languageService;
+
+  /** @param {string} fileName
+   * @param {ts.TextSpan} textSpan */
+  function fromTextSpan(fileName, { start, length }) {
+    const sourceFile = languageService.getProgram().getSourceFile(fileName);
+    return {
+      fileName,
+      start: sourceFile.getLineAndCharacterOfPosition(start),
+      end: sourceFile.getLineAndCharacterOfPosition(start + length),
+    };
+  }
+
+  /**
+   * @typedef {object} LanguageServiceRequest
+   * @property {"getQuickInfo" | "getCompletions" | "getDefinition"} method
+   * @property {string} specifier
+   * @property {number} line
+   * @property {number} character
+   */
+
+  /** @param {LanguageServiceRequest} request */
+  function languageServiceRequest({ method, specifier, line, character }) {
+    const sourceFile = languageService.getProgram().getSourceFile(specifier);
+    if (!sourceFile) {
+      return null;
+    }
+    const position = sourceFile.getPositionOfLineAndCharacter(
+      line,
+      character,
+    );
+    switch (method) {
+      case "getQuickInfo": {
+        const info = languageService.getQuickInfoAtPosition(
+          specifier,
+          position,
+        );
+        if (!info) {
+          return null;
+        }
+        return {
+          displayString: ts.displayPartsToString(info.displayParts),
+          documentation: ts.displayPartsToString(info.documentation),
+          ...fromTextSpan(specifier, info.textSpan),
+        };
+      }
+      case "getCompletions": {
+        const completions = languageService.getCompletionsAtPosition(
+          specifier,
+          position,
+          {},
+        );
+        if (!completions) {
+          return [];
+        }
+        return completions.entries.map(({ name, kind, sortText }) => ({
+          name,
+          kind,
+          sortText,
+        }));
+      }
+      case "getDefinition": {
+        const definitions = languageService.getDefinitionAtPosition(
+          specifier,
+          position,
+        );
+        if (!definitions) {
+          return [];
+        }
+        return definitions.map(({ fileName, textSpan }) =>
+          fromTextSpan(fileName, textSpan)
+        );
+      }
+      default:
+        throw new TypeError(`Invalid language service method: "${method}"`);
+    }
+  }
+
+  /**
+   * @typedef {object} ServerRequest
+   * @property {Record<string, any>} config
+   * @property {boolean} debug
+   * @property {string[]} rootNames
+   * @property {LanguageServiceRequest} request
+   */
+
+  /** The API that is called by Rust when the language server needs
+   * information from the language service.
+   * @param {ServerRequest} serverRequest
+   */
+  function serverRequest({ config, debug: debugFlag, rootNames, request }) {
+    setLogDebug(debugFlag, "TSLS");
+    debug(">>> server request start", { rootNames, request });
+
+    const { options } = ts.convertCompilerOptionsFromJson(
+      config,
+      "",
+      "tsconfig.json",
+    );
+    serverCompilerOptions = options;
+    serverRootNames = rootNames;
+    if (!languageService) {
+      languageService = ts.createLanguageService(languageServiceHost);
+    }
+
+    core.jsonOpSync("op_server_respond", {
+      data: languageServiceRequest(request),
+    });
+    debug("<<< server request stop");
+  }
+
   let hasStarted = false;
 
   /** Startup the runtime environment, setting various flags.
@@ -386,4 +544,7 @@ delete Object.prototype.__proto__;
   // checking TypeScript.
   globalThis.startup = startup;
   globalThis.exec = exec;
+  // exposes the function that is called by `tsc::LanguageService` when the
+  // language server requests information about a program.
+  globalThis.serverRequest = serverRequest;
 })(this);
@@ -12,6 +12,7 @@ use crate::AnyError;
 use deno_core::error::custom_error;
 use deno_core::futures::FutureExt;
 use deno_core::url::Url;
+use deno_fetch::proxy::ProxyConfig;
 use deno_fetch::reqwest;
 use deno_fetch::reqwest::redirect::Policy;
 use deno_fetch::reqwest::Client;
@@ -60,7 +61,8 @@ pub async fn upgrade_command(
   output: Option<PathBuf>,
   ca_file: Option<String>,
 ) -> Result<(), AnyError> {
-  let mut client_builder = Client::builder().redirect(Policy::none());
+  let client_builder = Client::builder().redirect(Policy::none());
+  let mut client_builder = ProxyConfig::from_env()?.apply(client_builder);
 
   // If we have been provided a CA Certificate, add it into the HTTP client
   if let Some(ca_file) = ca_file {
@@ -139,7 +141,7 @@ fn download_package(
   let url = url.clone();
   let version = version.clone();
   let fut = async move {
-    match fetch_once(client.clone(), &url, None).await {
+    match fetch_once(client.clone(), &url, None, None, None).await {
       Ok(result) => {
         println!(
           "Version has been found\nDeno is upgrading to version {}",
@@ -147,7 +149,7 @@ fn download_package(
         );
         match result {
           FetchOnceResult::Code(source, _) => Ok(source),
-          FetchOnceResult::NotModified => unreachable!(),
+          FetchOnceResult::NotModified(_) => unreachable!(),
           FetchOnceResult::Redirect(_url, _) => {
             download_package(&_url, client, &version).await
           }
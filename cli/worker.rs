@@ -21,6 +21,7 @@ use deno_core::url::Url;
 use deno_core::v8;
 use deno_core::JsRuntime;
 use deno_core::ModuleId;
+use deno_core::ModuleLoader;
 use deno_core::ModuleSpecifier;
 use deno_core::RuntimeOptions;
 use deno_core::Snapshot;
@@ -112,7 +113,7 @@ impl Worker {
     name: String,
     startup_snapshot: Snapshot,
     program_state: Arc<ProgramState>,
-    module_loader: Rc<CliModuleLoader>,
+    module_loader: Rc<dyn ModuleLoader>,
     is_main: bool,
   ) -> Self {
     let global_state_ = program_state.clone();
@@ -137,7 +138,9 @@ impl Worker {
           &mut js_runtime,
           Some(inspector_server.clone()),
         ))
-      } else if program_state.flags.coverage || program_state.flags.repl {
+      } else if program_state.flags.coverage_dir.is_some()
+        || program_state.flags.repl
+      {
         Some(DenoInspector::new(&mut js_runtime, None))
       } else {
         None
@@ -256,11 +259,22 @@ impl MainWorker {
     permissions: Permissions,
   ) -> Self {
     let loader = CliModuleLoader::new(program_state.maybe_import_map.clone());
+    Self::from_module_loader(program_state, main_module, permissions, loader)
+  }
+
+  /// Same as `MainWorker::new()` but modules are loaded through the provided
+  /// `module_loader` instead of the `CliModuleLoader`.
+  pub fn from_module_loader(
+    program_state: &Arc<ProgramState>,
+    main_module: ModuleSpecifier,
+    permissions: Permissions,
+    module_loader: Rc<dyn ModuleLoader>,
+  ) -> Self {
     let mut worker = Worker::new(
       "main".to_string(),
       js::deno_isolate_init(),
       program_state.clone(),
-      loader,
+      module_loader,
       true,
     );
     let js_runtime = &mut worker.js_runtime;
@@ -275,7 +289,11 @@ impl MainWorker {
       }
 
       ops::runtime::init(js_runtime, main_module);
-      ops::fetch::init(js_runtime, program_state.flags.ca_file.as_deref());
+      ops::fetch::init(
+        js_runtime,
+        program_state.flags.ca_file.as_deref(),
+        program_state.blob_url_store.clone(),
+      );
       ops::timers::init(js_runtime);
       ops::worker_host::init(js_runtime, None);
       ops::random::init(js_runtime, program_state.flags.seed);
@@ -289,6 +307,7 @@ impl MainWorker {
       ops::errors::init(js_runtime);
       ops::fs_events::init(js_runtime);
       ops::fs::init(js_runtime);
+      ops::http::init(js_runtime);
       ops::io::init(js_runtime);
       ops::net::init(js_runtime);
       ops::os::init(js_runtime);
@@ -297,6 +316,7 @@ impl MainWorker {
       ops::process::init(js_runtime);
       ops::runtime_compiler::init(js_runtime);
       ops::signal::init(js_runtime);
+      ops::testing::init(js_runtime);
       ops::tls::init(js_runtime);
       ops::tty::init(js_runtime);
       ops::websocket::init(js_runtime);
@@ -447,7 +467,11 @@ impl WebWorker {
 
       ops::web_worker::init(js_runtime, sender.clone(), handle);
       ops::runtime::init(js_runtime, main_module);
-      ops::fetch::init(js_runtime, program_state.flags.ca_file.as_deref());
+      ops::fetch::init(
+        js_runtime,
+        program_state.flags.ca_file.as_deref(),
+        program_state.blob_url_store.clone(),
+      );
       ops::timers::init(js_runtime);
       ops::worker_host::init(js_runtime, Some(sender));
       ops::reg_json_sync(js_runtime, "op_close", deno_core::op_close);
@@ -464,6 +488,7 @@ impl WebWorker {
       if has_deno_namespace {
         ops::fs_events::init(js_runtime);
         ops::fs::init(js_runtime);
+        ops::http::init(js_runtime);
         ops::net::init(js_runtime);
         ops::os::init(js_runtime);
         ops::permissions::init(js_runtime);
@@ -601,7 +626,7 @@ mod tests {
       },
       ..Default::default()
     };
-    let permissions = Permissions::from_flags(&flags);
+    let permissions = Permissions::from_flags(&flags).unwrap();
     let program_state =
       ProgramState::mock(vec!["deno".to_string()], Some(flags));
     MainWorker::new(&program_state, main_module, permissions)
@@ -4,7 +4,7 @@
   const core = window.Deno.core;
 
   // provided by "deno_web"
-  const { URLSearchParams } = window.__bootstrap.url;
+  const { URL, URLSearchParams } = window.__bootstrap.url;
 
   const { requiredArguments } = window.__bootstrap.fetchUtil;
   const { ReadableStream, isReadableStreamDisturbed } =
@@ -389,6 +389,27 @@
     }
   }
 
+  function createObjectURL(blob) {
+    requiredArguments("URL.createObjectURL", arguments.length, 1);
+    if (!(blob instanceof Blob)) {
+      throw new TypeError("Argument 1 is not a Blob.");
+    }
+    return core.jsonOpSync(
+      "op_create_object_url",
+      { type: blob.type ?? "" },
+      blob[bytesSymbol],
+    );
+  }
+
+  function revokeObjectURL(url) {
+    requiredArguments("URL.revokeObjectURL", arguments.length, 1);
+    core.jsonOpSync("op_revoke_object_url", { url: String(url) });
+  }
+
+  // `URL` is provided by "deno_web", which doesn't know about blobs.
+  URL.createObjectURL = createObjectURL;
+  URL.revokeObjectURL = revokeObjectURL;
+
   function parseFormDataValue(value, filename) {
     if (value instanceof DomFile) {
       return new DomFile([value], filename || value.name, {
@@ -24,14 +24,20 @@ use reqwest::Method;
 use reqwest::Response;
 use serde::Deserialize;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::convert::From;
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use uuid::Uuid;
 
 pub use reqwest; // Re-export reqwest
 
+pub mod proxy;
+
 /// Execute this crates' JS source files.
 pub fn init(isolate: &mut JsRuntime) {
   let files = vec![
@@ -79,6 +85,82 @@ impl FetchPermissions for NoFetchPermissions {
   }
 }
 
+/// The contents of a `Blob` which was registered with `URL.createObjectURL()`.
+#[derive(Debug, Clone)]
+pub struct Blob {
+  pub data: Vec<u8>,
+  pub media_type: String,
+}
+
+/// The blobs which are reachable through `blob:` URLs. The store is shared by
+/// all workers of a program, so that they can import each other's blob URLs.
+#[derive(Clone, Default)]
+pub struct BlobUrlStore(Arc<Mutex<HashMap<Url, Blob>>>);
+
+impl BlobUrlStore {
+  pub fn get(&self, url: &Url) -> Option<Blob> {
+    let blob_store = self.0.lock().unwrap();
+    blob_store.get(url).cloned()
+  }
+
+  /// Stores the blob under a new unique `blob:` URL and returns that URL.
+  pub fn insert(&self, blob: Blob) -> Url {
+    let url = Url::parse(&format!("blob:null/{}", Uuid::new_v4())).unwrap();
+    let mut blob_store = self.0.lock().unwrap();
+    blob_store.insert(url.clone(), blob);
+    url
+  }
+
+  pub fn remove(&self, url: &Url) {
+    let mut blob_store = self.0.lock().unwrap();
+    blob_store.remove(url);
+  }
+}
+
+pub fn op_create_object_url(
+  state: &mut OpState,
+  args: Value,
+  zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  #[derive(Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  struct CreateObjectUrlArgs {
+    r#type: String,
+  }
+
+  let args: CreateObjectUrlArgs = serde_json::from_value(args)?;
+  let data = match zero_copy.len() {
+    0 => vec![],
+    1 => Vec::from(&*zero_copy[0]),
+    _ => panic!("Invalid number of arguments"),
+  };
+  let blob = Blob {
+    data,
+    media_type: args.r#type,
+  };
+  let url = state.borrow::<BlobUrlStore>().insert(blob);
+  Ok(json!(url.to_string()))
+}
+
+pub fn op_revoke_object_url(
+  state: &mut OpState,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  #[derive(Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  struct RevokeObjectUrlArgs {
+    url: String,
+  }
+
+  let args: RevokeObjectUrlArgs = serde_json::from_value(args)?;
+  // Like in browsers, revoking a URL which isn't a blob URL is a no-op.
+  if let Ok(url) = Url::parse(&args.url) {
+    state.borrow::<BlobUrlStore>().remove(&url);
+  }
+  Ok(json!({}))
+}
+
 pub fn get_declaration() -> PathBuf {
   PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("lib.deno_fetch.d.ts")
 }
@@ -240,7 +322,7 @@ where
     permissions.check_read(&PathBuf::from(ca_file))?;
   }
 
-  let client = create_http_client(args.ca_file.as_deref()).unwrap();
+  let client = create_http_client(args.ca_file.as_deref())?;
 
   let rid = state
     .resource_table
@@ -251,7 +333,8 @@ where
 /// Create new instance of async reqwest::Client. This client supports
 /// proxies and doesn't follow redirects.
 fn create_http_client(ca_file: Option<&str>) -> Result<Client, AnyError> {
-  let mut builder = Client::builder().redirect(Policy::none()).use_rustls_tls();
+  let builder = Client::builder().redirect(Policy::none()).use_rustls_tls();
+  let mut builder = proxy::ProxyConfig::from_env()?.apply(builder);
   if let Some(ca_file) = ca_file {
     let mut buf = Vec::new();
     File::open(ca_file)?.read_to_end(&mut buf)?;
@@ -41,6 +41,11 @@ const REDIRECT_ABSOLUTE_PORT: u16 = 4550;
 const HTTPS_PORT: u16 = 5545;
 const WS_PORT: u16 = 4242;
 const WSS_PORT: u16 = 4243;
+const PROXY_PORT: u16 = 4561;
+/// The `Last-Modified` date of `last_modified_script.ts`.
+const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+/// The credentials the test proxy accepts, `user:pass` in base64.
+const PROXY_AUTHORIZATION: &str = "Basic dXNlcjpwYXNz";
 
 pub const PERMISSION_VARIANTS: [&str; 5] =
   ["read", "write", "env", "net", "run"];
@@ -311,6 +316,44 @@ pub async fn run_all_servers() {
         Box::new(res)
       }
     });
+  let last_modified_script = warp::path!("last_modified_script.ts")
+    .and(warp::header::optional::<String>("if-modified-since"))
+    .map(|if_modified_since| -> Box<dyn Reply> {
+      if if_modified_since == Some(LAST_MODIFIED.to_string()) {
+        let r =
+          warp::reply::with_status(warp::reply(), StatusCode::NOT_MODIFIED);
+        let r = with_header(r, "Cache-Control", "max-age=3600");
+        Box::new(r)
+      } else {
+        let mut res = Response::new(Body::from("console.log('last-modified')"));
+        let h = res.headers_mut();
+        h.insert(
+          "Content-type",
+          HeaderValue::from_static("application/typescript"),
+        );
+        h.insert("Last-Modified", HeaderValue::from_static(LAST_MODIFIED));
+        h.insert("Cache-Control", HeaderValue::from_static("no-cache"));
+        Box::new(res)
+      }
+    });
+  let auth_script = warp::path!("auth_script.ts")
+    .and(warp::header::optional::<String>("authorization"))
+    .map(|authorization| -> Box<dyn Reply> {
+      if authorization == Some("Bearer abc123".to_string()) {
+        let mut res =
+          Response::new(Body::from("export const authorized = true;\n"));
+        res.headers_mut().insert(
+          "Content-type",
+          HeaderValue::from_static("application/typescript"),
+        );
+        Box::new(res)
+      } else {
+        Box::new(warp::reply::with_status(
+          warp::reply(),
+          StatusCode::NOT_FOUND,
+        ))
+      }
+    });
   let xtypescripttypes = warp::path!("xTypeScriptTypes.js")
     .map(|| {
       let mut res = Response::new(Body::from("export const foo = 'foo';"));
@@ -440,6 +483,8 @@ pub async fn run_all_servers() {
     .and(warp::fs::dir(root_path()))
     .map(custom_headers)
     .or(etag_script)
+    .or(last_modified_script)
+    .or(auth_script)
     .or(xtypescripttypes)
     .or(echo_server)
     .or(echo_multipart_file)
@@ -466,6 +511,7 @@ pub async fn run_all_servers() {
       inf_redirect_server_fut,
       double_redirect_server_fut,
       absolute_redirect_server_fut,
+      run_proxy_server(),
     )
   }
   .boxed();
@@ -481,6 +527,117 @@ pub async fn run_all_servers() {
   .await;
 }
 
+/// A minimal forward proxy which requires basic auth. `CONNECT` requests are
+/// tunneled, other requests must use an absolute `http:` URL and are
+/// forwarded with `Connection: close`, so that each connection carries a
+/// single request.
+async fn run_proxy_server() {
+  let addr = std::net::SocketAddr::from(([127, 0, 0, 1], PROXY_PORT));
+  let mut listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+  loop {
+    let (stream, _) = match listener.accept().await {
+      Ok(conn) => conn,
+      Err(_) => continue,
+    };
+    tokio::spawn(async move {
+      if let Err(e) = proxy_connection(stream).await {
+        println!("proxy server error: {:?}", e);
+      }
+    });
+  }
+}
+
+async fn proxy_connection(
+  mut client: tokio::net::TcpStream,
+) -> std::io::Result<()> {
+  use tokio::io::AsyncReadExt;
+  use tokio::io::AsyncWriteExt;
+  use tokio::net::TcpStream;
+
+  let mut head = Vec::new();
+  let mut byte = [0u8; 1];
+  while !head.ends_with(b"\r\n\r\n") {
+    if client.read(&mut byte).await? == 0 {
+      return Ok(());
+    }
+    head.push(byte[0]);
+  }
+  let head = String::from_utf8_lossy(&head).to_string();
+  let mut lines = head.split("\r\n").filter(|line| !line.is_empty());
+  let request_line = lines.next().unwrap_or_default();
+  let headers: Vec<&str> = lines.collect();
+
+  let is_header = |line: &str, name: &str| {
+    line.len() > name.len()
+      && line[..name.len()].eq_ignore_ascii_case(name)
+      && line[name.len()..].starts_with(':')
+  };
+  let authorized = headers.iter().any(|line| {
+    is_header(line, "proxy-authorization")
+      && line["proxy-authorization:".len()..].trim() == PROXY_AUTHORIZATION
+  });
+  if !authorized {
+    client
+      .write_all(
+        b"HTTP/1.1 407 Proxy Authentication Required\r\n\
+          Proxy-Authenticate: Basic\r\n\
+          Content-Length: 0\r\n\
+          Connection: close\r\n\r\n",
+      )
+      .await?;
+    return Ok(());
+  }
+
+  let parts: Vec<&str> = request_line.split(' ').collect();
+  if parts.len() != 3 {
+    return Ok(());
+  }
+  let (method, target, version) = (parts[0], parts[1], parts[2]);
+  let mut server = if method == "CONNECT" {
+    let server = TcpStream::connect(target).await?;
+    client
+      .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+      .await?;
+    server
+  } else {
+    let rest = match target.strip_prefix("http://") {
+      Some(rest) => rest,
+      None => return Ok(()),
+    };
+    let (authority, path) = match rest.find('/') {
+      Some(slash) => (&rest[..slash], &rest[slash..]),
+      None => (rest, "/"),
+    };
+    let authority = if authority.contains(':') {
+      authority.to_string()
+    } else {
+      format!("{}:80", authority)
+    };
+    let mut server = TcpStream::connect(authority).await?;
+    let mut request = format!("{} {} {}\r\n", method, path, version);
+    for line in headers.iter().filter(|line| {
+      !is_header(line, "proxy-authorization")
+        && !is_header(line, "proxy-connection")
+        && !is_header(line, "connection")
+    }) {
+      request.push_str(line);
+      request.push_str("\r\n");
+    }
+    request.push_str("Connection: close\r\n\r\n");
+    server.write_all(request.as_bytes()).await?;
+    server
+  };
+
+  let (mut client_read, mut client_write) = client.split();
+  let (mut server_read, mut server_write) = server.split();
+  future::select(
+    Box::pin(tokio::io::copy(&mut client_read, &mut server_write)),
+    Box::pin(tokio::io::copy(&mut server_read, &mut client_write)),
+  )
+  .await;
+  Ok(())
+}
+
 fn custom_headers(path: warp::path::Peek, f: warp::fs::File) -> Box<dyn Reply> {
   let p = path.as_str();
 